@@ -0,0 +1,15 @@
+-- 站内消息（收件箱）
+CREATE TABLE IF NOT EXISTS `notify_site_message` (
+    `id`         BIGINT       NOT NULL AUTO_INCREMENT COMMENT '主键',
+    `user_id`    BIGINT       NOT NULL COMMENT '接收用户 ID',
+    `sender`     VARCHAR(128) NOT NULL DEFAULT '' COMMENT '发送者',
+    `title`      VARCHAR(255) NOT NULL DEFAULT '' COMMENT '标题',
+    `content`    TEXT         NOT NULL COMMENT '内容',
+    `is_read`    TINYINT      NOT NULL DEFAULT 0 COMMENT '是否已读：0 未读，1 已读',
+    `read_at`    BIGINT       NULL COMMENT '阅读时间（毫秒时间戳）',
+    `created_at` BIGINT       NOT NULL COMMENT '创建时间（毫秒时间戳）',
+    `updated_at` BIGINT       NOT NULL COMMENT '更新时间（毫秒时间戳）',
+    `is_del`     TINYINT      NOT NULL DEFAULT 0 COMMENT '逻辑删除：0 正常，1 已删除',
+    PRIMARY KEY (`id`),
+    KEY `idx_user_read` (`user_id`, `is_del`, `is_read`, `id`)
+) ENGINE = InnoDB DEFAULT CHARSET = utf8mb4 COMMENT = '站内消息';
@@ -0,0 +1,16 @@
+-- 通知模板
+CREATE TABLE IF NOT EXISTS `notify_template` (
+    `id`               BIGINT       NOT NULL AUTO_INCREMENT COMMENT '主键',
+    `code`             VARCHAR(64)  NOT NULL COMMENT '模板代码',
+    `channel`          VARCHAR(32)  NOT NULL COMMENT '渠道：email、sms、im_feishu、im_dingding、im_wechat、push、site_message',
+    `locale`           VARCHAR(16)  NOT NULL DEFAULT 'zh-CN' COMMENT '语言',
+    `subject_template` VARCHAR(512) NOT NULL DEFAULT '' COMMENT '主题模板',
+    `body_template`    TEXT         NOT NULL COMMENT '正文模板',
+    `version`          INT          NOT NULL DEFAULT 1 COMMENT '版本号（每次修改递增）',
+    `remark`           VARCHAR(255) NOT NULL DEFAULT '' COMMENT '备注',
+    `created_at`       BIGINT       NOT NULL COMMENT '创建时间（毫秒时间戳）',
+    `updated_at`       BIGINT       NOT NULL COMMENT '更新时间（毫秒时间戳）',
+    `is_del`           TINYINT      NOT NULL DEFAULT 0 COMMENT '逻辑删除：0 正常，1 已删除',
+    PRIMARY KEY (`id`),
+    KEY `idx_code_channel_locale` (`code`, `channel`, `locale`, `is_del`)
+) ENGINE = InnoDB DEFAULT CHARSET = utf8mb4 COMMENT = '通知模板';
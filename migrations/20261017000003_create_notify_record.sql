@@ -0,0 +1,37 @@
+-- 通知发送记录
+CREATE TABLE IF NOT EXISTS `notify_record` (
+    `id`              BIGINT       NOT NULL AUTO_INCREMENT COMMENT '主键（通知 ID）',
+    `channel`         VARCHAR(32)  NOT NULL COMMENT '渠道',
+    `subject`         VARCHAR(512) NOT NULL DEFAULT '' COMMENT '主题',
+    `template_code`   VARCHAR(64)  NULL COMMENT '通知模板代码',
+    `status`          VARCHAR(16)  NOT NULL COMMENT '状态：pending、sending、sent、partial、failed',
+    `attempts`        INT          NOT NULL DEFAULT 0 COMMENT '已尝试次数',
+    `recipient_count` INT          NOT NULL DEFAULT 0 COMMENT '接收者数量',
+    `success_count`   INT          NOT NULL DEFAULT 0 COMMENT '发送成功的接收者数量',
+    `error`           TEXT         NULL COMMENT '最近一次失败原因',
+    `payload`         MEDIUMTEXT   NOT NULL COMMENT '通知内容（Notification JSON）',
+    `sent_at`         BIGINT       NULL COMMENT '发送完成时间（毫秒时间戳）',
+    `created_at`      BIGINT       NOT NULL COMMENT '创建时间（毫秒时间戳）',
+    `updated_at`      BIGINT       NOT NULL COMMENT '更新时间（毫秒时间戳）',
+    `is_del`          TINYINT      NOT NULL DEFAULT 0 COMMENT '逻辑删除：0 正常，1 已删除',
+    PRIMARY KEY (`id`),
+    KEY `idx_channel_status` (`channel`, `status`, `id`),
+    KEY `idx_created_at` (`created_at`)
+) ENGINE = InnoDB DEFAULT CHARSET = utf8mb4 COMMENT = '通知发送记录';
+
+-- 通知接收者发送记录
+CREATE TABLE IF NOT EXISTS `notify_record_recipient` (
+    `id`                  BIGINT       NOT NULL AUTO_INCREMENT COMMENT '主键',
+    `record_id`           BIGINT       NOT NULL COMMENT '所属通知 ID',
+    `channel`             VARCHAR(32)  NOT NULL COMMENT '渠道',
+    `recipient`           VARCHAR(512) NOT NULL DEFAULT '' COMMENT '接收者',
+    `status`              VARCHAR(16)  NOT NULL COMMENT '状态：pending、sending、sent、failed',
+    `provider_message_id` VARCHAR(255) NULL COMMENT '服务商消息 ID',
+    `error`               TEXT         NULL COMMENT '失败原因',
+    `created_at`          BIGINT       NOT NULL COMMENT '创建时间（毫秒时间戳）',
+    `updated_at`          BIGINT       NOT NULL COMMENT '更新时间（毫秒时间戳）',
+    `is_del`              TINYINT      NOT NULL DEFAULT 0 COMMENT '逻辑删除：0 正常，1 已删除',
+    PRIMARY KEY (`id`),
+    KEY `idx_record` (`record_id`),
+    KEY `idx_recipient` (`recipient`(64), `id`)
+) ENGINE = InnoDB DEFAULT CHARSET = utf8mb4 COMMENT = '通知接收者发送记录';
@@ -0,0 +1,19 @@
+-- 死信（重试耗尽或不可重试而发送失败的 Kafka 消息）
+CREATE TABLE IF NOT EXISTS `notify_dead_letter` (
+    `id`              BIGINT       NOT NULL AUTO_INCREMENT COMMENT '主键',
+    `topic`           VARCHAR(255) NOT NULL COMMENT '原消息 topic',
+    `source`          VARCHAR(255) NOT NULL DEFAULT '' COMMENT '原消息来源',
+    `payload`         MEDIUMTEXT   NOT NULL COMMENT '原消息数据（JSON）',
+    `notification_id` BIGINT       NULL COMMENT '关联的通知 ID',
+    `error`           TEXT         NOT NULL COMMENT '失败原因',
+    `retryable`       TINYINT      NOT NULL DEFAULT 0 COMMENT '失败是否可重试：0 否，1 是',
+    `attempts`        INT          NOT NULL DEFAULT 0 COMMENT '已尝试次数',
+    `status`          VARCHAR(16)  NOT NULL DEFAULT 'pending' COMMENT '状态：pending、replayed',
+    `replay_count`    INT          NOT NULL DEFAULT 0 COMMENT '重放次数',
+    `replayed_at`     BIGINT       NULL COMMENT '最近重放时间（毫秒时间戳）',
+    `created_at`      BIGINT       NOT NULL COMMENT '创建时间（毫秒时间戳）',
+    `updated_at`      BIGINT       NOT NULL COMMENT '更新时间（毫秒时间戳）',
+    `is_del`          TINYINT      NOT NULL DEFAULT 0 COMMENT '逻辑删除：0 正常，1 已删除',
+    PRIMARY KEY (`id`),
+    KEY `idx_status` (`status`, `id`)
+) ENGINE = InnoDB DEFAULT CHARSET = utf8mb4 COMMENT = '死信';
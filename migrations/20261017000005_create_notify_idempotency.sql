@@ -0,0 +1,14 @@
+-- 幂等键（同一幂等键在去重窗口内重复请求时返回首次发送结果）
+CREATE TABLE IF NOT EXISTS `notify_idempotency` (
+    `id`              BIGINT       NOT NULL AUTO_INCREMENT COMMENT '主键',
+    `idempotency_key` VARCHAR(191) NOT NULL COMMENT '幂等键（带租户前缀）',
+    `status`          VARCHAR(16)  NOT NULL DEFAULT 'processing' COMMENT '状态：processing、completed',
+    `notification_id` BIGINT       NULL COMMENT '关联的通知 ID',
+    `result`          MEDIUMTEXT   NULL COMMENT '首次发送结果（JSON）',
+    `expires_at`      BIGINT       NOT NULL COMMENT '过期时间（毫秒时间戳），过期后可重新发送',
+    `created_at`      BIGINT       NOT NULL COMMENT '创建时间（毫秒时间戳）',
+    `updated_at`      BIGINT       NOT NULL COMMENT '更新时间（毫秒时间戳）',
+    `is_del`          TINYINT      NOT NULL DEFAULT 0 COMMENT '逻辑删除：0 正常，1 已删除',
+    PRIMARY KEY (`id`),
+    UNIQUE KEY `uk_idempotency_key` (`idempotency_key`)
+) ENGINE = InnoDB DEFAULT CHARSET = utf8mb4 COMMENT = '幂等键';
@@ -0,0 +1,5 @@
+-- 定时发送：计划发送时间
+ALTER TABLE `notify_record`
+    ADD COLUMN `scheduled_at` BIGINT NULL COMMENT '计划发送时间（毫秒时间戳），定时发送时有值' AFTER `payload`,
+    MODIFY COLUMN `status` VARCHAR(16) NOT NULL COMMENT '状态：pending、scheduled、sending、sent、partial、failed、cancelled',
+    ADD KEY `idx_status_scheduled_at` (`status`, `scheduled_at`);
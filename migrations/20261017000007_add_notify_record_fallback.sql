@@ -0,0 +1,5 @@
+-- 渠道降级：关联降级链中的上一步通知
+ALTER TABLE `notify_record`
+    ADD COLUMN `parent_id`     BIGINT NULL COMMENT '降级链中上一步的通知 ID' AFTER `scheduled_at`,
+    ADD COLUMN `fallback_step` INT    NULL COMMENT '第几个降级步骤（从 1 开始），首次发送为空' AFTER `parent_id`,
+    ADD KEY `idx_parent_id` (`parent_id`);
@@ -0,0 +1,4 @@
+-- 定时发送租约：调度器取出通知的时间，租约过期仍在发送中的通知（实例崩溃）重新取出
+ALTER TABLE `notify_record`
+    ADD COLUMN `claimed_at` BIGINT NULL COMMENT '调度器取出时间（毫秒时间戳），每次发送尝试续期' AFTER `scheduled_at`,
+    ADD KEY `idx_status_claimed_at` (`status`, `claimed_at`);
@@ -0,0 +1,6 @@
+-- 通知模板唯一约束：同一代码、渠道、语言只能有一个未删除的模板
+-- 已删除的模板 active 为 NULL，不参与唯一约束（可重复删除、重新创建）
+ALTER TABLE `notify_template`
+    ADD COLUMN `active` TINYINT AS (IF(`is_del` = 0, 1, NULL)) VIRTUAL COMMENT '未删除为 1，已删除为 NULL' AFTER `is_del`,
+    DROP KEY `idx_code_channel_locale`,
+    ADD UNIQUE KEY `uk_code_channel_locale` (`code`, `channel`, `locale`, `active`);
@@ -1,7 +1,7 @@
 use crate::adapters::Sender;
 use crate::config::DingdingConfig;
 use crate::error::{NotifyError, NotifyResult};
-use crate::models::{DingdingMessageType, Notification};
+use crate::models::{ChannelType, DingdingMessageType, Notification, SendReceipt};
 use async_trait::async_trait;
 use base64::Engine;
 use hmac::{Hmac, Mac};
@@ -58,7 +58,11 @@ where
 
 #[async_trait]
 impl Sender for DingdingSender {
-    async fn send(&self, notification: &Notification) -> NotifyResult<()> {
+    fn channel(&self) -> ChannelType {
+        ChannelType::ImDingding
+    }
+
+    async fn send(&self, notification: &Notification) -> NotifyResult<SendReceipt> {
         let mut url = self.config.webhook.clone();
 
         if let Some(secret) = &self.config.secret {
@@ -149,12 +153,17 @@ impl Sender for DingdingSender {
         );
 
         if !status.is_success() {
-            return Err(NotifyError::Send(format!(
-                "钉钉API错误 {}: {}",
-                status, response_text
-            )));
+            let message = format!("钉钉API错误 {}: {}", status, response_text);
+            // 4xx（限流除外）为请求不合法，重试也不会成功
+            return Err(
+                if status.is_client_error() && status != reqwest::StatusCode::TOO_MANY_REQUESTS {
+                    NotifyError::Rejected(message)
+                } else {
+                    NotifyError::Send(message)
+                },
+            );
         }
 
-        Ok(())
+        Ok(SendReceipt::default())
     }
 }
@@ -1,15 +1,60 @@
 use crate::adapters::Sender;
-use crate::config::EmailConfig;
-use crate::error::NotifyResult;
-use crate::models::Notification;
+use crate::config::{DkimAlgorithm, DkimCanonicalization, DkimConfig, EmailConfig, SmtpSecurity};
+use crate::error::{NotifyError, NotifyResult};
+use crate::models::{
+    validate_attachments, ChannelType, EmailAttachment, Notification, RecipientResult, SendReceipt,
+};
 use async_trait::async_trait;
-use lettre::message::{Mailbox, Message};
-use lettre::transport::smtp::AsyncSmtpTransport;
+use base64::Engine;
+use lettre::message::dkim;
+use lettre::message::header::{ContentType, HeaderName};
+use lettre::message::{Attachment, Mailbox, Message, MultiPart, MultiPartBuilder, SinglePart};
+use lettre::transport::smtp::authentication::Credentials;
+use lettre::transport::smtp::client::{Certificate, Tls, TlsParameters};
+use lettre::transport::smtp::{AsyncSmtpTransport, PoolConfig};
 use lettre::AsyncTransport;
+use reqwest::dns::{Addrs, Name, Resolve, Resolving};
+use reqwest::redirect::Policy;
+use reqwest::{Client, Url};
+use std::net::{IpAddr, SocketAddr};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// 下载附件的超时时间
+const ATTACHMENT_DOWNLOAD_TIMEOUT: Duration = Duration::from_secs(30);
 
 /// 邮件发送适配器
+///
+/// 邮件结构按内容组合：multipart/mixed（附件）⊃ multipart/related（内联图片）
+/// ⊃ multipart/alternative（text/plain + text/html），只有一种正文时为单个部分
 pub struct EmailSender {
     mailer: AsyncSmtpTransport<lettre::Tokio1Executor>,
+    /// 默认发件人（通知未指定 from 时使用）
+    default_from: String,
+    /// 下载 URL 附件
+    client: Client,
+    /// 单个附件最大字节数
+    max_attachment_size: usize,
+    /// 允许下载附件的主机（为空时允许任意公网主机）
+    attachment_hosts: Vec<String>,
+    /// DKIM 签名（未配置时不签名）
+    dkim: Option<dkim::DkimConfig>,
+}
+
+/// 已组装的邮件正文
+enum Body {
+    Single(SinglePart),
+    Multi(MultiPart),
+}
+
+impl Body {
+    /// 作为新 multipart 的第一部分
+    fn wrap(self, builder: MultiPartBuilder) -> MultiPart {
+        match self {
+            Body::Single(part) => builder.singlepart(part),
+            Body::Multi(part) => builder.multipart(part),
+        }
+    }
 }
 
 impl EmailSender {
@@ -17,30 +62,480 @@ impl EmailSender {
     ///
     /// # 参数
     /// - `config`: 邮件配置
-    pub fn new(config: &EmailConfig) -> Self {
-        let mailer = AsyncSmtpTransport::<lettre::Tokio1Executor>::relay(&config.smtp_server)
-            .expect("Failed to create SMTP transport")
-            .port(config.smtp_port)
-            .credentials(lettre::transport::smtp::authentication::Credentials::new(
+    ///
+    /// # 返回
+    /// - `Err(NotifyError::Config)`: 默认发件人为空或不是合法邮箱地址、CA 证书无法读取或解析、
+    ///   TLS 参数无效、DKIM 私钥无效
+    pub fn new(config: &EmailConfig) -> NotifyResult<Self> {
+        // 未配置 from 时使用 SMTP 用户名，通知未指定发件人时使用
+        let default_from = config
+            .from
+            .clone()
+            .unwrap_or_else(|| config.smtp_user.clone());
+        if let Err(e) = default_from.parse::<Mailbox>() {
+            return Err(NotifyError::Config(format!(
+                "邮件默认发件人无效 (from 或 smtp_user): {:?}: {}",
+                default_from, e
+            )));
+        }
+
+        let tls = match config.security {
+            SmtpSecurity::None => Tls::None,
+            SmtpSecurity::Starttls => Tls::Required(tls_parameters(config)?),
+            SmtpSecurity::Tls => Tls::Wrapper(tls_parameters(config)?),
+        };
+        let pool = PoolConfig::new()
+            .max_size(config.pool.max_size.max(1))
+            .min_idle(config.pool.min_idle)
+            .idle_timeout(Duration::from_millis(config.pool.idle_timeout_ms));
+        let mut builder =
+            AsyncSmtpTransport::<lettre::Tokio1Executor>::builder_dangerous(&config.smtp_server)
+                .port(
+                    config
+                        .smtp_port
+                        .unwrap_or_else(|| config.security.default_port()),
+                )
+                .tls(tls)
+                .timeout(Some(Duration::from_millis(config.timeout_ms)))
+                .pool_config(pool);
+        if !config.smtp_user.is_empty() {
+            builder = builder.credentials(Credentials::new(
                 config.smtp_user.clone(),
                 config.smtp_pass.clone(),
+            ));
+        }
+
+        let attachment_hosts: Vec<String> = config
+            .attachment_hosts
+            .split(',')
+            .map(|host| host.trim().trim_start_matches("*.").to_ascii_lowercase())
+            .filter(|host| !host.is_empty())
+            .collect();
+        // 不跟随重定向（重定向目标可绕过主机校验）；未配置允许主机时解析结果只能是公网地址
+        let mut client = Client::builder()
+            .timeout(ATTACHMENT_DOWNLOAD_TIMEOUT)
+            .redirect(Policy::none());
+        if attachment_hosts.is_empty() {
+            client = client.dns_resolver(Arc::new(PublicResolver));
+        }
+        let client = client
+            .build()
+            .map_err(|e| NotifyError::Config(format!("创建附件下载 HTTP 客户端失败: {}", e)))?;
+
+        Ok(Self {
+            mailer: builder.build(),
+            default_from,
+            client,
+            max_attachment_size: config.max_attachment_size,
+            attachment_hosts,
+            dkim: config.dkim.as_ref().map(dkim_config).transpose()?,
+        })
+    }
+
+    /// 组装邮件正文：text/html 备选部分、内联图片、附件
+    async fn body(&self, notification: &Notification) -> NotifyResult<Body> {
+        let html = notification.html_body();
+        // 未单独提供 html 且 body 为 HTML 时，body 不再作为纯文本部分
+        let text = (html.is_none() || notification.html.is_some())
+            .then_some(notification.body.as_str())
+            .filter(|text| !text.is_empty() || html.is_none());
+        let mut body = match (text, html) {
+            (Some(text), Some(html)) => Body::Multi(MultiPart::alternative_plain_html(
+                text.to_string(),
+                html.to_string(),
+            )),
+            (None, Some(html)) => Body::Single(SinglePart::html(html.to_string())),
+            (text, None) => Body::Single(SinglePart::plain(text.unwrap_or_default().to_string())),
+        };
+
+        let (inline, attached): (Vec<_>, Vec<_>) =
+            notification.attachments.iter().partition(|a| a.is_inline());
+        if !inline.is_empty() {
+            let mut related = body.wrap(MultiPart::related());
+            for attachment in inline {
+                let (content, content_type) = self.load(attachment).await?;
+                let content_id = attachment.content_id.clone().unwrap_or_default();
+                related = related
+                    .singlepart(Attachment::new_inline(content_id).body(content, content_type));
+            }
+            body = Body::Multi(related);
+        }
+        if !attached.is_empty() {
+            let mut mixed = body.wrap(MultiPart::mixed());
+            for attachment in attached {
+                let (content, content_type) = self.load(attachment).await?;
+                mixed = mixed.singlepart(
+                    Attachment::new(attachment.filename.clone()).body(content, content_type),
+                );
+            }
+            body = Body::Multi(mixed);
+        }
+        Ok(body)
+    }
+
+    /// 读取附件内容：解码 Base64 内容或下载 URL
+    async fn load(&self, attachment: &EmailAttachment) -> NotifyResult<(Vec<u8>, ContentType)> {
+        let mut mime_type = attachment.mime_type().to_string();
+        let content = match (&attachment.content, &attachment.url) {
+            (Some(content), _) => base64::engine::general_purpose::STANDARD
+                .decode(content)
+                .map_err(|e| {
+                    NotifyError::InvalidMessage(format!(
+                        "附件 {} 内容不是有效的 Base64: {}",
+                        attachment.filename, e
+                    ))
+                })?,
+            (None, Some(url)) => {
+                let url = self.check_url(attachment, url).await?;
+                let response = self.client.get(url).send().await?;
+                if response.status().is_redirection() {
+                    return Err(NotifyError::InvalidMessage(format!(
+                        "附件 {} 下载地址返回重定向，不允许跟随",
+                        attachment.filename
+                    )));
+                }
+                let mut response = response.error_for_status()?;
+                if response
+                    .content_length()
+                    .is_some_and(|len| len as usize > self.max_attachment_size)
+                {
+                    return Err(self.too_large(attachment));
+                }
+                // 未指定类型且无法按扩展名推断时使用下载响应的类型
+                if attachment.content_type.is_none() && mime_type == "application/octet-stream" {
+                    if let Some(header) = response
+                        .headers()
+                        .get(reqwest::header::CONTENT_TYPE)
+                        .and_then(|v| v.to_str().ok())
+                    {
+                        mime_type = header.to_string();
+                    }
+                }
+                // 边下载边累计大小，超过上限立即中止（响应可能没有 Content-Length）
+                let mut content = Vec::new();
+                while let Some(chunk) = response.chunk().await? {
+                    if content.len() + chunk.len() > self.max_attachment_size {
+                        return Err(self.too_large(attachment));
+                    }
+                    content.extend_from_slice(&chunk);
+                }
+                content
+            }
+            (None, None) => {
+                return Err(NotifyError::InvalidMessage(format!(
+                    "附件 {} 缺少 content 或 url",
+                    attachment.filename
+                )))
+            }
+        };
+        if content.len() > self.max_attachment_size {
+            return Err(self.too_large(attachment));
+        }
+        let content_type = ContentType::parse(&mime_type).map_err(|e| {
+            NotifyError::InvalidMessage(format!(
+                "附件 {} 的 content_type 不合法: {}",
+                attachment.filename, e
+            ))
+        })?;
+        Ok((content, content_type))
+    }
+
+    /// 校验附件下载地址：配置了允许主机时只能下载这些主机，否则主机只能解析到公网地址
+    async fn check_url(&self, attachment: &EmailAttachment, url: &str) -> NotifyResult<Url> {
+        let rejected = |reason: &str| {
+            NotifyError::InvalidMessage(format!(
+                "附件 {} 下载地址不允许: {}",
+                attachment.filename, reason
             ))
-            .build();
+        };
+        let url = Url::parse(url).map_err(|e| rejected(&e.to_string()))?;
+        let host = match url.host_str() {
+            Some(host) => host.trim_start_matches('[').trim_end_matches(']'),
+            None => return Err(rejected("missing host")),
+        };
+        if !self.attachment_hosts.is_empty() {
+            let host = host.to_ascii_lowercase();
+            let allowed = self.attachment_hosts.iter().any(|allowed| {
+                host == *allowed
+                    || host
+                        .strip_suffix(allowed.as_str())
+                        .is_some_and(|prefix| prefix.ends_with('.'))
+            });
+            return if allowed {
+                Ok(url)
+            } else {
+                Err(rejected("host is not in attachment_hosts"))
+            };
+        }
+        let port = url.port_or_known_default().unwrap_or(80);
+        let addrs: Vec<SocketAddr> = match host.parse::<IpAddr>() {
+            Ok(ip) => vec![SocketAddr::new(ip, port)],
+            Err(_) => tokio::net::lookup_host((host, port))
+                .await
+                .map_err(|e| rejected(&e.to_string()))?
+                .collect(),
+        };
+        if let Some(addr) = addrs.iter().find(|addr| !is_public(addr.ip())) {
+            return Err(rejected(&format!("{} is not a public address", addr.ip())));
+        }
+        Ok(url)
+    }
+
+    fn too_large(&self, attachment: &EmailAttachment) -> NotifyError {
+        NotifyError::InvalidMessage(format!(
+            "附件 {} 超过大小上限 {} 字节",
+            attachment.filename, self.max_attachment_size
+        ))
+    }
+}
+
+/// 附件下载的 DNS 解析：拒绝解析到非公网地址的主机（防止 DNS 重绑定绕过下载前的校验）
+struct PublicResolver;
 
-        Self { mailer }
+impl Resolve for PublicResolver {
+    fn resolve(&self, name: Name) -> Resolving {
+        Box::pin(async move {
+            let addrs: Vec<SocketAddr> =
+                tokio::net::lookup_host((name.as_str(), 0)).await?.collect();
+            if let Some(addr) = addrs.iter().find(|addr| !is_public(addr.ip())) {
+                return Err(format!(
+                    "{} resolves to non-public address {}",
+                    name.as_str(),
+                    addr.ip()
+                )
+                .into());
+            }
+            Ok(Box::new(addrs.into_iter()) as Addrs)
+        })
     }
 }
 
+/// 是否为公网地址（排除回环、内网、链路本地、运营商级 NAT、未指定、广播、文档及组播地址）
+fn is_public(ip: IpAddr) -> bool {
+    match ip.to_canonical() {
+        IpAddr::V4(ip) => {
+            let [a, b, ..] = ip.octets();
+            !(ip.is_loopback()
+                || ip.is_private()
+                || ip.is_link_local()
+                || ip.is_unspecified()
+                || ip.is_broadcast()
+                || ip.is_documentation()
+                || ip.is_multicast()
+                || a == 0
+                || (a == 100 && (64..128).contains(&b)))
+        }
+        IpAddr::V6(ip) => {
+            let first = ip.segments()[0];
+            !(ip.is_loopback()
+                || ip.is_unspecified()
+                || ip.is_multicast()
+                || (first & 0xfe00) == 0xfc00
+                || (first & 0xffc0) == 0xfe80)
+        }
+    }
+}
+
+/// TLS 参数：服务器证书按 SMTP 服务器地址校验，可追加自定义 CA 或接受无效证书
+fn tls_parameters(config: &EmailConfig) -> NotifyResult<TlsParameters> {
+    let mut builder = TlsParameters::builder(config.smtp_server.clone())
+        .dangerous_accept_invalid_certs(config.accept_invalid_certs);
+    if let Some(path) = &config.ca_cert_path {
+        let pem = std::fs::read(path)
+            .map_err(|e| NotifyError::Config(format!("读取 SMTP CA 证书失败 {}: {}", path, e)))?;
+        let certificate = Certificate::from_pem(&pem)
+            .map_err(|e| NotifyError::Config(format!("SMTP CA 证书无效 {}: {}", path, e)))?;
+        builder = builder.add_root_certificate(certificate);
+    }
+    builder
+        .build()
+        .map_err(|e| NotifyError::Config(format!("SMTP TLS 参数无效: {}", e)))
+}
+
+/// 加载 DKIM 私钥并生成签名配置
+fn dkim_config(config: &DkimConfig) -> NotifyResult<dkim::DkimConfig> {
+    let private_key = match (&config.private_key, &config.private_key_path) {
+        (Some(key), None) => key.clone(),
+        (None, Some(path)) => std::fs::read_to_string(path)
+            .map_err(|e| NotifyError::Config(format!("读取 DKIM 私钥失败 {}: {}", path, e)))?,
+        _ => {
+            return Err(NotifyError::Config(
+                "DKIM 需要且只能配置 private_key 或 private_key_path 之一".to_string(),
+            ))
+        }
+    };
+    let algorithm = match config.algorithm {
+        DkimAlgorithm::Rsa => dkim::DkimSigningAlgorithm::Rsa,
+        DkimAlgorithm::Ed25519 => dkim::DkimSigningAlgorithm::Ed25519,
+    };
+    let key = dkim::DkimSigningKey::new(private_key.trim(), algorithm).map_err(|e| {
+        NotifyError::Config(format!(
+            "DKIM 私钥无效（RSA 需为 PKCS#1 PEM，Ed25519 需为 Base64 编码的 32 字节私钥）: {}",
+            e
+        ))
+    })?;
+
+    let mut headers = Vec::new();
+    for name in config
+        .headers
+        .split(',')
+        .map(str::trim)
+        .filter(|n| !n.is_empty())
+    {
+        let header = HeaderName::new_from_ascii(name.to_string())
+            .map_err(|e| NotifyError::Config(format!("DKIM 签名头部无效 {}: {}", name, e)))?;
+        if !headers.contains(&header) {
+            headers.push(header);
+        }
+    }
+    if !headers.iter().any(|h| h.eq_ignore_ascii_case("From")) {
+        return Err(NotifyError::Config(
+            "DKIM 签名头部必须包含 From".to_string(),
+        ));
+    }
+
+    let canonicalization = |c: DkimCanonicalization| match c {
+        DkimCanonicalization::Simple => dkim::DkimCanonicalizationType::Simple,
+        DkimCanonicalization::Relaxed => dkim::DkimCanonicalizationType::Relaxed,
+    };
+    Ok(dkim::DkimConfig::new(
+        config.selector.clone(),
+        config.domain.clone(),
+        key,
+        headers,
+        dkim::DkimCanonicalization {
+            header: canonicalization(config.header_canonicalization),
+            body: canonicalization(config.body_canonicalization),
+        },
+    ))
+}
+
 #[async_trait]
 impl Sender for EmailSender {
-    async fn send(&self, notification: &Notification) -> NotifyResult<()> {
-        let email = Message::builder()
-            .from(notification.from.parse::<Mailbox>()?)
-            .to(notification.to.parse::<Mailbox>()?)
-            .subject(&notification.subject)
-            .body(notification.body.clone())?;
-
-        self.mailer.send(email).await?;
-        Ok(())
+    fn channel(&self) -> ChannelType {
+        ChannelType::Email
+    }
+
+    /// 以单个 SMTP 信封发送给全部 to/cc/bcc 接收者（多个 RCPT TO）
+    async fn send(&self, notification: &Notification) -> NotifyResult<SendReceipt> {
+        if notification.to.is_empty() {
+            return Err(NotifyError::InvalidMessage(
+                "邮件接收者不能为空".to_string(),
+            ));
+        }
+
+        // 如果 from 为空，使用配置的默认发件人
+        let from = if notification.from.is_empty() {
+            self.default_from.as_str()
+        } else {
+            notification.from.as_str()
+        };
+
+        let mut builder = Message::builder().from(from.parse::<Mailbox>()?);
+        for to in &notification.to {
+            builder = builder.to(to.parse::<Mailbox>()?);
+        }
+        for cc in &notification.cc {
+            builder = builder.cc(cc.parse::<Mailbox>()?);
+        }
+        for bcc in &notification.bcc {
+            builder = builder.bcc(bcc.parse::<Mailbox>()?);
+        }
+
+        validate_attachments(
+            &notification.attachments,
+            notification.html_body().is_some(),
+        )
+        .map_err(|(index, reason)| {
+            NotifyError::InvalidMessage(format!("attachments[{}]: {}", index, reason))
+        })?;
+
+        let builder = builder.subject(&notification.subject);
+        let mut email = match self.body(notification).await? {
+            Body::Single(part) => builder.singlepart(part)?,
+            Body::Multi(part) => builder.multipart(part)?,
+        };
+        if let Some(dkim) = &self.dkim {
+            email.sign(dkim);
+        }
+
+        let response = self.mailer.send(email).await?;
+        // SMTP 250 响应中通常带有服务器分配的队列 ID
+        let queue_id = response.message().collect::<Vec<_>>().join(" ");
+        Ok(SendReceipt {
+            provider_message_id: (!queue_id.is_empty()).then_some(queue_id),
+        })
+    }
+
+    /// 单封邮件投递，全部接收者共享同一结果
+    async fn send_batch(&self, notification: &Notification) -> NotifyResult<Vec<RecipientResult>> {
+        let receipt = self.send(notification).await?;
+        Ok(notification
+            .to
+            .iter()
+            .chain(&notification.cc)
+            .chain(&notification.bcc)
+            .map(|r| RecipientResult::sent(r.clone(), receipt.clone()))
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn blocks_non_public_addresses() {
+        for ip in [
+            "127.0.0.1",
+            "10.1.2.3",
+            "172.16.0.1",
+            "192.168.1.1",
+            "169.254.169.254",
+            "100.64.0.1",
+            "0.0.0.0",
+            "255.255.255.255",
+            "224.0.0.1",
+            "::1",
+            "::",
+            "fd00::1",
+            "fe80::1",
+            "::ffff:127.0.0.1",
+            "::ffff:169.254.169.254",
+        ] {
+            assert!(!is_public(ip.parse().unwrap()), "{} should be blocked", ip);
+        }
+    }
+
+    #[test]
+    fn allows_public_addresses() {
+        for ip in ["93.184.216.34", "8.8.8.8", "100.128.0.1", "2606:4700::1111"] {
+            assert!(is_public(ip.parse().unwrap()), "{} should be allowed", ip);
+        }
+    }
+
+    fn config(from: Option<&str>, smtp_user: &str) -> EmailConfig {
+        serde_json::from_value(serde_json::json!({
+            "smtp_server": "localhost",
+            "security": "none",
+            "smtp_user": smtp_user,
+            "from": from,
+        }))
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn requires_valid_default_sender() {
+        for (from, smtp_user) in [(None, ""), (Some(""), "user@example.com"), (None, "apikey")] {
+            let err = EmailSender::new(&config(from, smtp_user)).err().unwrap();
+            assert!(
+                matches!(err, NotifyError::Config(_)),
+                "{from:?}/{smtp_user}"
+            );
+        }
+        let sender = EmailSender::new(&config(None, "user@example.com")).unwrap();
+        assert_eq!(sender.default_from, "user@example.com");
+        let sender = EmailSender::new(&config(Some("Ops <ops@example.com>"), "apikey")).unwrap();
+        assert_eq!(sender.default_from, "Ops <ops@example.com>");
     }
 }
@@ -1,15 +1,18 @@
 use crate::adapters::Sender;
 use crate::config::FeishuConfig;
 use crate::error::{NotifyError, NotifyResult};
-use crate::models::{FeishuMessageType, Notification};
+use crate::models::{ChannelType, FeishuMessageType, Notification, SendReceipt};
 use async_trait::async_trait;
 use base64::Engine;
 use hmac::{Hmac, Mac};
-use reqwest::Client;
+use reqwest::{Client, StatusCode};
 use serde::Deserialize;
 use serde_json::json;
 use sha2::Sha256;
 
+/// 可重试的飞书错误码（11232 消息发送频率超限）
+const THROTTLED_CODES: [i64; 1] = [11232];
+
 /// 飞书发送器
 pub struct FeishuSender {
     client: Client,
@@ -44,6 +47,17 @@ struct FeishuIncoming<'a> {
     text: Option<&'a str>,
 }
 
+/// 飞书接口响应（旧版 Webhook 返回 StatusCode/StatusMessage）
+#[derive(Debug, Deserialize)]
+struct FeishuResponse {
+    #[serde(default)]
+    code: Option<i64>,
+    #[serde(default)]
+    msg: String,
+    #[serde(default, rename = "StatusCode")]
+    status_code: Option<i64>,
+}
+
 /// 反序列化消息类型：支持枚举和字符串两种格式
 fn deserialize_msg_type<'de, D>(deserializer: D) -> Result<Option<FeishuMessageType>, D::Error>
 where
@@ -62,7 +76,11 @@ where
 
 #[async_trait]
 impl Sender for FeishuSender {
-    async fn send(&self, notification: &Notification) -> NotifyResult<()> {
+    fn channel(&self) -> ChannelType {
+        ChannelType::ImFeishu
+    }
+
+    async fn send(&self, notification: &Notification) -> NotifyResult<SendReceipt> {
         let mut url = self.config.webhook.clone();
 
         // 如果配置了 secret，需要附带签名
@@ -179,13 +197,102 @@ impl Sender for FeishuSender {
             }
         };
 
-        self.client
-            .post(&url)
-            .json(&body_value)
-            .send()
-            .await?
-            .error_for_status()?;
+        let response = self.client.post(&url).json(&body_value).send().await?;
+        let status = response.status();
+        let response_text = response.text().await?;
+        tracing::debug!(
+            "Feishu response status: {}, body: {}",
+            status,
+            response_text
+        );
+        check_response(status, &response_text)?;
+
+        Ok(SendReceipt::default())
+    }
+}
+
+/// 校验飞书接口响应：HTTP 状态与响应体 code 均需成功
+fn check_response(status: StatusCode, response_text: &str) -> NotifyResult<()> {
+    let result = serde_json::from_str::<FeishuResponse>(response_text).ok();
+    let code = result
+        .as_ref()
+        .and_then(|r| r.code.or(r.status_code))
+        .unwrap_or(0);
+
+    if !status.is_success() && code == 0 {
+        let message = format!("飞书API错误 {}: {}", status, response_text);
+        // 4xx（限流除外）为请求不合法，重试也不会成功
+        return Err(
+            if status.is_client_error() && status != StatusCode::TOO_MANY_REQUESTS {
+                NotifyError::Rejected(message)
+            } else {
+                NotifyError::Send(message)
+            },
+        );
+    }
+    if code != 0 {
+        let msg = result.map(|r| r.msg).unwrap_or_default();
+        let message = format!("飞书API错误 {}: {}", code, msg);
+        // 限流可重试，其余错误码（签名校验失败、关键词不匹配、消息格式等）重试也不会成功
+        return Err(if THROTTLED_CODES.contains(&code) {
+            NotifyError::Send(message)
+        } else {
+            NotifyError::Rejected(message)
+        });
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn accepts_success_in_both_response_formats() {
+        assert!(check_response(StatusCode::OK, r#"{"code":0,"msg":"success","data":{}}"#).is_ok());
+        assert!(check_response(
+            StatusCode::OK,
+            r#"{"StatusCode":0,"StatusMessage":"success","code":0,"msg":"success"}"#
+        )
+        .is_ok());
+        assert!(check_response(StatusCode::OK, "").is_ok());
+    }
+
+    #[test]
+    fn throttling_code_is_retryable() {
+        let err = check_response(
+            StatusCode::OK,
+            r#"{"code":11232,"msg":"frequency limited"}"#,
+        )
+        .unwrap_err();
+        assert!(matches!(err, NotifyError::Send(_)));
+    }
+
+    #[test]
+    fn other_codes_are_rejected() {
+        for body in [
+            r#"{"code":19021,"msg":"sign match fail or timestamp is not within one hour from current time"}"#,
+            r#"{"code":19024,"msg":"Key Words Not Found"}"#,
+            r#"{"code":9499,"msg":"Bad Request"}"#,
+        ] {
+            let err = check_response(StatusCode::OK, body).unwrap_err();
+            assert!(matches!(err, NotifyError::Rejected(_)), "{body}");
+        }
+    }
 
-        Ok(())
+    #[test]
+    fn http_errors_follow_status_class() {
+        let err = check_response(StatusCode::NOT_FOUND, "404 page not found").unwrap_err();
+        assert!(matches!(err, NotifyError::Rejected(_)));
+        let err = check_response(StatusCode::TOO_MANY_REQUESTS, "").unwrap_err();
+        assert!(matches!(err, NotifyError::Send(_)));
+        let err = check_response(StatusCode::BAD_GATEWAY, "").unwrap_err();
+        assert!(matches!(err, NotifyError::Send(_)));
+        let err = check_response(
+            StatusCode::BAD_REQUEST,
+            r#"{"code":19002,"msg":"params error"}"#,
+        )
+        .unwrap_err();
+        assert!(matches!(err, NotifyError::Rejected(_)));
     }
 }
@@ -1,15 +1,22 @@
 mod dingding;
 mod email;
 mod feishu;
+mod push;
+mod registry;
 mod sender;
+mod site_message;
 mod sms;
 mod wechat;
 
-// 导出 Sender trait
-pub use sender::Sender;
+// 导出 Sender trait 与注册表
+pub use registry::SenderRegistry;
+pub use sender::{merge_outcomes, Sender};
 
 // 导出适配器
 pub use dingding::DingdingSender;
 pub use email::EmailSender;
 pub use feishu::FeishuSender;
+pub use push::PushSender;
+pub use site_message::SiteMessageSender;
 pub use sms::SmsSender;
+pub use wechat::WechatSender;
@@ -0,0 +1,184 @@
+use super::PushMessage;
+use crate::config::ApnsConfig;
+use crate::error::{NotifyError, NotifyResult};
+use jsonwebtoken::{Algorithm, EncodingKey, Header};
+use reqwest::{Client, StatusCode};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use tokio::sync::Mutex;
+
+/// 生产环境地址
+const APNS_PRODUCTION_HOST: &str = "https://api.push.apple.com";
+/// 沙箱环境地址
+const APNS_SANDBOX_HOST: &str = "https://api.sandbox.push.apple.com";
+/// 认证令牌刷新间隔（APNs 要求 20~60 分钟之间）
+const TOKEN_REFRESH_SECS: i64 = 50 * 60;
+/// 表示设备令牌失效的错误原因
+const INVALID_TOKEN_REASONS: [&str; 3] =
+    ["BadDeviceToken", "Unregistered", "DeviceTokenNotForTopic"];
+
+/// APNs 认证令牌声明
+#[derive(Serialize)]
+struct ApnsClaims<'a> {
+    iss: &'a str,
+    iat: i64,
+}
+
+/// APNs 错误响应
+#[derive(Debug, Deserialize)]
+struct ApnsErrorResponse {
+    #[serde(default)]
+    reason: String,
+}
+
+/// 缓存的认证令牌
+struct CachedToken {
+    token: String,
+    issued_at: i64,
+}
+
+/// APNs 客户端（HTTP/2 + Token 认证）
+pub(super) struct ApnsClient {
+    client: Client,
+    config: ApnsConfig,
+    key: EncodingKey,
+    token: Mutex<Option<CachedToken>>,
+}
+
+impl ApnsClient {
+    /// 创建 APNs 客户端，读取 .p8 私钥
+    pub(super) fn new(config: &ApnsConfig) -> NotifyResult<Self> {
+        let pem = std::fs::read(&config.key_path).map_err(|e| {
+            NotifyError::Config(format!("读取 APNs 私钥失败 {}: {}", config.key_path, e))
+        })?;
+        let key = EncodingKey::from_ec_pem(&pem)
+            .map_err(|e| NotifyError::Config(format!("APNs 私钥无效: {}", e)))?;
+        let client = Client::builder()
+            .http2_prior_knowledge()
+            .build()
+            .map_err(|e| NotifyError::Config(format!("创建 APNs HTTP/2 客户端失败: {}", e)))?;
+
+        Ok(Self {
+            client,
+            config: config.clone(),
+            key,
+            token: Mutex::new(None),
+        })
+    }
+
+    /// 获取认证令牌，过期前复用
+    async fn auth_token(&self) -> NotifyResult<String> {
+        let now = chrono::Utc::now().timestamp();
+        let mut cached = self.token.lock().await;
+        if let Some(token) = cached.as_ref() {
+            if now - token.issued_at < TOKEN_REFRESH_SECS {
+                return Ok(token.token.clone());
+            }
+        }
+
+        let mut header = Header::new(Algorithm::ES256);
+        header.kid = Some(self.config.key_id.clone());
+        let claims = ApnsClaims {
+            iss: &self.config.team_id,
+            iat: now,
+        };
+        let token = jsonwebtoken::encode(&header, &claims, &self.key)
+            .map_err(|e| NotifyError::Config(format!("APNs 认证令牌签名失败: {}", e)))?;
+
+        *cached = Some(CachedToken {
+            token: token.clone(),
+            issued_at: now,
+        });
+        Ok(token)
+    }
+
+    /// 丢弃缓存的认证令牌（服务端返回令牌过期时调用）
+    async fn invalidate_token(&self) {
+        *self.token.lock().await = None;
+    }
+
+    /// 构建 APNs 负载
+    fn build_payload(message: &PushMessage) -> Value {
+        let mut aps = json!({
+            "alert": {
+                "title": message.title.as_deref().unwrap_or_default(),
+                "body": message.body.as_deref().unwrap_or_default(),
+            }
+        });
+        if let Some(badge) = message.badge {
+            aps["badge"] = json!(badge);
+        }
+        if let Some(sound) = &message.sound {
+            aps["sound"] = json!(sound);
+        }
+
+        let mut payload = serde_json::Map::new();
+        if let Some(data) = &message.data {
+            payload.extend(data.clone());
+        }
+        payload.insert("aps".to_string(), aps);
+        Value::Object(payload)
+    }
+
+    /// 推送到单个设备，返回 apns-id
+    pub(super) async fn send(
+        &self,
+        device_token: &str,
+        message: &PushMessage,
+    ) -> NotifyResult<Option<String>> {
+        let host = if self.config.sandbox {
+            APNS_SANDBOX_HOST
+        } else {
+            APNS_PRODUCTION_HOST
+        };
+        let url = format!("{}/3/device/{}", host, device_token);
+
+        let response = self
+            .client
+            .post(&url)
+            .bearer_auth(self.auth_token().await?)
+            .header("apns-topic", &self.config.topic)
+            .header("apns-push-type", "alert")
+            .header("apns-priority", "10")
+            .json(&Self::build_payload(message))
+            .send()
+            .await?;
+
+        let status = response.status();
+        if status.is_success() {
+            let apns_id = response
+                .headers()
+                .get("apns-id")
+                .and_then(|v| v.to_str().ok())
+                .map(|s| s.to_string());
+            return Ok(apns_id);
+        }
+
+        let response_text = response.text().await?;
+        let reason = serde_json::from_str::<ApnsErrorResponse>(&response_text)
+            .map(|r| r.reason)
+            .unwrap_or_default();
+        tracing::debug!("APNs response status: {}, body: {}", status, response_text);
+
+        if status == StatusCode::GONE || INVALID_TOKEN_REASONS.contains(&reason.as_str()) {
+            return Err(NotifyError::InvalidDeviceToken(format!(
+                "apns:{} ({})",
+                device_token, reason
+            )));
+        }
+        if reason == "ExpiredProviderToken" {
+            self.invalidate_token().await;
+        }
+
+        let message = format!("APNs错误 {}: {}", status, reason);
+        // 400/413 等为请求不合法（BadTopic、PayloadEmpty、PayloadTooLarge 等），重试也不会成功；
+        // 认证令牌过期、限流和服务端错误可重试
+        if status.is_client_error()
+            && status != StatusCode::TOO_MANY_REQUESTS
+            && reason != "ExpiredProviderToken"
+        {
+            return Err(NotifyError::Rejected(message));
+        }
+        Err(NotifyError::Send(message))
+    }
+}
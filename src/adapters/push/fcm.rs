@@ -0,0 +1,255 @@
+use super::PushMessage;
+use crate::config::FcmConfig;
+use crate::error::{NotifyError, NotifyResult};
+use jsonwebtoken::{Algorithm, EncodingKey, Header};
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use tokio::sync::Mutex;
+
+/// FCM 发送接口 OAuth 作用域
+const FCM_SCOPE: &str = "https://www.googleapis.com/auth/firebase.messaging";
+/// 默认 OAuth 令牌地址
+const DEFAULT_TOKEN_URI: &str = "https://oauth2.googleapis.com/token";
+/// 访问令牌提前刷新的秒数
+const TOKEN_REFRESH_MARGIN_SECS: i64 = 60;
+
+/// 服务账号 JSON 文件（仅解析所需字段）
+#[derive(Deserialize)]
+struct ServiceAccount {
+    client_email: String,
+    private_key: String,
+    #[serde(default)]
+    project_id: Option<String>,
+    #[serde(default)]
+    token_uri: Option<String>,
+}
+
+/// OAuth 断言声明
+#[derive(Serialize)]
+struct OAuthClaims<'a> {
+    iss: &'a str,
+    scope: &'a str,
+    aud: &'a str,
+    iat: i64,
+    exp: i64,
+}
+
+/// OAuth 令牌响应
+#[derive(Deserialize)]
+struct TokenResponse {
+    access_token: String,
+    expires_in: i64,
+}
+
+/// FCM 发送成功响应
+#[derive(Deserialize)]
+struct SendResponse {
+    #[serde(default)]
+    name: Option<String>,
+}
+
+/// FCM 错误响应
+#[derive(Debug, Default, Deserialize)]
+struct FcmErrorResponse {
+    #[serde(default)]
+    error: FcmError,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct FcmError {
+    #[serde(default)]
+    status: String,
+    #[serde(default)]
+    message: String,
+    #[serde(default)]
+    details: Vec<Value>,
+}
+
+impl FcmError {
+    /// FCM 细分错误码（details 中的 errorCode）
+    fn error_code(&self) -> Option<&str> {
+        self.details
+            .iter()
+            .find_map(|d| d.get("errorCode").and_then(|v| v.as_str()))
+    }
+
+    /// 是否表示设备令牌失效
+    fn is_invalid_token(&self) -> bool {
+        if self.error_code() == Some("UNREGISTERED") || self.status == "NOT_FOUND" {
+            return true;
+        }
+        self.status == "INVALID_ARGUMENT" && self.message.contains("registration token")
+    }
+}
+
+/// 缓存的访问令牌
+struct CachedToken {
+    token: String,
+    expires_at: i64,
+}
+
+/// FCM HTTP v1 客户端
+pub(super) struct FcmClient {
+    client: Client,
+    client_email: String,
+    token_uri: String,
+    send_url: String,
+    key: EncodingKey,
+    token: Mutex<Option<CachedToken>>,
+}
+
+impl FcmClient {
+    /// 创建 FCM 客户端，读取服务账号文件
+    pub(super) fn new(config: &FcmConfig) -> NotifyResult<Self> {
+        let content = std::fs::read_to_string(&config.service_account_path).map_err(|e| {
+            NotifyError::Config(format!(
+                "读取 FCM 服务账号失败 {}: {}",
+                config.service_account_path, e
+            ))
+        })?;
+        let account: ServiceAccount = serde_json::from_str(&content)
+            .map_err(|e| NotifyError::Config(format!("FCM 服务账号格式无效: {}", e)))?;
+        let key = EncodingKey::from_rsa_pem(account.private_key.as_bytes())
+            .map_err(|e| NotifyError::Config(format!("FCM 服务账号私钥无效: {}", e)))?;
+        let project_id = config
+            .project_id
+            .clone()
+            .or(account.project_id)
+            .ok_or_else(|| NotifyError::Config("FCM 缺少 project_id".to_string()))?;
+
+        Ok(Self {
+            client: Client::new(),
+            client_email: account.client_email,
+            token_uri: account
+                .token_uri
+                .unwrap_or_else(|| DEFAULT_TOKEN_URI.to_string()),
+            send_url: format!(
+                "https://fcm.googleapis.com/v1/projects/{}/messages:send",
+                project_id
+            ),
+            key,
+            token: Mutex::new(None),
+        })
+    }
+
+    /// 获取 OAuth 访问令牌，过期前复用
+    async fn access_token(&self) -> NotifyResult<String> {
+        let now = chrono::Utc::now().timestamp();
+        let mut cached = self.token.lock().await;
+        if let Some(token) = cached.as_ref() {
+            if now < token.expires_at - TOKEN_REFRESH_MARGIN_SECS {
+                return Ok(token.token.clone());
+            }
+        }
+
+        let claims = OAuthClaims {
+            iss: &self.client_email,
+            scope: FCM_SCOPE,
+            aud: &self.token_uri,
+            iat: now,
+            exp: now + 3600,
+        };
+        let assertion = jsonwebtoken::encode(&Header::new(Algorithm::RS256), &claims, &self.key)
+            .map_err(|e| NotifyError::Config(format!("FCM OAuth 断言签名失败: {}", e)))?;
+
+        let response = self
+            .client
+            .post(&self.token_uri)
+            .form(&[
+                ("grant_type", "urn:ietf:params:oauth:grant-type:jwt-bearer"),
+                ("assertion", assertion.as_str()),
+            ])
+            .send()
+            .await?;
+        let status = response.status();
+        if !status.is_success() {
+            let response_text = response.text().await?;
+            return Err(NotifyError::Send(format!(
+                "FCM OAuth 错误 {}: {}",
+                status, response_text
+            )));
+        }
+        let token: TokenResponse = response.json().await?;
+
+        *cached = Some(CachedToken {
+            token: token.access_token.clone(),
+            expires_at: now + token.expires_in,
+        });
+        Ok(token.access_token)
+    }
+
+    /// 构建 FCM 消息（data 值必须为字符串）
+    fn build_payload(device_token: &str, message: &PushMessage) -> Value {
+        let mut payload = json!({
+            "token": device_token,
+            "notification": {
+                "title": message.title.as_deref().unwrap_or_default(),
+                "body": message.body.as_deref().unwrap_or_default(),
+            }
+        });
+        if let Some(sound) = &message.sound {
+            payload["android"] = json!({ "notification": { "sound": sound } });
+        }
+        if let Some(data) = &message.data {
+            let data: serde_json::Map<String, Value> = data
+                .iter()
+                .map(|(k, v)| {
+                    let v = match v {
+                        Value::String(s) => s.clone(),
+                        other => other.to_string(),
+                    };
+                    (k.clone(), Value::String(v))
+                })
+                .collect();
+            payload["data"] = Value::Object(data);
+        }
+        json!({ "message": payload })
+    }
+
+    /// 推送到单个设备，返回 FCM 消息名称（projects/*/messages/*）
+    pub(super) async fn send(
+        &self,
+        device_token: &str,
+        message: &PushMessage,
+    ) -> NotifyResult<Option<String>> {
+        let response = self
+            .client
+            .post(&self.send_url)
+            .bearer_auth(self.access_token().await?)
+            .json(&Self::build_payload(device_token, message))
+            .send()
+            .await?;
+
+        let status = response.status();
+        if status.is_success() {
+            let sent: SendResponse = response.json().await?;
+            return Ok(sent.name);
+        }
+
+        let response_text = response.text().await?;
+        tracing::debug!("FCM response status: {}, body: {}", status, response_text);
+        let error = serde_json::from_str::<FcmErrorResponse>(&response_text)
+            .unwrap_or_default()
+            .error;
+
+        if error.is_invalid_token() {
+            return Err(NotifyError::InvalidDeviceToken(format!(
+                "fcm:{} ({})",
+                device_token,
+                error.error_code().unwrap_or(&error.status)
+            )));
+        }
+        let message = format!("FCM错误 {}: {}", status, error.message);
+        if status == reqwest::StatusCode::UNAUTHORIZED {
+            // 访问令牌失效，清除缓存后重试会重新获取
+            *self.token.lock().await = None;
+            return Err(NotifyError::Send(message));
+        }
+        // 其余 4xx（INVALID_ARGUMENT、SENDER_ID_MISMATCH 等）重试也不会成功
+        if status.is_client_error() && status != reqwest::StatusCode::TOO_MANY_REQUESTS {
+            return Err(NotifyError::Rejected(message));
+        }
+        Err(NotifyError::Send(message))
+    }
+}
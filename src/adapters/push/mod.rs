@@ -0,0 +1,146 @@
+mod apns;
+mod fcm;
+
+use crate::adapters::Sender;
+use crate::config::{PushConfig, PushProvider};
+use crate::error::{NotifyError, NotifyResult};
+use crate::models::{ChannelType, Notification, SendReceipt};
+use apns::ApnsClient;
+use async_trait::async_trait;
+use fcm::FcmClient;
+use serde::Deserialize;
+use serde_json::Value;
+
+/// 推送消息内容
+#[derive(Debug, Default, Deserialize)]
+struct PushMessage {
+    /// 标题（缺省时使用 notification.subject）
+    #[serde(default)]
+    title: Option<String>,
+    /// 正文
+    #[serde(default)]
+    body: Option<String>,
+    /// 角标（仅 APNs）
+    #[serde(default)]
+    badge: Option<u32>,
+    /// 提示音
+    #[serde(default)]
+    sound: Option<String>,
+    /// 自定义数据
+    #[serde(default)]
+    data: Option<serde_json::Map<String, Value>>,
+}
+
+/// 移动推送发送器
+///
+/// `Notification.to` 为设备令牌，可带 `apns:` / `fcm:` 前缀指定推送通道；
+/// `body` 支持 JSON 对象 `{"title", "body", "badge", "sound", "data"}` 或纯文本。
+pub struct PushSender {
+    apns: Option<ApnsClient>,
+    fcm: Option<FcmClient>,
+    default_provider: Option<PushProvider>,
+}
+
+impl PushSender {
+    /// 创建推送发送器
+    ///
+    /// # 参数
+    /// - `config`: 推送配置
+    pub fn new(config: &PushConfig) -> NotifyResult<Self> {
+        let apns = config.apns.as_ref().map(ApnsClient::new).transpose()?;
+        let fcm = config.fcm.as_ref().map(FcmClient::new).transpose()?;
+        if apns.is_none() && fcm.is_none() {
+            return Err(NotifyError::Config(
+                "push 已启用但未配置 apns 或 fcm".to_string(),
+            ));
+        }
+
+        // 仅配置一个通道时作为默认通道
+        let default_provider = config.default_provider.or(match (&apns, &fcm) {
+            (Some(_), None) => Some(PushProvider::Apns),
+            (None, Some(_)) => Some(PushProvider::Fcm),
+            _ => None,
+        });
+
+        Ok(Self {
+            apns,
+            fcm,
+            default_provider,
+        })
+    }
+
+    /// 解析接收者：返回 (推送通道, 设备令牌)
+    fn resolve_target<'a>(&self, to: &'a str) -> NotifyResult<(PushProvider, &'a str)> {
+        let to = to.trim();
+        let (provider, token) = if let Some(token) = to.strip_prefix("apns:") {
+            (Some(PushProvider::Apns), token)
+        } else if let Some(token) = to.strip_prefix("fcm:") {
+            (Some(PushProvider::Fcm), token)
+        } else {
+            (self.default_provider, to)
+        };
+
+        // 同时配置了 APNs 和 FCM 时接收者必须带前缀，缺少前缀属于请求错误（令牌本身未必失效）
+        let provider = provider.ok_or_else(|| {
+            NotifyError::InvalidMessage(format!(
+                "无法确定推送通道，请为设备令牌添加 apns: 或 fcm: 前缀: {}",
+                to
+            ))
+        })?;
+        if token.is_empty() {
+            return Err(NotifyError::InvalidDeviceToken(to.to_string()));
+        }
+        Ok((provider, token))
+    }
+}
+
+#[async_trait]
+impl Sender for PushSender {
+    fn channel(&self) -> ChannelType {
+        ChannelType::Push
+    }
+
+    async fn send(&self, notification: &Notification) -> NotifyResult<SendReceipt> {
+        let (provider, token) = self.resolve_target(notification.recipient())?;
+
+        // 解析 body：JSON 对象或纯文本
+        let mut message =
+            serde_json::from_str::<PushMessage>(&notification.body).unwrap_or_else(|_| {
+                PushMessage {
+                    body: Some(notification.body.clone()),
+                    ..Default::default()
+                }
+            });
+        if message.title.is_none() && !notification.subject.is_empty() {
+            message.title = Some(notification.subject.clone());
+        }
+
+        let result = match provider {
+            PushProvider::Apns => {
+                let client = self
+                    .apns
+                    .as_ref()
+                    .ok_or_else(|| NotifyError::Config("APNs 未配置".to_string()))?;
+                client.send(token, &message).await
+            }
+            PushProvider::Fcm => {
+                let client = self
+                    .fcm
+                    .as_ref()
+                    .ok_or_else(|| NotifyError::Config("FCM 未配置".to_string()))?;
+                client.send(token, &message).await
+            }
+        };
+
+        match result {
+            Ok(message_id) => Ok(SendReceipt {
+                provider_message_id: message_id,
+            }),
+            Err(NotifyError::InvalidDeviceToken(detail)) => {
+                tracing::warn!(provider = ?provider, token = %token, "推送设备令牌已失效");
+                Err(NotifyError::InvalidDeviceToken(detail))
+            }
+            Err(e) => Err(e),
+        }
+    }
+}
@@ -0,0 +1,295 @@
+use crate::adapters::{
+    DingdingSender, EmailSender, FeishuSender, PushSender, Sender, SmsSender, WechatSender,
+};
+use crate::config::{EmailConfig, NotifyServiceConfig};
+use crate::error::{NotifyError, NotifyResult};
+use crate::models::{ChannelType, Notification};
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/// 默认渠道实例名称
+pub const DEFAULT_INSTANCE: &str = "default";
+
+/// 发送器注册表
+/// 按 (渠道类型, 实例名称) 保存已注册的发送器
+///
+/// 邮件渠道的每个账号注册为一个实例，按账号名称、发件地址或域名路由，
+/// 实例可配置故障转移到另一实例
+#[derive(Default)]
+pub struct SenderRegistry {
+    senders: HashMap<ChannelType, HashMap<String, Arc<dyn Sender>>>,
+    /// 邮件发件地址路由（小写地址 -> 账号名称）
+    email_addresses: HashMap<String, String>,
+    /// 邮件发件域名路由（小写域名 -> 账号名称）
+    email_domains: HashMap<String, String>,
+    /// 故障转移（(渠道, 实例) -> 备用实例）
+    failovers: HashMap<(ChannelType, String), String>,
+}
+
+impl SenderRegistry {
+    /// 创建空的注册表
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 根据配置注册所有已配置的发送器
+    ///
+    /// # 参数
+    /// - `config`: 通知服务配置
+    ///
+    /// # 返回
+    /// - `Err(NotifyError::Config)`: 某个已启用渠道的配置无效
+    pub fn from_config(config: &NotifyServiceConfig) -> NotifyResult<Self> {
+        let mut registry = Self::new();
+
+        registry.register_email(config)?;
+        if let Some(cfg) = &config.sms {
+            registry.register(Arc::new(SmsSender::new(cfg.clone())));
+        }
+        if let Some(cfg) = &config.feishu {
+            registry.register(Arc::new(FeishuSender::new(cfg.clone())));
+        }
+        if let Some(cfg) = &config.dingding {
+            registry.register(Arc::new(DingdingSender::new(cfg.clone())));
+        }
+        if let Some(cfg) = &config.wechat {
+            registry.register(Arc::new(WechatSender::new(cfg.clone())));
+        }
+        if let Some(cfg) = &config.push {
+            registry.register(Arc::new(PushSender::new(cfg)?));
+        }
+
+        Ok(registry)
+    }
+
+    /// 注册全部邮件账号及其路由和故障转移
+    fn register_email(&mut self, config: &NotifyServiceConfig) -> NotifyResult<()> {
+        let mut profiles: Vec<(&str, &EmailConfig)> = config
+            .email_profiles
+            .iter()
+            .map(|(name, cfg)| (name.as_str(), cfg))
+            .collect();
+        if let Some(cfg) = &config.email {
+            if config.email_profiles.contains_key(DEFAULT_INSTANCE) {
+                return Err(NotifyError::Config(
+                    "email 与 email_profiles.default 不能同时配置".to_string(),
+                ));
+            }
+            profiles.push((DEFAULT_INSTANCE, cfg));
+        }
+
+        for (name, cfg) in &profiles {
+            self.register_named(*name, Arc::new(EmailSender::new(cfg)?));
+            for sender in cfg.senders.split(',') {
+                let sender = sender.trim().to_ascii_lowercase();
+                if sender.is_empty() {
+                    continue;
+                }
+                let (routes, key) = match sender.strip_prefix('@') {
+                    Some(domain) => (&mut self.email_domains, domain.to_string()),
+                    None if sender.contains('@') => (&mut self.email_addresses, sender),
+                    None => (&mut self.email_domains, sender),
+                };
+                if let Some(existing) = routes.insert(key.clone(), name.to_string()) {
+                    return Err(NotifyError::Config(format!(
+                        "发件地址或域名 {} 同时路由到邮件账号 {} 和 {}",
+                        key, existing, name
+                    )));
+                }
+            }
+            if let Some(failover) = &cfg.failover {
+                self.failovers
+                    .insert((ChannelType::Email, name.to_string()), failover.to_string());
+            }
+        }
+
+        for ((channel, name), failover) in &self.failovers {
+            if failover == name || self.get_named(*channel, failover).is_none() {
+                return Err(NotifyError::Config(format!(
+                    "邮件账号 {} 的故障转移账号无效: {}",
+                    name, failover
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    /// 以默认实例名称注册发送器
+    pub fn register(&mut self, sender: Arc<dyn Sender>) {
+        self.register_named(DEFAULT_INSTANCE, sender);
+    }
+
+    /// 以指定实例名称注册发送器，同名实例会被覆盖
+    pub fn register_named(&mut self, instance: impl Into<String>, sender: Arc<dyn Sender>) {
+        self.senders
+            .entry(sender.channel())
+            .or_default()
+            .insert(instance.into(), sender);
+    }
+
+    /// 获取渠道的指定实例发送器
+    pub fn get_named(&self, channel: ChannelType, instance: &str) -> Option<Arc<dyn Sender>> {
+        self.senders
+            .get(&channel)
+            .and_then(|instances| instances.get(instance))
+            .cloned()
+    }
+
+    /// 选择发送通知的实例，返回实例名称及发送器
+    ///
+    /// 邮件依次按 `email_profile`、`from` 的完整地址、`from` 的域名选择账号，
+    /// 都未指定或未匹配时使用默认账号；其他渠道使用默认实例
+    ///
+    /// # 返回
+    /// - `Err(NotifyError::InvalidMessage)`: 指定的邮件账号不存在
+    /// - `Err(NotifyError::Config)`: 渠道未配置（或邮件未配置默认账号）
+    pub fn resolve(&self, notification: &Notification) -> NotifyResult<(String, Arc<dyn Sender>)> {
+        let channel = notification.channel;
+        let instance = match (&notification.email_profile, channel) {
+            (Some(profile), ChannelType::Email) => {
+                let sender = self.get_named(channel, profile).ok_or_else(|| {
+                    NotifyError::InvalidMessage(format!("邮件账号不存在: {}", profile))
+                })?;
+                return Ok((profile.clone(), sender));
+            }
+            (None, ChannelType::Email) => self
+                .route_email(&notification.from)
+                .unwrap_or(DEFAULT_INSTANCE),
+            _ => DEFAULT_INSTANCE,
+        };
+        let sender = self.get_named(channel, instance).ok_or_else(|| {
+            NotifyError::Config(format!(
+                "Unsupported or unconfigured channel type: {:?}",
+                channel
+            ))
+        })?;
+        Ok((instance.to_string(), sender))
+    }
+
+    /// 实例的故障转移实例，返回实例名称及发送器
+    pub fn failover(
+        &self,
+        channel: ChannelType,
+        instance: &str,
+    ) -> Option<(&str, Arc<dyn Sender>)> {
+        let failover = self.failovers.get(&(channel, instance.to_string()))?;
+        self.get_named(channel, failover)
+            .map(|sender| (failover.as_str(), sender))
+    }
+
+    /// 按发件地址（支持 `名称 <地址>` 格式）路由邮件账号
+    fn route_email(&self, from: &str) -> Option<&str> {
+        let address = match (from.rfind('<'), from.rfind('>')) {
+            (Some(start), Some(end)) if start < end => &from[start + 1..end],
+            _ => from,
+        };
+        let address = address.trim().to_ascii_lowercase();
+        if address.is_empty() {
+            return None;
+        }
+        if let Some(name) = self.email_addresses.get(&address) {
+            return Some(name);
+        }
+        let (_, domain) = address.rsplit_once('@')?;
+        self.email_domains.get(domain).map(String::as_str)
+    }
+
+    /// 渠道是否已注册发送器
+    pub fn contains(&self, channel: ChannelType) -> bool {
+        self.senders
+            .get(&channel)
+            .is_some_and(|instances| !instances.is_empty())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::{json, Value};
+
+    fn profile(senders: &str) -> Value {
+        json!({
+            "smtp_server": "localhost",
+            "security": "none",
+            "from": "noreply@example.com",
+            "senders": senders,
+        })
+    }
+
+    fn registry(config: Value) -> NotifyResult<SenderRegistry> {
+        SenderRegistry::from_config(&serde_json::from_value(config).unwrap())
+    }
+
+    #[tokio::test]
+    async fn routes_email_by_address_then_domain() {
+        let registry = registry(json!({
+            "email": profile(""),
+            "email_profiles": {
+                "billing": profile("Billing@Example.com"),
+                "marketing": profile("@example.com,example.org"),
+            },
+        }))
+        .unwrap();
+
+        assert_eq!(registry.route_email("billing@example.com"), Some("billing"));
+        assert_eq!(
+            registry.route_email("Billing Team <BILLING@example.com>"),
+            Some("billing")
+        );
+        assert_eq!(registry.route_email("news@example.com"), Some("marketing"));
+        assert_eq!(registry.route_email("news@example.org"), Some("marketing"));
+        assert_eq!(registry.route_email("news@other.com"), None);
+        assert_eq!(registry.route_email(""), None);
+        assert_eq!(registry.route_email("no-domain"), None);
+
+        let notification = Notification {
+            from: "news@other.com".to_string(),
+            ..Notification::new(ChannelType::Email)
+        };
+        assert_eq!(registry.resolve(&notification).unwrap().0, DEFAULT_INSTANCE);
+        let notification = Notification {
+            email_profile: Some("missing".to_string()),
+            ..notification
+        };
+        assert!(matches!(
+            registry.resolve(&notification),
+            Err(NotifyError::InvalidMessage(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn rejects_duplicate_routes() {
+        let result = registry(json!({
+            "email_profiles": {
+                "a": profile("example.com"),
+                "b": profile("@example.com"),
+            },
+        }));
+        assert!(matches!(result, Err(NotifyError::Config(_))));
+    }
+
+    #[tokio::test]
+    async fn rejects_duplicate_default_profile() {
+        let result = registry(json!({
+            "email": profile(""),
+            "email_profiles": {"default": profile("")},
+        }));
+        assert!(matches!(result, Err(NotifyError::Config(_))));
+    }
+
+    #[tokio::test]
+    async fn validates_failover_target() {
+        let mut primary = profile("");
+        primary["failover"] = json!("backup");
+        let result = registry(json!({"email_profiles": {"primary": primary.clone()}}));
+        assert!(matches!(result, Err(NotifyError::Config(_))));
+
+        let registry = registry(json!({
+            "email_profiles": {"primary": primary, "backup": profile("")},
+        }))
+        .unwrap();
+        let (name, _) = registry.failover(ChannelType::Email, "primary").unwrap();
+        assert_eq!(name, "backup");
+        assert!(registry.failover(ChannelType::Email, "backup").is_none());
+    }
+}
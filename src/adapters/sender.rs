@@ -1,18 +1,77 @@
 use crate::error::NotifyResult;
-use crate::models::Notification;
+use crate::models::{ChannelType, Notification, RecipientResult, SendReceipt};
 use async_trait::async_trait;
 
 /// 消息发送器 trait
 /// 所有消息适配器都需要实现此 trait
 #[async_trait]
-pub trait Sender {
-    /// 发送通知消息
+pub trait Sender: Send + Sync {
+    /// 发送器对应的渠道类型，用于在注册表中登记
+    fn channel(&self) -> ChannelType;
+
+    /// 发送通知消息（单接收者，取 `notification.recipient()`）
     ///
     /// # 参数
     /// - `notification`: 通知消息
     ///
     /// # 返回
-    /// - `Ok(())`: 发送成功
+    /// - `Ok(SendReceipt)`: 发送成功，包含服务商消息 ID（如有）
     /// - `Err(NotifyError)`: 发送失败
-    async fn send(&self, notification: &Notification) -> NotifyResult<()>;
+    async fn send(&self, notification: &Notification) -> NotifyResult<SendReceipt>;
+
+    /// 向 `notification.to` 中的全部接收者发送
+    ///
+    /// 默认逐个接收者调用 [`Sender::send`]；支持原生批量接口的渠道（邮件、短信）应覆盖此方法。
+    ///
+    /// # 返回
+    /// - `Ok(Vec<RecipientResult>)`: 至少部分接收者发送成功，逐个给出结果
+    /// - `Err(NotifyError)`: 全部接收者发送失败（返回首个错误）
+    async fn send_batch(&self, notification: &Notification) -> NotifyResult<Vec<RecipientResult>> {
+        if notification.to.len() <= 1 {
+            let receipt = self.send(notification).await?;
+            return Ok(vec![RecipientResult::sent(
+                notification.recipient(),
+                receipt,
+            )]);
+        }
+
+        let mut outcomes = Vec::with_capacity(notification.to.len());
+        for recipient in &notification.to {
+            let single = notification.for_recipient(recipient);
+            outcomes.push((vec![recipient.clone()], self.send(&single).await));
+        }
+        merge_outcomes(outcomes)
+    }
+}
+
+/// 合并分组发送结果：全部失败时返回首个错误，否则展开为逐个接收者的结果
+pub fn merge_outcomes(
+    outcomes: Vec<(Vec<String>, NotifyResult<SendReceipt>)>,
+) -> NotifyResult<Vec<RecipientResult>> {
+    if outcomes.iter().all(|(_, outcome)| outcome.is_err()) {
+        if let Some((_, Err(e))) = outcomes.into_iter().next() {
+            return Err(e);
+        }
+        return Ok(Vec::new());
+    }
+
+    let mut results = Vec::new();
+    for (recipients, outcome) in outcomes {
+        match outcome {
+            Ok(receipt) => results.extend(
+                recipients
+                    .into_iter()
+                    .map(|r| RecipientResult::sent(r, receipt.clone())),
+            ),
+            Err(e) => {
+                tracing::warn!(error = %e, recipients = ?recipients, "部分接收者发送失败");
+                results.extend(
+                    recipients
+                        .into_iter()
+                        .map(|r| RecipientResult::failed(r, &e)),
+                );
+            }
+        }
+    }
+    Ok(results)
 }
@@ -0,0 +1,35 @@
+use crate::adapters::Sender;
+use crate::error::NotifyResult;
+use crate::models::{ChannelType, Notification, SendReceipt};
+use crate::services::SiteMessageService;
+use async_trait::async_trait;
+use std::sync::Arc;
+
+/// 站内消息发送器
+/// 将通知写入 notify_site_message 表，由用户在收件箱中查看
+pub struct SiteMessageSender {
+    service: Arc<SiteMessageService>,
+}
+
+impl SiteMessageSender {
+    /// 创建站内消息发送器
+    ///
+    /// # 参数
+    /// - `service`: 站内消息服务
+    pub fn new(service: Arc<SiteMessageService>) -> Self {
+        Self { service }
+    }
+}
+
+#[async_trait]
+impl Sender for SiteMessageSender {
+    fn channel(&self) -> ChannelType {
+        ChannelType::SiteMessage
+    }
+
+    async fn send(&self, notification: &Notification) -> NotifyResult<SendReceipt> {
+        let id = self.service.create(notification).await?;
+        tracing::debug!(id, user_id = %notification.recipient(), "站内消息已写入");
+        Ok(SendReceipt::with_provider_id(id.to_string()))
+    }
+}
@@ -1,45 +1,121 @@
+use crate::adapters::merge_outcomes;
 use crate::adapters::Sender;
-use crate::config::SmsConfig;
+use crate::config::{SmsConfig, SmsTemplateConfig};
 use crate::error::{NotifyError, NotifyResult};
-use crate::models::Notification;
+use crate::models::{ChannelType, Notification, RecipientResult, SendReceipt};
 use async_trait::async_trait;
 use base64::Engine;
 use hmac::{Hmac, Mac};
 use reqwest::Client;
-use serde::Serialize;
+use serde::Deserialize;
 use sha1::Sha1;
-use std::collections::HashMap;
+use std::collections::BTreeMap;
 use uuid::Uuid;
 
-/// 阿里云短信API请求参数
-#[derive(Serialize)]
-struct SmsRequest<'a> {
-    #[serde(rename = "PhoneNumbers")]
-    phone_numbers: &'a str,
-    #[serde(rename = "SignName")]
-    sign_name: &'a str,
-    #[serde(rename = "TemplateCode")]
-    template_code: &'a str,
-    #[serde(rename = "TemplateParam")]
-    template_param: &'a str,
-    #[serde(rename = "Action")]
-    action: &'a str,
-    #[serde(rename = "Version")]
-    version: &'a str,
-    #[serde(rename = "RegionId")]
-    region_id: &'a str,
-    #[serde(rename = "AccessKeyId")]
-    access_key_id: &'a str,
-    #[serde(rename = "Signature")]
-    signature: String,
-    #[serde(rename = "SignatureMethod")]
-    signature_method: &'a str,
-    #[serde(rename = "SignatureVersion")]
-    signature_version: &'a str,
-    #[serde(rename = "SignatureNonce")]
-    signature_nonce: String,
-    #[serde(rename = "Timestamp")]
-    timestamp: String,
+/// 阿里云短信API响应
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct SmsResponse {
+    /// 状态码，OK 表示成功
+    code: String,
+    /// 状态码描述
+    #[serde(default)]
+    message: String,
+    /// 发送回执 ID，可用于查询发送详情
+    #[serde(default)]
+    biz_id: Option<String>,
+    /// 请求 ID
+    #[serde(default)]
+    request_id: Option<String>,
+}
+
+/// SendBatchSms 单次请求最多支持的手机号数量
+const BATCH_SIZE: usize = 100;
+
+/// 限流类错误码（稍后重试可能成功）
+const THROTTLING_CODES: &[&str] = &[
+    "Throttling",
+    "Throttling.User",
+    "Throttling.Api",
+    "isv.BUSINESS_LIMIT_CONTROL",
+    "isv.DAY_LIMIT_CONTROL",
+    "isv.MINUTE_LIMIT_CONTROL",
+    "isv.HOUR_LIMIT_CONTROL",
+];
+
+/// 认证/授权类错误码（需要修正账号配置）
+const AUTH_CODES: &[&str] = &[
+    "InvalidAccessKeyId.NotFound",
+    "InvalidAccessKeyId.Inactive",
+    "SignatureDoesNotMatch",
+    "IncompleteSignature",
+    "Forbidden.NoPermission",
+    "isp.RAM_PERMISSION_DENY",
+    "isv.ACCOUNT_NOT_EXISTS",
+    "isv.ACCOUNT_ABNORMAL",
+];
+
+/// 服务端临时故障错误码（可重试）
+const TRANSIENT_CODES: &[&str] = &["isp.SYSTEM_ERROR", "ServiceUnavailable", "InternalError"];
+
+/// 将阿里云错误码映射为 NotifyError
+///
+/// 未列出的 `isv.*` 等业务错误（模板非法、签名未审核、号码非法等）视为永久失败
+fn classify_error(code: String, message: String) -> NotifyError {
+    let code_str = code.as_str();
+    if THROTTLING_CODES.contains(&code_str) {
+        NotifyError::SmsThrottled { code, message }
+    } else if AUTH_CODES.contains(&code_str) {
+        NotifyError::SmsAuth { code, message }
+    } else if TRANSIENT_CODES.contains(&code_str) {
+        NotifyError::Send(format!("阿里云短信服务异常 {}: {}", code, message))
+    } else {
+        NotifyError::SmsRejected { code, message }
+    }
+}
+
+/// 解析旧格式的 JSON 参数字符串（body），空字符串视为无参数
+fn parse_legacy_params(body: &str) -> NotifyResult<BTreeMap<String, String>> {
+    if body.trim().is_empty() {
+        return Ok(BTreeMap::new());
+    }
+    let map: serde_json::Map<String, serde_json::Value> = serde_json::from_str(body)
+        .map_err(|e| NotifyError::SmsTemplate(format!("模板参数必须是 JSON 对象: {}", e)))?;
+    map.into_iter()
+        .map(|(k, v)| match v {
+            serde_json::Value::String(s) => Ok((k, s)),
+            serde_json::Value::Number(_) | serde_json::Value::Bool(_) => Ok((k, v.to_string())),
+            _ => Err(NotifyError::SmsTemplate(format!(
+                "模板参数 {} 必须是字符串",
+                k
+            ))),
+        })
+        .collect()
+}
+
+/// 校验模板参数：不允许未登记的参数，也不允许缺少登记的参数
+fn validate_params(
+    name: &str,
+    template: &SmsTemplateConfig,
+    params: &BTreeMap<String, String>,
+) -> NotifyResult<()> {
+    let allowed: Vec<&str> = template.param_names().collect();
+
+    if let Some(unknown) = params.keys().find(|k| !allowed.contains(&k.as_str())) {
+        return Err(NotifyError::SmsTemplate(format!(
+            "模板 {} 不支持参数 {}，允许的参数: {}",
+            name,
+            unknown,
+            allowed.join(",")
+        )));
+    }
+    if let Some(missing) = allowed.iter().find(|k| !params.contains_key(**k)) {
+        return Err(NotifyError::SmsTemplate(format!(
+            "模板 {} 缺少参数 {}",
+            name, missing
+        )));
+    }
+    Ok(())
 }
 
 /// 短信发送器
@@ -60,36 +136,117 @@ impl SmsSender {
         }
     }
 
-    async fn send_sms(&self, phone: &str, param_json: &str) -> NotifyResult<()> {
+    /// 解析本次发送使用的模板代码及模板参数 JSON
+    ///
+    /// 模板按别名或模板代码在 `templates` 中查找，参数需与模板登记的参数名完全一致；
+    /// 未登记的模板仅允许使用配置的默认模板（兼容未配置 `templates` 的部署）。
+    fn resolve_template<'a>(
+        &'a self,
+        notification: &'a Notification,
+    ) -> NotifyResult<(&'a str, String)> {
+        let requested = notification
+            .sms_template_code
+            .as_deref()
+            .or(self.config.template_code.as_deref())
+            .ok_or_else(|| {
+                NotifyError::Config(
+                    "未指定短信模板，且未配置默认模板 (APP__NOTIFY__SMS__TEMPLATE_CODE)"
+                        .to_string(),
+                )
+            })?;
+
+        let params = if notification.sms_params.is_empty() {
+            parse_legacy_params(&notification.body)?
+        } else {
+            notification.sms_params.clone()
+        };
+
+        let template = self
+            .config
+            .templates
+            .get(requested)
+            .or_else(|| self.config.templates.values().find(|t| t.code == requested));
+
+        let code = match template {
+            Some(template) => {
+                validate_params(requested, template, &params)?;
+                template.code.as_str()
+            }
+            None if self.config.template_code.as_deref() == Some(requested) => requested,
+            None => {
+                return Err(NotifyError::SmsTemplate(format!(
+                    "短信模板未在配置中登记: {}",
+                    requested
+                )))
+            }
+        };
+
+        let param_json = serde_json::to_string(&params)
+            .map_err(|e| NotifyError::SmsTemplate(format!("模板参数序列化失败: {}", e)))?;
+        Ok((code, param_json))
+    }
+
+    /// 调用 SendSms 发送短信，成功时返回 BizId
+    async fn send_sms(
+        &self,
+        phone: &str,
+        template_code: &str,
+        param_json: &str,
+    ) -> NotifyResult<Option<String>> {
+        let mut params = BTreeMap::new();
+        params.insert("PhoneNumbers", phone.to_string());
+        params.insert("SignName", self.config.sign_name.clone());
+        params.insert("TemplateCode", template_code.to_string());
+        params.insert("TemplateParam", param_json.to_string());
+        self.call_api("SendSms", params).await
+    }
+
+    /// 调用 SendBatchSms 批量发送短信（同一模板、同一参数），成功时返回 BizId
+    async fn send_batch_sms(
+        &self,
+        phones: &[String],
+        template_code: &str,
+        param_json: &str,
+    ) -> NotifyResult<Option<String>> {
+        let to_json = |v: Vec<&str>| {
+            serde_json::to_string(&v)
+                .map_err(|e| NotifyError::Send(format!("批量短信参数序列化失败: {}", e)))
+        };
+        let phone_json = to_json(phones.iter().map(String::as_str).collect())?;
+        let sign_json = to_json(vec![self.config.sign_name.as_str(); phones.len()])?;
+        let param_json = to_json(vec![param_json; phones.len()])?;
+
+        let mut params = BTreeMap::new();
+        params.insert("PhoneNumberJson", phone_json);
+        params.insert("SignNameJson", sign_json);
+        params.insert("TemplateCode", template_code.to_string());
+        params.insert("TemplateParamJson", param_json);
+        self.call_api("SendBatchSms", params).await
+    }
+
+    /// 签名并调用阿里云短信 API，成功时返回 BizId
+    async fn call_api(
+        &self,
+        action: &str,
+        mut params: BTreeMap<&'static str, String>,
+    ) -> NotifyResult<Option<String>> {
         // 生成时间戳和随机数 (ISO 8601 格式)
         let timestamp = chrono::Utc::now().format("%Y-%m-%dT%H:%M:%SZ").to_string();
         let nonce = Uuid::new_v4().to_string();
 
-        // 构建请求参数
-        let mut params = HashMap::new();
-        params.insert("Action", "SendSms");
-        params.insert("Version", "2017-05-25");
-        params.insert("RegionId", &self.config.region_id);
-        params.insert("PhoneNumbers", phone);
-        params.insert("SignName", &self.config.sign_name);
-        let template_code = self
-            .config
-            .template_code
-            .as_deref()
-            .unwrap_or("SMS_123456789");
-        params.insert("TemplateCode", template_code);
-        params.insert("TemplateParam", param_json);
-        params.insert("AccessKeyId", &self.config.access_key_id);
-        params.insert("SignatureMethod", "HMAC-SHA1");
-        params.insert("SignatureVersion", "1.0");
-        params.insert("SignatureNonce", &nonce);
-        params.insert("Timestamp", &timestamp);
-
-        // 生成签名
-        let mut sorted_params: Vec<_> = params.iter().collect();
-        sorted_params.sort_by_key(|(k, _)| *k);
-
-        let query_string = sorted_params
+        // 公共请求参数
+        params.insert("Action", action.to_string());
+        params.insert("Version", "2017-05-25".to_string());
+        params.insert("RegionId", self.config.region_id.clone());
+        params.insert("AccessKeyId", self.config.access_key_id.clone());
+        params.insert("SignatureMethod", "HMAC-SHA1".to_string());
+        params.insert("SignatureVersion", "1.0".to_string());
+        params.insert("SignatureNonce", nonce);
+        params.insert("Timestamp", timestamp);
+        params.insert("Format", "JSON".to_string());
+
+        // 生成签名（BTreeMap 已按参数名排序）
+        let query_string = params
             .iter()
             .map(|(k, v)| format!("{}={}", k, urlencoding::encode(v)))
             .collect::<Vec<_>>()
@@ -107,43 +264,233 @@ impl SmsSender {
         mac.update(string_to_sign.as_bytes());
         let signature =
             base64::engine::general_purpose::STANDARD.encode(mac.finalize().into_bytes());
-
-        // 构建最终请求
-        let req = SmsRequest {
-            phone_numbers: phone,
-            sign_name: &self.config.sign_name,
-            template_code,
-            template_param: param_json,
-            action: "SendSms",
-            version: "2017-05-25",
-            region_id: &self.config.region_id,
-            access_key_id: &self.config.access_key_id,
-            signature,
-            signature_method: "HMAC-SHA1",
-            signature_version: "1.0",
-            signature_nonce: nonce,
-            timestamp,
-        };
+        params.insert("Signature", signature);
 
         let resp = self
             .client
             .post(&self.config.endpoint)
-            .form(&req)
+            .form(&params)
             .send()
             .await?;
 
+        let status = resp.status();
         let text = resp.text().await?;
-        tracing::debug!("SMS response: {}", text);
+        tracing::debug!("SMS response status: {}, body: {}", status, text);
+        check_response(status, &text)
+    }
+}
+
+/// 解析阿里云短信响应，成功时返回 BizId
+fn check_response(status: reqwest::StatusCode, text: &str) -> NotifyResult<Option<String>> {
+    // 阿里云在业务失败时也可能返回非 2xx 状态码，响应体中同样包含 Code
+    let response: SmsResponse = serde_json::from_str(text).map_err(|e| {
+        NotifyError::Send(format!(
+            "阿里云短信响应解析失败 {}: {}, body: {}",
+            status, e, text
+        ))
+    })?;
 
-        // 可在这里根据返回判断是否发送成功
-        Ok(())
+    if response.code != "OK" {
+        tracing::warn!(
+            code = %response.code,
+            request_id = ?response.request_id,
+            "阿里云短信发送失败: {}",
+            response.message
+        );
+        return Err(classify_error(response.code, response.message));
     }
+
+    Ok(response.biz_id)
 }
 
 #[async_trait]
 impl Sender for SmsSender {
-    async fn send(&self, notification: &Notification) -> NotifyResult<()> {
-        // 假设 Notification.to 是手机号，body 是 JSON 参数字符串
-        self.send_sms(&notification.to, &notification.body).await
+    fn channel(&self) -> ChannelType {
+        ChannelType::Sms
+    }
+
+    async fn send(&self, notification: &Notification) -> NotifyResult<SendReceipt> {
+        // Notification.to 是手机号，模板参数取自 sms_params（或兼容旧的 body JSON 字符串）
+        let (template_code, param_json) = self.resolve_template(notification)?;
+        let biz_id = self
+            .send_sms(notification.recipient(), template_code, &param_json)
+            .await?;
+        Ok(SendReceipt {
+            provider_message_id: biz_id,
+        })
+    }
+
+    /// 多个手机号时使用 SendBatchSms，每批最多 100 个号码
+    async fn send_batch(&self, notification: &Notification) -> NotifyResult<Vec<RecipientResult>> {
+        if notification.to.len() <= 1 {
+            let receipt = self.send(notification).await?;
+            return Ok(vec![RecipientResult::sent(
+                notification.recipient(),
+                receipt,
+            )]);
+        }
+
+        let (template_code, param_json) = self.resolve_template(notification)?;
+        let mut outcomes = Vec::new();
+        for phones in notification.to.chunks(BATCH_SIZE) {
+            let outcome = self
+                .send_batch_sms(phones, template_code, &param_json)
+                .await
+                .map(|biz_id| SendReceipt {
+                    provider_message_id: biz_id,
+                });
+            outcomes.push((phones.to_vec(), outcome));
+        }
+        merge_outcomes(outcomes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reqwest::StatusCode;
+    use serde_json::json;
+
+    /// 构造阿里云错误响应体
+    fn error_body(code: &str) -> String {
+        json!({
+            "Code": code,
+            "Message": "error",
+            "RequestId": "F655A8D5-B967-440B-8683-DAD6FF8DE990"
+        })
+        .to_string()
+    }
+
+    #[test]
+    fn classifies_error_codes() {
+        use crate::error::error_code::*;
+        let cases = [
+            ("Throttling.User", SMS_THROTTLED),
+            ("isv.BUSINESS_LIMIT_CONTROL", SMS_THROTTLED),
+            ("isv.DAY_LIMIT_CONTROL", SMS_THROTTLED),
+            ("SignatureDoesNotMatch", SMS_AUTH_ERROR),
+            ("InvalidAccessKeyId.NotFound", SMS_AUTH_ERROR),
+            ("isp.SYSTEM_ERROR", NOTIFY_SEND_ERROR),
+            ("ServiceUnavailable", NOTIFY_SEND_ERROR),
+            ("isv.MOBILE_NUMBER_ILLEGAL", SMS_REJECTED),
+            ("isv.SMS_TEMPLATE_ILLEGAL", SMS_REJECTED),
+        ];
+        for (code, expected) in cases {
+            let err = check_response(StatusCode::OK, &error_body(code)).unwrap_err();
+            assert_eq!(err.code(), expected, "{code}");
+        }
+    }
+
+    #[test]
+    fn only_throttling_and_transient_codes_are_retryable() {
+        for code in THROTTLING_CODES.iter().chain(TRANSIENT_CODES) {
+            assert!(
+                classify_error(code.to_string(), String::new()).is_retryable(),
+                "{code}"
+            );
+        }
+        for code in AUTH_CODES.iter().chain(&["isv.MOBILE_NUMBER_ILLEGAL"]) {
+            assert!(
+                !classify_error(code.to_string(), String::new()).is_retryable(),
+                "{code}"
+            );
+        }
+    }
+
+    #[test]
+    fn returns_biz_id_on_success() {
+        let body = r#"{"Code":"OK","Message":"OK","BizId":"9006197469364984400","RequestId":"x"}"#;
+        assert_eq!(
+            check_response(StatusCode::OK, body).unwrap().as_deref(),
+            Some("9006197469364984400")
+        );
+    }
+
+    #[test]
+    fn error_body_with_non_2xx_status_is_classified() {
+        let err =
+            check_response(StatusCode::BAD_REQUEST, &error_body("Throttling.User")).unwrap_err();
+        assert!(matches!(err, NotifyError::SmsThrottled { .. }));
+    }
+
+    #[test]
+    fn non_json_body_is_a_retryable_send_error() {
+        let err =
+            check_response(StatusCode::BAD_GATEWAY, "<html>502 Bad Gateway</html>").unwrap_err();
+        assert!(matches!(err, NotifyError::Send(_)));
+        assert!(err.is_retryable());
+    }
+
+    /// 登记了 login_code 模板、默认模板为 SMS_DEFAULT 的发送器
+    fn sender() -> SmsSender {
+        let config: SmsConfig = serde_json::from_value(json!({
+            "endpoint": "https://dysmsapi.aliyuncs.com",
+            "access_key_id": "id",
+            "access_key_secret": "secret",
+            "sign_name": "sign",
+            "template_code": "SMS_DEFAULT",
+            "templates": {
+                "login_code": { "code": "SMS_100001", "params": "code, product" }
+            }
+        }))
+        .unwrap();
+        SmsSender::new(config)
+    }
+
+    fn sms(template: Option<&str>, params: &[(&str, &str)]) -> Notification {
+        Notification {
+            sms_template_code: template.map(str::to_string),
+            sms_params: params
+                .iter()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect(),
+            ..Notification::new(ChannelType::Sms)
+        }
+    }
+
+    #[test]
+    fn resolves_template_by_alias_and_code() {
+        let sender = sender();
+        let params = [("code", "1234"), ("product", "app")];
+        for requested in ["login_code", "SMS_100001"] {
+            let notification = sms(Some(requested), &params);
+            let (code, param_json) = sender.resolve_template(&notification).unwrap();
+            assert_eq!(code, "SMS_100001");
+            assert_eq!(param_json, r#"{"code":"1234","product":"app"}"#);
+        }
+    }
+
+    #[test]
+    fn rejects_unknown_param() {
+        let notification = sms(
+            Some("login_code"),
+            &[("code", "1234"), ("product", "app"), ("extra", "x")],
+        );
+        let err = sender().resolve_template(&notification).unwrap_err();
+        assert!(matches!(&err, NotifyError::SmsTemplate(m) if m.contains("extra")));
+    }
+
+    #[test]
+    fn rejects_missing_param() {
+        let notification = sms(Some("login_code"), &[("code", "1234")]);
+        let err = sender().resolve_template(&notification).unwrap_err();
+        assert!(matches!(&err, NotifyError::SmsTemplate(m) if m.contains("product")));
+    }
+
+    #[test]
+    fn default_template_accepts_legacy_body_params() {
+        let mut notification = sms(None, &[]);
+        notification.body = r#"{"code": 1234}"#.to_string();
+        let sender = sender();
+        let (code, param_json) = sender.resolve_template(&notification).unwrap();
+        assert_eq!(code, "SMS_DEFAULT");
+        assert_eq!(param_json, r#"{"code":"1234"}"#);
+    }
+
+    #[test]
+    fn rejects_unregistered_template() {
+        let notification = sms(Some("SMS_999999"), &[]);
+        let err = sender().resolve_template(&notification).unwrap_err();
+        assert!(matches!(err, NotifyError::SmsTemplate(_)));
     }
 }
@@ -1,3 +1,222 @@
-// 企业微信适配器（可选）
-// 从 flare-adapters/src/im_wechat.rs 迁移
+use crate::adapters::Sender;
+use crate::config::WechatConfig;
+use crate::error::{NotifyError, NotifyResult};
+use crate::models::{ChannelType, Notification, SendReceipt, WechatMessageType};
+use async_trait::async_trait;
+use base64::Engine;
+use md5::{Digest, Md5};
+use reqwest::Client;
+use serde::Deserialize;
+use serde_json::{json, Value};
 
+/// 企业微信群机器人图片大小上限（编码前 2MB）
+const MAX_IMAGE_BYTES: usize = 2 * 1024 * 1024;
+/// 可重试的企业微信错误码（-1 系统繁忙，45009 接口调用超过限制）
+const TRANSIENT_ERRCODES: [i64; 2] = [-1, 45009];
+
+/// 企业微信发送器（群机器人 Webhook）
+pub struct WechatSender {
+    client: Client,
+    config: WechatConfig,
+}
+
+impl WechatSender {
+    /// 创建企业微信发送器
+    ///
+    /// # 参数
+    /// - `config`: 企业微信配置
+    pub fn new(config: WechatConfig) -> Self {
+        Self {
+            client: Client::new(),
+            config,
+        }
+    }
+
+    /// 构建文本消息，未显式指定 @ 列表时使用配置中的默认值
+    fn build_text(&self, text: &str, content: Option<&Value>) -> Value {
+        let mut payload = json!({ "content": text });
+        let mentioned_list = content
+            .and_then(|c| c.get("mentioned_list").cloned())
+            .or_else(|| split_list(self.config.mentioned_list.as_deref()));
+        let mentioned_mobile_list = content
+            .and_then(|c| c.get("mentioned_mobile_list").cloned())
+            .or_else(|| split_list(self.config.mentioned_mobile_list.as_deref()));
+        if let Some(list) = mentioned_list {
+            payload["mentioned_list"] = list;
+        }
+        if let Some(list) = mentioned_mobile_list {
+            payload["mentioned_mobile_list"] = list;
+        }
+        json!({ "msgtype": "text", "text": payload })
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct WechatIncoming<'a> {
+    /// 消息类型（支持枚举或字符串）
+    #[serde(default)]
+    #[serde(deserialize_with = "deserialize_msg_type")]
+    msg_type: Option<WechatMessageType>,
+    #[serde(default)]
+    content: Option<Value>,
+    #[serde(default)]
+    text: Option<&'a str>,
+}
+
+/// 企业微信接口响应
+#[derive(Debug, Deserialize)]
+struct WechatResponse {
+    errcode: i64,
+    #[serde(default)]
+    errmsg: String,
+}
+
+/// 反序列化消息类型：支持枚举和字符串两种格式
+fn deserialize_msg_type<'de, D>(deserializer: D) -> Result<Option<WechatMessageType>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    use serde::de::Error;
+    let value: Option<String> = Option::deserialize(deserializer)?;
+    match value {
+        Some(s) => WechatMessageType::from_str(&s)
+            .ok_or_else(|| D::Error::custom(format!("Invalid Wechat message type: {}", s)))
+            .map(Some),
+        None => Ok(None),
+    }
+}
+
+/// 将逗号分隔的配置项转换为 JSON 数组
+fn split_list(value: Option<&str>) -> Option<Value> {
+    let items: Vec<&str> = value?
+        .split(',')
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+        .collect();
+    if items.is_empty() {
+        None
+    } else {
+        Some(json!(items))
+    }
+}
+
+/// 构建图片消息：要求 base64，md5 缺省时根据图片内容计算
+fn build_image(content: Option<Value>) -> NotifyResult<Value> {
+    let content = content.unwrap_or_else(|| json!({}));
+    let data = content
+        .get("base64")
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| {
+            NotifyError::InvalidMessage("企业微信图片消息缺少 base64 字段".to_string())
+        })?;
+
+    let bytes = base64::engine::general_purpose::STANDARD
+        .decode(data)
+        .map_err(|e| NotifyError::InvalidMessage(format!("企业微信图片 base64 无效: {}", e)))?;
+    if bytes.len() > MAX_IMAGE_BYTES {
+        return Err(NotifyError::InvalidMessage(format!(
+            "企业微信图片超过 2MB 限制: {} bytes",
+            bytes.len()
+        )));
+    }
+
+    let md5 = match content.get("md5").and_then(|v| v.as_str()) {
+        Some(md5) => md5.to_string(),
+        None => format!("{:x}", Md5::digest(&bytes)),
+    };
+
+    Ok(json!({ "msgtype": "image", "image": { "base64": data, "md5": md5 } }))
+}
+
+#[async_trait]
+impl Sender for WechatSender {
+    fn channel(&self) -> ChannelType {
+        ChannelType::ImWechat
+    }
+
+    async fn send(&self, notification: &Notification) -> NotifyResult<SendReceipt> {
+        // 解析 body：允许直接传 text，或 content 对象
+        // 支持两种格式：
+        // 1. JSON 对象格式：{"msg_type": "text", "content": {...}}
+        // 2. 纯文本格式：直接作为文本消息发送
+        let parsed: Result<WechatIncoming, _> = serde_json::from_str(&notification.body);
+        let body_value = match parsed {
+            Ok(incoming) => {
+                let msg_type = incoming.msg_type.unwrap_or(WechatMessageType::Text);
+                match msg_type {
+                    WechatMessageType::Text => {
+                        let text = incoming
+                            .content
+                            .as_ref()
+                            .and_then(|v| v.get("content").and_then(|x| x.as_str()))
+                            .or(incoming.text)
+                            .unwrap_or(notification.body.as_str());
+                        self.build_text(text, incoming.content.as_ref())
+                    }
+                    WechatMessageType::Markdown => {
+                        let content = incoming.content.unwrap_or_else(|| json!({}));
+                        json!({ "msgtype": "markdown", "markdown": content })
+                    }
+                    WechatMessageType::Image => build_image(incoming.content)?,
+                    WechatMessageType::News => {
+                        let content = incoming.content.unwrap_or_else(|| json!({}));
+                        json!({ "msgtype": "news", "news": content })
+                    }
+                    WechatMessageType::File => {
+                        let content = incoming.content.unwrap_or_else(|| json!({}));
+                        json!({ "msgtype": "file", "file": content })
+                    }
+                    WechatMessageType::TemplateCard => {
+                        let content = incoming.content.unwrap_or_else(|| json!({}));
+                        json!({ "msgtype": "template_card", "template_card": content })
+                    }
+                }
+            }
+            Err(_) => {
+                // 如果不是 JSON 格式，作为纯文本消息发送
+                self.build_text(&notification.body, None)
+            }
+        };
+
+        let response = self
+            .client
+            .post(&self.config.webhook)
+            .json(&body_value)
+            .send()
+            .await?;
+
+        let status = response.status();
+        let response_text = response.text().await?;
+
+        tracing::debug!(
+            "Wechat response status: {}, body: {}",
+            status,
+            response_text
+        );
+
+        if !status.is_success() {
+            return Err(NotifyError::Send(format!(
+                "企业微信API错误 {}: {}",
+                status, response_text
+            )));
+        }
+
+        let result: WechatResponse = serde_json::from_str(&response_text).map_err(|e| {
+            NotifyError::Send(format!(
+                "企业微信响应解析失败: {}, body: {}",
+                e, response_text
+            ))
+        })?;
+        if result.errcode != 0 {
+            let message = format!("企业微信API错误 {}: {}", result.errcode, result.errmsg);
+            // 系统繁忙、限流可重试，其余错误码（消息格式、Webhook 地址无效等）重试也不会成功
+            return Err(if TRANSIENT_ERRCODES.contains(&result.errcode) {
+                NotifyError::Send(message)
+            } else {
+                NotifyError::Rejected(message)
+            });
+        }
+
+        Ok(SendReceipt::default())
+    }
+}
@@ -17,6 +17,50 @@ pub mod error_code {
     pub const NOTIFY_CONFIG_ERROR: i32 = 5004;
     /// 通知发送失败
     pub const NOTIFY_SEND_ERROR: i32 = 5005;
+    /// 数据库错误
+    pub const DATABASE_ERROR: i32 = 5006;
+    /// 站内消息不存在
+    pub const SITE_MESSAGE_NOT_FOUND: i32 = 4002;
+    /// 推送设备令牌无效
+    pub const INVALID_DEVICE_TOKEN: i32 = 4003;
+    /// 短信被拒绝（模板、签名、号码等不合法）
+    pub const SMS_REJECTED: i32 = 4004;
+    /// 短信发送被限流
+    pub const SMS_THROTTLED: i32 = 4005;
+    /// 短信服务认证失败
+    pub const SMS_AUTH_ERROR: i32 = 5007;
+    /// 短信模板或模板参数不合法
+    pub const SMS_TEMPLATE_ERROR: i32 = 4006;
+    /// 通知模板不存在
+    pub const TEMPLATE_NOT_FOUND: i32 = 4007;
+    /// 通知模板不合法（语法错误、缺少变量等）
+    pub const TEMPLATE_INVALID: i32 = 4008;
+    /// 通知模板已存在
+    pub const TEMPLATE_EXISTS: i32 = 4009;
+    /// 通知记录不存在
+    pub const NOTIFICATION_NOT_FOUND: i32 = 4010;
+    /// 消息内容不合法
+    pub const INVALID_MESSAGE: i32 = 4011;
+    /// 死信不存在
+    pub const DEAD_LETTER_NOT_FOUND: i32 = 4012;
+    /// 消息队列错误
+    pub const KAFKA_ERROR: i32 = 5008;
+    /// 相同幂等键的请求正在处理中
+    pub const IDEMPOTENCY_IN_PROGRESS: i32 = 4013;
+    /// 缓存错误
+    pub const REDIS_ERROR: i32 = 5009;
+    /// 服务正在关闭
+    pub const SHUTTING_DOWN: i32 = 5010;
+    /// 通知不可取消（非待定时发送状态）
+    pub const NOTIFICATION_NOT_CANCELLABLE: i32 = 4014;
+    /// 渠道发送频率超限
+    pub const CHANNEL_RATE_LIMITED: i32 = 4401;
+    /// 接收者发送频率超限
+    pub const RECIPIENT_RATE_LIMITED: i32 = 4402;
+    /// 批量发送的通知数超出上限
+    pub const BATCH_TOO_LARGE: i32 = 4015;
+    /// 服务商拒绝发送（请求或消息内容不合法，重试也不会成功）
+    pub const PROVIDER_REJECTED: i32 = 4016;
 }
 
 /// 通知服务错误类型
@@ -46,34 +90,233 @@ pub enum NotifyError {
     /// 通知发送失败
     #[error("通知发送失败: {0}")]
     Send(String),
+
+    /// 服务商拒绝发送（请求或消息内容不合法，重试也不会成功）
+    #[error("服务商拒绝发送: {0}")]
+    Rejected(String),
+
+    /// 数据库错误
+    #[error("数据库错误: {0}")]
+    Database(#[from] sqlxplus::SqlxPlusError),
+
+    /// 站内消息不存在
+    #[error("站内消息不存在: {0}")]
+    SiteMessageNotFound(i64),
+
+    /// 推送设备令牌无效（已卸载或令牌失效，不应重试）
+    #[error("推送设备令牌无效: {0}")]
+    InvalidDeviceToken(String),
+
+    /// 短信被服务商拒绝（永久失败，不应重试）
+    #[error("短信被拒绝 {code}: {message}")]
+    SmsRejected { code: String, message: String },
+
+    /// 短信发送被限流（稍后可重试）
+    #[error("短信发送被限流 {code}: {message}")]
+    SmsThrottled { code: String, message: String },
+
+    /// 短信服务认证/授权失败（需修正配置）
+    #[error("短信服务认证失败 {code}: {message}")]
+    SmsAuth { code: String, message: String },
+
+    /// 短信模板或模板参数不合法
+    #[error("短信模板错误: {0}")]
+    SmsTemplate(String),
+
+    /// 通知模板不存在
+    #[error("通知模板不存在: {0}")]
+    TemplateNotFound(String),
+
+    /// 通知模板不合法（语法错误、缺少变量、渲染失败等）
+    #[error("通知模板错误: {0}")]
+    TemplateInvalid(String),
+
+    /// 通知模板已存在（code + channel + locale 重复）
+    #[error("通知模板已存在: {0}")]
+    TemplateExists(String),
+
+    /// 通知记录不存在
+    #[error("通知不存在: {0}")]
+    NotificationNotFound(i64),
+
+    /// 消息内容不合法（接收者、消息体格式错误等，不应重试）
+    #[error("消息内容不合法: {0}")]
+    InvalidMessage(String),
+
+    /// 死信不存在
+    #[error("死信不存在: {0}")]
+    DeadLetterNotFound(i64),
+
+    /// 消息队列错误
+    #[error("消息队列错误: {0}")]
+    Kafka(String),
+
+    /// 相同幂等键的请求正在处理中
+    #[error("相同幂等键的请求正在处理中: {0}")]
+    IdempotencyInProgress(String),
+
+    /// 缓存错误
+    #[error("缓存错误: {0}")]
+    Redis(String),
+
+    /// 通知不可取消（已发送、发送中或已取消）
+    #[error("通知不可取消: {0}")]
+    NotificationNotCancellable(i64),
+
+    /// 服务正在关闭，不再接受新的发送
+    #[error("服务正在关闭，请稍后重试")]
+    ShuttingDown,
+
+    /// 渠道发送频率超限
+    #[error("渠道发送频率超限: {channel}，{retry_after_ms} ms 后可重试")]
+    ChannelRateLimited {
+        channel: String,
+        retry_after_ms: u64,
+    },
+
+    /// 接收者发送频率超限
+    #[error("接收者发送频率超限: {recipient}，{retry_after_ms} ms 后可重试")]
+    RecipientRateLimited {
+        recipient: String,
+        retry_after_ms: u64,
+    },
+
+    /// 批量发送的通知数超出上限
+    #[error("批量发送的通知数 {size} 超出上限 {max}")]
+    BatchTooLarge { size: usize, max: usize },
+}
+
+impl NotifyError {
+    /// 对应的错误码（见 [`error_code`]），用于 HTTP 响应和发送结果事件
+    pub fn code(&self) -> i32 {
+        use error_code::*;
+        match self {
+            NotifyError::Smtp(_) => SMTP_ERROR,
+            NotifyError::EmailAddress(_) => EMAIL_ADDRESS_ERROR,
+            NotifyError::EmailBuild(_) => EMAIL_BUILD_ERROR,
+            NotifyError::Http(_) => HTTP_ERROR,
+            NotifyError::Config(_) => NOTIFY_CONFIG_ERROR,
+            NotifyError::Send(_) => NOTIFY_SEND_ERROR,
+            NotifyError::Rejected(_) => PROVIDER_REJECTED,
+            NotifyError::Database(_) => DATABASE_ERROR,
+            NotifyError::SiteMessageNotFound(_) => SITE_MESSAGE_NOT_FOUND,
+            NotifyError::InvalidDeviceToken(_) => INVALID_DEVICE_TOKEN,
+            NotifyError::SmsRejected { .. } => SMS_REJECTED,
+            NotifyError::SmsThrottled { .. } => SMS_THROTTLED,
+            NotifyError::SmsAuth { .. } => SMS_AUTH_ERROR,
+            NotifyError::SmsTemplate(_) => SMS_TEMPLATE_ERROR,
+            NotifyError::TemplateNotFound(_) => TEMPLATE_NOT_FOUND,
+            NotifyError::TemplateInvalid(_) => TEMPLATE_INVALID,
+            NotifyError::TemplateExists(_) => TEMPLATE_EXISTS,
+            NotifyError::NotificationNotFound(_) => NOTIFICATION_NOT_FOUND,
+            NotifyError::InvalidMessage(_) => INVALID_MESSAGE,
+            NotifyError::DeadLetterNotFound(_) => DEAD_LETTER_NOT_FOUND,
+            NotifyError::Kafka(_) => KAFKA_ERROR,
+            NotifyError::IdempotencyInProgress(_) => IDEMPOTENCY_IN_PROGRESS,
+            NotifyError::Redis(_) => REDIS_ERROR,
+            NotifyError::NotificationNotCancellable(_) => NOTIFICATION_NOT_CANCELLABLE,
+            NotifyError::ShuttingDown => SHUTTING_DOWN,
+            NotifyError::ChannelRateLimited { .. } => CHANNEL_RATE_LIMITED,
+            NotifyError::RecipientRateLimited { .. } => RECIPIENT_RATE_LIMITED,
+            NotifyError::BatchTooLarge { .. } => BATCH_TOO_LARGE,
+        }
+    }
+
+    /// 是否为可重试的临时性错误
+    ///
+    /// 网络错误、服务端 5xx、限流、数据库错误可重试；
+    /// 配置错误、消息内容错误、服务商明确拒绝的错误重试也不会成功
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            NotifyError::Smtp(e) => !e.is_permanent() && !e.is_client(),
+            NotifyError::Http(e) => match e.status() {
+                Some(status) => {
+                    status.is_server_error() || status == reqwest::StatusCode::TOO_MANY_REQUESTS
+                }
+                None => !e.is_builder() && !e.is_decode(),
+            },
+            NotifyError::Send(_)
+            | NotifyError::Database(_)
+            | NotifyError::SmsThrottled { .. }
+            | NotifyError::Kafka(_)
+            | NotifyError::Redis(_)
+            | NotifyError::ShuttingDown
+            | NotifyError::ChannelRateLimited { .. }
+            | NotifyError::RecipientRateLimited { .. } => true,
+            NotifyError::EmailAddress(_)
+            | NotifyError::EmailBuild(_)
+            | NotifyError::Config(_)
+            | NotifyError::SiteMessageNotFound(_)
+            | NotifyError::InvalidDeviceToken(_)
+            | NotifyError::SmsRejected { .. }
+            | NotifyError::Rejected(_)
+            | NotifyError::SmsAuth { .. }
+            | NotifyError::SmsTemplate(_)
+            | NotifyError::TemplateNotFound(_)
+            | NotifyError::TemplateInvalid(_)
+            | NotifyError::TemplateExists(_)
+            | NotifyError::NotificationNotFound(_)
+            | NotifyError::InvalidMessage(_)
+            | NotifyError::DeadLetterNotFound(_)
+            | NotifyError::IdempotencyInProgress(_)
+            | NotifyError::NotificationNotCancellable(_)
+            | NotifyError::BatchTooLarge { .. } => false,
+        }
+    }
 }
 
 /// 将 NotifyError 转换为 AppError
 impl From<NotifyError> for BaseAppError {
     fn from(err: NotifyError) -> Self {
-        use error_code::*;
+        let code = err.code();
         match err {
-            NotifyError::Smtp(e) => {
-                BaseAppError::biz_error(SMTP_ERROR as i32, format!("SMTP 错误: {}", e))
-            }
-            NotifyError::EmailAddress(e) => {
-                BaseAppError::biz_error(EMAIL_ADDRESS_ERROR as i32, format!("邮件地址错误: {}", e))
-            }
-            NotifyError::EmailBuild(e) => {
-                BaseAppError::biz_error(EMAIL_BUILD_ERROR as i32, format!("邮件构建错误: {}", e))
+            NotifyError::Database(e) => {
+                tracing::error!(error = ?e, "数据库操作失败");
+                BaseAppError::common_error(code, "数据库错误".to_string())
             }
-            NotifyError::Http(e) => {
-                BaseAppError::biz_error(HTTP_ERROR, format!("HTTP 请求错误: {}", e))
+            NotifyError::SmsAuth {
+                code: sms_code,
+                message,
+            } => {
+                tracing::error!(code = %sms_code, "短信服务认证失败: {}", message);
+                BaseAppError::common_error(code, "短信服务认证失败".to_string())
             }
-            NotifyError::Config(msg) => {
-                BaseAppError::biz_error(NOTIFY_CONFIG_ERROR, format!("通知配置错误: {}", msg))
+            NotifyError::Kafka(msg) => {
+                tracing::error!("消息队列错误: {}", msg);
+                BaseAppError::common_error(code, "消息队列错误".to_string())
             }
-            NotifyError::Send(msg) => {
-                BaseAppError::biz_error(NOTIFY_SEND_ERROR, format!("通知发送失败: {}", msg))
+            NotifyError::Redis(msg) => {
+                tracing::error!("缓存错误: {}", msg);
+                BaseAppError::common_error(code, "缓存错误".to_string())
             }
+            err => BaseAppError::biz_error(code, err.to_string()),
         }
     }
 }
 
 /// 通知服务结果类型
 pub type NotifyResult<T> = Result<T, NotifyError>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn provider_rejections_are_not_retryable() {
+        let rejected = NotifyError::Rejected("企业微信API错误 93000: invalid webhook url".into());
+        assert!(!rejected.is_retryable());
+        assert_eq!(rejected.code(), error_code::PROVIDER_REJECTED);
+
+        assert!(NotifyError::Send("企业微信API错误 -1: system busy".into()).is_retryable());
+        assert!(!NotifyError::SmsRejected {
+            code: "isv.MOBILE_NUMBER_ILLEGAL".into(),
+            message: "invalid".into(),
+        }
+        .is_retryable());
+        assert!(NotifyError::ChannelRateLimited {
+            channel: "sms".into(),
+            retry_after_ms: 10,
+        }
+        .is_retryable());
+    }
+}
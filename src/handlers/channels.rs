@@ -1,6 +1,9 @@
-use axum::response::Json;
+use crate::models::ChannelType;
+use crate::state::AppState;
+use axum::{extract::State, response::Json};
 use fbc_starter::R;
 use serde::Serialize;
+use std::sync::Arc;
 
 /// 渠道信息
 #[derive(Debug, Serialize)]
@@ -9,49 +12,20 @@ pub struct ChannelInfo {
     pub channel: String,
     /// 渠道名称
     pub name: String,
-    /// 是否支持
+    /// 是否支持（已注册发送器）
     pub supported: bool,
 }
 
 /// 获取支持的渠道列表处理器
-pub async fn list_channels() -> Json<R<Vec<ChannelInfo>>> {
-    let channels = vec![
-        ChannelInfo {
-            channel: "email".to_string(),
-            name: "邮件".to_string(),
-            supported: true,
-        },
-        ChannelInfo {
-            channel: "sms".to_string(),
-            name: "短信".to_string(),
-            supported: true,
-        },
-        ChannelInfo {
-            channel: "im_feishu".to_string(),
-            name: "飞书".to_string(),
-            supported: true,
-        },
-        ChannelInfo {
-            channel: "im_dingding".to_string(),
-            name: "钉钉".to_string(),
-            supported: true,
-        },
-        ChannelInfo {
-            channel: "im_wechat".to_string(),
-            name: "企业微信".to_string(),
-            supported: false,
-        },
-        ChannelInfo {
-            channel: "push".to_string(),
-            name: "推送通知".to_string(),
-            supported: false,
-        },
-        ChannelInfo {
-            channel: "site_message".to_string(),
-            name: "站内消息".to_string(),
-            supported: false,
-        },
-    ];
+pub async fn list_channels(State(state): State<Arc<AppState>>) -> Json<R<Vec<ChannelInfo>>> {
+    let channels = ChannelType::ALL
+        .iter()
+        .map(|channel| ChannelInfo {
+            channel: channel.as_str().to_string(),
+            name: channel.display_name().to_string(),
+            supported: state.context.registry().contains(*channel),
+        })
+        .collect();
 
     Json(R::ok_with_data(channels))
 }
@@ -0,0 +1,44 @@
+use crate::models::DeadLetter;
+use crate::state::AppState;
+use axum::{
+    extract::{Path, Query, State},
+    response::Json,
+};
+use fbc_starter::{AppResult, CursorPageBaseResp, R};
+use serde::Deserialize;
+use std::sync::Arc;
+
+/// 死信列表查询参数
+#[derive(Debug, Deserialize)]
+pub struct DeadLetterListQuery {
+    /// 游标（首页不传，后续传上一页返回的 cursor）
+    #[serde(default)]
+    pub cursor: Option<u32>,
+    /// 每页条数（默认 20，最大 100）
+    #[serde(default)]
+    pub page_size: Option<u32>,
+    /// 状态过滤（pending、replayed）
+    #[serde(default)]
+    pub status: Option<String>,
+}
+
+/// 分页查询死信
+pub async fn list_dead_letters(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<DeadLetterListQuery>,
+) -> AppResult<Json<R<CursorPageBaseResp<DeadLetter>>>> {
+    let page = state
+        .dead_letter_service
+        .list(query.cursor, query.page_size, query.status)
+        .await?;
+    Ok(Json(R::ok_with_data(page)))
+}
+
+/// 将死信重新发布到原 topic
+pub async fn replay_dead_letter(
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<i64>,
+) -> AppResult<Json<R<()>>> {
+    state.dead_letter_service.replay(id).await?;
+    Ok(Json(R::ok()))
+}
@@ -1,5 +1,19 @@
 mod channels;
+mod dead_letters;
 mod notification;
+mod site_messages;
+mod templates;
 
 pub use channels::list_channels;
-pub use notification::send_notification;
+pub use dead_letters::{list_dead_letters, replay_dead_letter};
+pub use notification::{
+    cancel_notification, get_notification, list_notifications, send_notification,
+    send_notification_batch,
+};
+pub use site_messages::{
+    count_unread_site_messages, delete_site_message, list_site_messages,
+    mark_all_site_messages_read, mark_site_message_read,
+};
+pub use templates::{
+    create_template, delete_template, get_template, list_templates, update_template,
+};
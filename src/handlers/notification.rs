@@ -1,58 +1,330 @@
-use crate::kafka::NotificationHandlerContext;
-use crate::models::{ChannelType, Notification};
-use axum::{extract::State, response::Json};
-use fbc_starter::{AppResult, R};
+use crate::error::NotifyError;
+use crate::models::{
+    one_or_many, schedule_time, BatchItemResult, BatchSendResult, ChannelType, EmailAttachment,
+    FallbackStep, Notification, NotificationDetail, NotificationFilter, NotificationStatus,
+    NotifyRecord, SendResult,
+};
+use crate::services::NotificationRecordService;
+use crate::state::AppState;
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    response::Json,
+};
+use fbc_starter::{AppResult, CursorPageBaseResp, R};
 use serde::Deserialize;
+use std::collections::{BTreeMap, HashSet};
 use std::sync::Arc;
+use tokio::sync::Semaphore;
+use tokio::task::JoinSet;
 
 /// 发送通知请求
 #[derive(Debug, Deserialize)]
 pub struct SendNotificationRequest {
+    /// 关联 ID（可选，原样带回发送结果事件）
+    #[serde(default)]
+    pub correlation_id: Option<String>,
+    /// 幂等键（可选，去重窗口内重复请求返回首次发送结果）
+    #[serde(default)]
+    pub idempotency_key: Option<String>,
     /// 发送者（邮件时使用，可选）
     #[serde(default)]
     pub from: String,
-    /// 接收者（邮件、短信时使用）
-    pub to: String,
+    /// 邮件账号名称（邮件时使用，可选，未设置时按 from 的地址或域名选择账号）
+    #[serde(default)]
+    pub email_profile: Option<String>,
+    /// 接收者（邮件、短信、推送、站内消息时使用），支持单个字符串或数组
+    #[serde(default, deserialize_with = "one_or_many")]
+    pub to: Vec<String>,
+    /// 抄送（邮件时使用，可选）
+    #[serde(default, deserialize_with = "one_or_many")]
+    pub cc: Vec<String>,
+    /// 密送（邮件时使用，可选）
+    #[serde(default, deserialize_with = "one_or_many")]
+    pub bcc: Vec<String>,
     /// 主题（邮件时使用，可选）
     #[serde(default)]
     pub subject: String,
     /// 消息内容
     ///
     /// 根据渠道类型有不同的用途：
-    /// - **邮件渠道 (Email)**：作为邮件正文内容，支持纯文本或 HTML 格式
-    /// - **短信渠道 (Sms)**：作为 JSON 格式的模板参数字符串，用于填充短信模板变量
+    /// - **邮件渠道 (Email)**：纯文本正文；同时设置 `html` 时作为 text/plain 备选部分，
+    ///   未设置 `html` 且内容以 HTML 标签开头时按 HTML 发送
+    /// - **短信渠道 (Sms)**：作为 JSON 格式的模板参数字符串（兼容旧格式，优先使用 `sms_params`）
     /// - **钉钉渠道 (ImDingding)**：支持两种格式：
     ///   - JSON 对象格式：`{"msg_type": "text|markdown|link|actionCard|...", "content": {...}}`
     ///   - 纯文本格式：直接作为文本消息发送
     /// - **飞书渠道 (ImFeishu)**：支持两种格式：
     ///   - JSON 对象格式：`{"msg_type": "text|post|image|interactive|...", "content": {...}}`
     ///   - 纯文本格式：直接作为文本消息发送
-    /// - **企业微信渠道 (ImWechat)**：消息内容（具体格式待实现）
-    /// - **推送通知渠道 (Push)**：推送消息内容（具体格式待实现）
-    /// - **站内消息渠道 (SiteMessage)**：站内消息内容（具体格式待实现）
+    /// - **企业微信渠道 (ImWechat)**：支持两种格式：
+    ///   - JSON 对象格式：`{"msg_type": "text|markdown|image|news|file|template_card", "content": {...}}`
+    ///   - 纯文本格式：直接作为文本消息发送
+    /// - **推送通知渠道 (Push)**：`to` 为设备令牌（可带 `apns:` / `fcm:` 前缀），body 支持两种格式：
+    ///   - JSON 对象格式：`{"title": "...", "body": "...", "badge": 1, "sound": "default", "data": {...}}`
+    ///   - 纯文本格式：作为推送正文，`subject` 作为标题
+    /// - **站内消息渠道 (SiteMessage)**：站内消息正文，`to` 为接收用户 ID，`subject` 为标题
+    ///
+    /// 使用 `template_code` 时可不传，由模板渲染
+    #[serde(default)]
     pub body: String,
+    /// HTML 正文（邮件时使用，可选）
+    #[serde(default)]
+    pub html: Option<String>,
+    /// 附件及内联图片（邮件时使用，可选）
+    ///
+    /// 每项为 `{"filename", "content_type"?, "content"? | "url"?, "content_id"?}`：
+    /// `content` 为 Base64 内容，`url` 为发送时下载的地址；设置 `content_id` 的为内联图片，
+    /// HTML 正文中以 `<img src="cid:logo">` 引用
+    #[serde(default)]
+    pub attachments: Vec<EmailAttachment>,
     /// 消息渠道类型
     pub channel: ChannelType,
+    /// 短信模板别名或模板代码（短信时使用，可选）
+    #[serde(default)]
+    pub sms_template_code: Option<String>,
+    /// 短信模板参数（短信时使用，可选）
+    #[serde(default)]
+    pub sms_params: BTreeMap<String, String>,
+    /// 通知模板代码（可选，设置后 subject/body 由模板渲染）
+    #[serde(default)]
+    pub template_code: Option<String>,
+    /// 模板语言（可选，默认 zh-CN）
+    #[serde(default)]
+    pub locale: Option<String>,
+    /// 模板变量（可选）
+    #[serde(default)]
+    pub variables: serde_json::Map<String, serde_json::Value>,
+    /// 计划发送时间（毫秒时间戳，可选）
+    #[serde(default)]
+    pub send_at: Option<i64>,
+    /// 延迟发送秒数（可选，与 send_at 同时设置时以 send_at 为准）
+    #[serde(default)]
+    pub delay: Option<u64>,
+    /// 降级链（可选，按顺序在满足条件时改用下一渠道发送，如 `im_feishu → email`）
+    #[serde(default)]
+    pub fallback: Vec<FallbackStep>,
+}
+
+impl SendNotificationRequest {
+    /// 构建通知消息
+    fn into_notification(self) -> Notification {
+        Notification {
+            correlation_id: self.correlation_id,
+            tenant: None,
+            idempotency_key: self.idempotency_key,
+            from: self.from,
+            email_profile: self.email_profile,
+            to: self.to,
+            cc: self.cc,
+            bcc: self.bcc,
+            subject: self.subject,
+            body: self.body,
+            html: self.html,
+            attachments: self.attachments,
+            channel: self.channel,
+            sms_template_code: self.sms_template_code,
+            sms_params: self.sms_params,
+            template_code: self.template_code,
+            locale: self.locale,
+            variables: self.variables,
+            send_at: schedule_time(self.send_at, self.delay),
+            fallback: self.fallback,
+            fallback_of: None,
+        }
+    }
+}
+
+/// 批量发送中的单条通知
+#[derive(Debug, Deserialize)]
+pub struct BatchSendItem {
+    /// 客户端提供的条目 ID（批内唯一，结果按此 ID 对应）
+    pub id: String,
+    /// 通知内容（字段同单条发送）
+    #[serde(flatten)]
+    pub request: SendNotificationRequest,
+}
+
+/// 发送通知查询参数
+#[derive(Debug, Deserialize)]
+pub struct SendNotificationQuery {
+    /// 异步发送（可选，默认 false）：校验并写入排队记录后立即返回 202 和通知 ID，
+    /// 发送状态通过 `GET /api/v1/notifications/{id}` 或发送结果事件获取（需要数据库）
+    #[serde(default, rename = "async")]
+    pub is_async: bool,
 }
 
 /// 发送通知处理器
+///
+/// 同步发送返回 200 及发送结果；`async=true` 时返回 202 及通知 ID
 pub async fn send_notification(
-    State(context): State<Arc<NotificationHandlerContext>>,
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<SendNotificationQuery>,
     Json(request): Json<SendNotificationRequest>,
-) -> AppResult<Json<R<String>>> {
-    // 构建通知消息
-    let notification = Notification {
-        from: request.from,
-        to: request.to,
-        subject: request.subject,
-        body: request.body,
-        channel: request.channel,
-    };
+) -> AppResult<(StatusCode, Json<R<SendResult>>)> {
+    if state.shutdown.is_stopping() {
+        return Err(NotifyError::ShuttingDown.into());
+    }
 
-    // 使用上下文的方法发送通知
-    context.send(&notification).await?;
+    let notification = request.into_notification();
 
-    Ok(Json(R::ok_with_data(
-        "Notification sent successfully".to_string(),
-    )))
+    if query.is_async {
+        let result = state.orchestrator.enqueue(&notification).await?;
+        state.scheduler_wakeup.notify_one();
+        return Ok((StatusCode::ACCEPTED, Json(R::ok_with_data(result))));
+    }
+
+    // 按降级链发送通知，返回通知 ID、逐个接收者的结果（含服务商消息 ID）及降级步骤结果
+    let result = state.orchestrator.send(&notification).await?;
+
+    Ok((StatusCode::OK, Json(R::ok_with_data(result))))
+}
+
+/// 批量发送通知处理器
+///
+/// 请求体为通知数组（可混合渠道），每条带客户端 ID；按配置的并发数同时发送，
+/// 单条失败不影响其他通知，返回与请求顺序一致的逐条结果。
+/// 同步发送返回 200，`async=true` 时逐条写入排队记录后返回 202
+pub async fn send_notification_batch(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<SendNotificationQuery>,
+    Json(items): Json<Vec<BatchSendItem>>,
+) -> AppResult<(StatusCode, Json<R<BatchSendResult>>)> {
+    if state.shutdown.is_stopping() {
+        return Err(NotifyError::ShuttingDown.into());
+    }
+    if items.is_empty() {
+        return Err(NotifyError::InvalidMessage("批量发送的通知不能为空".to_string()).into());
+    }
+    if items.len() > state.batch.max_size {
+        return Err(NotifyError::BatchTooLarge {
+            size: items.len(),
+            max: state.batch.max_size,
+        }
+        .into());
+    }
+    let mut seen = HashSet::new();
+    for item in &items {
+        if item.id.is_empty() {
+            return Err(NotifyError::InvalidMessage("条目 ID 不能为空".to_string()).into());
+        }
+        if !seen.insert(item.id.as_str()) {
+            return Err(NotifyError::InvalidMessage(format!("条目 ID 重复: {}", item.id)).into());
+        }
+    }
+
+    let ids: Vec<String> = items.iter().map(|item| item.id.clone()).collect();
+    let permits = Arc::new(Semaphore::new(state.batch.concurrency.max(1)));
+    let mut tasks = JoinSet::new();
+    for (index, item) in items.into_iter().enumerate() {
+        let state = state.clone();
+        let permits = permits.clone();
+        let is_async = query.is_async;
+        tasks.spawn(async move {
+            let _permit = permits.acquire_owned().await;
+            let notification = item.request.into_notification();
+            let outcome = if is_async {
+                state.orchestrator.enqueue(&notification).await
+            } else {
+                state.orchestrator.send(&notification).await
+            };
+            (index, BatchItemResult::new(item.id, outcome))
+        });
+    }
+
+    let mut results: Vec<Option<BatchItemResult>> = ids.iter().map(|_| None).collect();
+    while let Some(joined) = tasks.join_next().await {
+        match joined {
+            Ok((index, result)) => results[index] = Some(result),
+            Err(e) => tracing::error!("批量发送任务异常退出: {}", e),
+        }
+    }
+    let items: Vec<BatchItemResult> = results
+        .into_iter()
+        .zip(ids)
+        .map(|(result, id)| {
+            result.unwrap_or_else(|| {
+                BatchItemResult::new(id, Err(NotifyError::Send("发送任务异常退出".to_string())))
+            })
+        })
+        .collect();
+
+    let result = BatchSendResult::new(items);
+    if query.is_async {
+        if result.succeeded > 0 {
+            state.scheduler_wakeup.notify_one();
+        }
+        return Ok((StatusCode::ACCEPTED, Json(R::ok_with_data(result))));
+    }
+    Ok((StatusCode::OK, Json(R::ok_with_data(result))))
+}
+
+/// 通知记录列表查询参数
+#[derive(Debug, Deserialize)]
+pub struct NotificationListQuery {
+    /// 游标（首页不传，后续传上一页返回的 cursor）
+    #[serde(default)]
+    pub cursor: Option<u32>,
+    /// 每页条数（默认 20，最大 100）
+    #[serde(default)]
+    pub page_size: Option<u32>,
+    /// 渠道过滤
+    #[serde(default)]
+    pub channel: Option<ChannelType>,
+    /// 状态过滤（pending、scheduled、sending、sent、partial、failed、cancelled）
+    #[serde(default)]
+    pub status: Option<NotificationStatus>,
+    /// 接收者过滤（手机号、邮箱、用户 ID 等，精确匹配）
+    #[serde(default)]
+    pub recipient: Option<String>,
+    /// 创建时间起（毫秒时间戳，含）
+    #[serde(default)]
+    pub start_time: Option<i64>,
+    /// 创建时间止（毫秒时间戳，不含）
+    #[serde(default)]
+    pub end_time: Option<i64>,
+}
+
+/// 获取发送记录服务，未配置数据库时返回配置错误
+fn record_service(state: &AppState) -> Result<&Arc<NotificationRecordService>, NotifyError> {
+    state
+        .record_service
+        .as_ref()
+        .ok_or_else(|| NotifyError::Config("发送记录未启用（未配置数据库）".to_string()))
+}
+
+/// 查询通知详情（含各接收者发送结果）
+pub async fn get_notification(
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<i64>,
+) -> AppResult<Json<R<NotificationDetail>>> {
+    let detail = record_service(&state)?.get(id).await?;
+    Ok(Json(R::ok_with_data(detail)))
+}
+
+/// 取消待定时发送的通知
+pub async fn cancel_notification(
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<i64>,
+) -> AppResult<Json<R<()>>> {
+    record_service(&state)?.cancel(id).await?;
+    Ok(Json(R::ok()))
+}
+
+/// 分页查询通知记录
+pub async fn list_notifications(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<NotificationListQuery>,
+) -> AppResult<Json<R<CursorPageBaseResp<NotifyRecord>>>> {
+    let filter = NotificationFilter {
+        channel: query.channel,
+        status: query.status,
+        recipient: query.recipient,
+        start_time: query.start_time,
+        end_time: query.end_time,
+    };
+    let page = record_service(&state)?
+        .list(query.cursor, query.page_size, filter)
+        .await?;
+    Ok(Json(R::ok_with_data(page)))
 }
@@ -0,0 +1,89 @@
+use crate::error::NotifyError;
+use crate::models::SiteMessage;
+use crate::services::SiteMessageService;
+use crate::state::AppState;
+use axum::{
+    extract::{Path, Query, State},
+    http::HeaderMap,
+    response::Json,
+};
+use fbc_starter::{get_uid_from_headers, AppResult, CursorPageBaseResp, R};
+use serde::Deserialize;
+use std::sync::Arc;
+
+/// 站内消息列表查询参数
+#[derive(Debug, Deserialize)]
+pub struct SiteMessageListQuery {
+    /// 游标（首页不传，后续传上一页返回的 cursor）
+    #[serde(default)]
+    pub cursor: Option<u32>,
+    /// 每页条数（默认 10，最大 100）
+    #[serde(default)]
+    pub page_size: Option<u32>,
+    /// 已读状态过滤（0 未读，1 已读，不传为全部）
+    #[serde(default)]
+    pub is_read: Option<i32>,
+}
+
+/// 获取站内消息服务，未配置数据库时返回配置错误
+fn site_message_service(state: &AppState) -> Result<&Arc<SiteMessageService>, NotifyError> {
+    state
+        .site_message_service
+        .as_ref()
+        .ok_or_else(|| NotifyError::Config("站内消息未启用（未配置数据库）".to_string()))
+}
+
+/// 分页查询当前用户的站内消息
+pub async fn list_site_messages(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Query(query): Query<SiteMessageListQuery>,
+) -> AppResult<Json<R<CursorPageBaseResp<SiteMessage>>>> {
+    let user_id = get_uid_from_headers(&headers)?;
+    let page = site_message_service(&state)?
+        .list(user_id, query.cursor, query.page_size, query.is_read)
+        .await?;
+    Ok(Json(R::ok_with_data(page)))
+}
+
+/// 统计当前用户的未读站内消息数
+pub async fn count_unread_site_messages(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+) -> AppResult<Json<R<u64>>> {
+    let user_id = get_uid_from_headers(&headers)?;
+    let count = site_message_service(&state)?.count_unread(user_id).await?;
+    Ok(Json(R::ok_with_data(count)))
+}
+
+/// 将单条站内消息标记为已读
+pub async fn mark_site_message_read(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Path(id): Path<i64>,
+) -> AppResult<Json<R<()>>> {
+    let user_id = get_uid_from_headers(&headers)?;
+    site_message_service(&state)?.mark_read(user_id, id).await?;
+    Ok(Json(R::ok()))
+}
+
+/// 将当前用户的全部站内消息标记为已读，返回更新条数
+pub async fn mark_all_site_messages_read(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+) -> AppResult<Json<R<u64>>> {
+    let user_id = get_uid_from_headers(&headers)?;
+    let affected = site_message_service(&state)?.mark_all_read(user_id).await?;
+    Ok(Json(R::ok_with_data(affected)))
+}
+
+/// 删除（逻辑删除）单条站内消息
+pub async fn delete_site_message(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Path(id): Path<i64>,
+) -> AppResult<Json<R<()>>> {
+    let user_id = get_uid_from_headers(&headers)?;
+    site_message_service(&state)?.delete(user_id, id).await?;
+    Ok(Json(R::ok()))
+}
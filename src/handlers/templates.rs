@@ -0,0 +1,133 @@
+use crate::error::NotifyError;
+use crate::models::{ChannelType, NotifyTemplate};
+use crate::services::TemplateService;
+use crate::state::AppState;
+use axum::{
+    extract::{Path, Query, State},
+    response::Json,
+};
+use fbc_starter::{AppResult, CursorPageBaseResp, R};
+use serde::Deserialize;
+use std::sync::Arc;
+
+/// 模板列表查询参数
+#[derive(Debug, Deserialize)]
+pub struct TemplateListQuery {
+    /// 游标（首页不传，后续传上一页返回的 cursor）
+    #[serde(default)]
+    pub cursor: Option<u32>,
+    /// 每页条数（默认 20，最大 100）
+    #[serde(default)]
+    pub page_size: Option<u32>,
+    /// 模板代码过滤
+    #[serde(default)]
+    pub code: Option<String>,
+    /// 渠道过滤
+    #[serde(default)]
+    pub channel: Option<ChannelType>,
+}
+
+/// 新建模板请求
+#[derive(Debug, Deserialize)]
+pub struct CreateTemplateRequest {
+    /// 模板代码
+    pub code: String,
+    /// 渠道
+    pub channel: ChannelType,
+    /// 语言（可选，默认 zh-CN）
+    #[serde(default)]
+    pub locale: Option<String>,
+    /// 主题模板（可选）
+    #[serde(default)]
+    pub subject_template: Option<String>,
+    /// 正文模板
+    pub body_template: String,
+    /// 备注（可选）
+    #[serde(default)]
+    pub remark: Option<String>,
+}
+
+/// 修改模板请求（未传的字段保持不变）
+#[derive(Debug, Deserialize)]
+pub struct UpdateTemplateRequest {
+    /// 主题模板
+    #[serde(default)]
+    pub subject_template: Option<String>,
+    /// 正文模板
+    #[serde(default)]
+    pub body_template: Option<String>,
+    /// 备注
+    #[serde(default)]
+    pub remark: Option<String>,
+}
+
+/// 获取模板服务，未配置数据库时返回配置错误
+fn template_service(state: &AppState) -> Result<&Arc<TemplateService>, NotifyError> {
+    state
+        .template_service
+        .as_ref()
+        .ok_or_else(|| NotifyError::Config("通知模板未启用（未配置数据库）".to_string()))
+}
+
+/// 新建模板，返回模板 ID
+pub async fn create_template(
+    State(state): State<Arc<AppState>>,
+    Json(request): Json<CreateTemplateRequest>,
+) -> AppResult<Json<R<i64>>> {
+    let template = NotifyTemplate {
+        code: Some(request.code),
+        channel: Some(request.channel.as_str().to_string()),
+        locale: request.locale,
+        subject_template: request.subject_template,
+        body_template: Some(request.body_template),
+        remark: request.remark,
+        ..Default::default()
+    };
+    let id = template_service(&state)?.create(template).await?;
+    Ok(Json(R::ok_with_data(id)))
+}
+
+/// 分页查询模板
+pub async fn list_templates(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<TemplateListQuery>,
+) -> AppResult<Json<R<CursorPageBaseResp<NotifyTemplate>>>> {
+    let page = template_service(&state)?
+        .list(query.cursor, query.page_size, query.code, query.channel)
+        .await?;
+    Ok(Json(R::ok_with_data(page)))
+}
+
+/// 查询单个模板
+pub async fn get_template(
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<i64>,
+) -> AppResult<Json<R<NotifyTemplate>>> {
+    let template = template_service(&state)?.get(id).await?;
+    Ok(Json(R::ok_with_data(template)))
+}
+
+/// 修改模板
+pub async fn update_template(
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<i64>,
+    Json(request): Json<UpdateTemplateRequest>,
+) -> AppResult<Json<R<()>>> {
+    let template = NotifyTemplate {
+        subject_template: request.subject_template,
+        body_template: request.body_template,
+        remark: request.remark,
+        ..Default::default()
+    };
+    template_service(&state)?.update(id, template).await?;
+    Ok(Json(R::ok()))
+}
+
+/// 删除（逻辑删除）模板
+pub async fn delete_template(
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<i64>,
+) -> AppResult<Json<R<()>>> {
+    template_service(&state)?.delete(id).await?;
+    Ok(Json(R::ok()))
+}
@@ -0,0 +1,575 @@
+use crate::models::{
+    one_or_many, schedule_time, validate_attachments, validate_fallback, ChannelType,
+    EmailAttachment, FallbackStep, Notification,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::fmt;
+
+/// 消息信封结构
+///
+/// 上游发布到通知 topic 的标准格式，`payload` 的结构由 `schema_version` 决定：
+/// `{"schema_version": 1, "message_id": "...", "correlation_id": "...", "tenant": "...",
+///   "idempotency_key": "...", "key": "...", "created_at": 1760000000000, "payload": {...}}`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Envelope {
+    /// 消息结构版本
+    pub schema_version: u32,
+    /// 消息 ID（上游生成，全局唯一）
+    pub message_id: String,
+    /// 关联 ID（可选，为空时使用 message_id）
+    #[serde(default)]
+    pub correlation_id: Option<String>,
+    /// 租户（可选）
+    #[serde(default)]
+    pub tenant: Option<String>,
+    /// 幂等键（可选，重复投递的消息只发送一次）
+    #[serde(default)]
+    pub idempotency_key: Option<String>,
+    /// 顺序键（可选，相同顺序键的消息按到达顺序发送，默认按接收者）
+    #[serde(default)]
+    pub key: Option<String>,
+    /// 消息创建时间（毫秒时间戳）
+    pub created_at: i64,
+    /// 消息内容
+    pub payload: Value,
+}
+
+/// 单条校验失败原因
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Violation {
+    /// 出错字段路径（如 `payload.to`，整条消息为 `$`）
+    pub field: String,
+    /// 失败原因
+    pub reason: String,
+}
+
+impl Violation {
+    fn new(field: impl Into<String>, reason: impl Into<String>) -> Self {
+        Self {
+            field: field.into(),
+            reason: reason.into(),
+        }
+    }
+}
+
+/// 消息解码失败（结构不合法、版本不支持或校验未通过）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Rejection {
+    /// 消息结构版本（旧格式或版本无法识别时为 None）
+    pub schema_version: Option<u32>,
+    /// 消息 ID（无法识别时为 None）
+    pub message_id: Option<String>,
+    /// 失败原因列表
+    pub violations: Vec<Violation>,
+}
+
+impl Rejection {
+    /// 旧格式（无信封）消息解析失败
+    pub fn legacy(reason: impl ToString) -> Self {
+        Self {
+            schema_version: None,
+            message_id: None,
+            violations: vec![Violation::new("$", reason.to_string())],
+        }
+    }
+}
+
+impl fmt::Display for Rejection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let reasons: Vec<String> = self
+            .violations
+            .iter()
+            .map(|v| format!("{}: {}", v.field, v.reason))
+            .collect();
+        write!(f, "{}", reasons.join("; "))
+    }
+}
+
+/// 按版本解码 payload 的函数
+type Decoder = fn(&Envelope) -> Result<Notification, Vec<Violation>>;
+
+/// 获取指定版本的解码器
+fn decoder(schema_version: u32) -> Option<Decoder> {
+    match schema_version {
+        1 => Some(decode_v1),
+        _ => None,
+    }
+}
+
+/// 是否为信封格式的消息（带 `schema_version` 字段）
+pub fn is_envelope(data: &Value) -> bool {
+    data.get("schema_version").is_some()
+}
+
+/// 解码信封格式的消息
+///
+/// 先校验信封字段，再交给对应版本的解码器校验 payload，
+/// 所有失败原因一次性返回，便于上游修正
+pub fn decode(data: &Value) -> Result<Notification, Rejection> {
+    let envelope = decode_envelope(data).map_err(|violations| Rejection {
+        schema_version: data
+            .get("schema_version")
+            .and_then(Value::as_u64)
+            .map(|v| v as u32),
+        message_id: data
+            .get("message_id")
+            .and_then(Value::as_str)
+            .map(str::to_string),
+        violations,
+    })?;
+
+    let rejection = |violations| Rejection {
+        schema_version: Some(envelope.schema_version),
+        message_id: Some(envelope.message_id.clone()),
+        violations,
+    };
+    let decoder = decoder(envelope.schema_version).ok_or_else(|| {
+        rejection(vec![Violation::new(
+            "schema_version",
+            format!("unsupported version {}", envelope.schema_version),
+        )])
+    })?;
+    let notification = decoder(&envelope).map_err(rejection)?;
+
+    Ok(Notification {
+        correlation_id: envelope
+            .correlation_id
+            .clone()
+            .or_else(|| Some(envelope.message_id.clone())),
+        tenant: envelope.tenant.clone(),
+        idempotency_key: envelope.idempotency_key.clone(),
+        ..notification
+    })
+}
+
+/// 校验信封字段
+fn decode_envelope(data: &Value) -> Result<Envelope, Vec<Violation>> {
+    let Some(object) = data.as_object() else {
+        return Err(vec![Violation::new("$", "expected object")]);
+    };
+
+    let mut violations = Vec::new();
+    let mut required =
+        |field: &str, check: fn(&Value) -> bool, expected: &str| match object.get(field) {
+            None | Some(Value::Null) => violations.push(Violation::new(field, "required")),
+            Some(value) if !check(value) => {
+                violations.push(Violation::new(field, format!("expected {}", expected)))
+            }
+            Some(_) => {}
+        };
+    required(
+        "schema_version",
+        |v| v.as_u64().is_some_and(|n| n <= u32::MAX as u64),
+        "unsigned integer",
+    );
+    required(
+        "message_id",
+        |v| v.as_str().is_some_and(|s| !s.trim().is_empty()),
+        "non-empty string",
+    );
+    required(
+        "created_at",
+        |v| v.as_i64().is_some_and(|n| n > 0),
+        "millisecond timestamp",
+    );
+    required("payload", Value::is_object, "object");
+    for field in ["correlation_id", "tenant", "idempotency_key", "key"] {
+        if let Some(value) = object.get(field) {
+            if !value.is_null() && !value.is_string() {
+                violations.push(Violation::new(field, "expected string"));
+            }
+        }
+    }
+
+    if !violations.is_empty() {
+        return Err(violations);
+    }
+    serde_json::from_value(data.clone()).map_err(|e| vec![Violation::new("$", e.to_string())])
+}
+
+/// v1 payload：字段与 HTTP 发送接口一致，不允许出现未知字段
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct PayloadV1 {
+    channel: ChannelType,
+    #[serde(default)]
+    from: String,
+    #[serde(default)]
+    email_profile: Option<String>,
+    #[serde(default, deserialize_with = "one_or_many")]
+    to: Vec<String>,
+    #[serde(default, deserialize_with = "one_or_many")]
+    cc: Vec<String>,
+    #[serde(default, deserialize_with = "one_or_many")]
+    bcc: Vec<String>,
+    #[serde(default)]
+    subject: String,
+    #[serde(default)]
+    body: String,
+    #[serde(default)]
+    html: Option<String>,
+    #[serde(default)]
+    attachments: Vec<EmailAttachment>,
+    #[serde(default)]
+    sms_template_code: Option<String>,
+    #[serde(default)]
+    sms_params: BTreeMap<String, String>,
+    #[serde(default)]
+    template_code: Option<String>,
+    #[serde(default)]
+    locale: Option<String>,
+    #[serde(default)]
+    variables: serde_json::Map<String, Value>,
+    #[serde(default)]
+    send_at: Option<i64>,
+    #[serde(default)]
+    delay: Option<u64>,
+    #[serde(default)]
+    fallback: Vec<FallbackStep>,
+}
+
+/// v1 解码器
+fn decode_v1(envelope: &Envelope) -> Result<Notification, Vec<Violation>> {
+    let payload: PayloadV1 = serde_json::from_value(envelope.payload.clone())
+        .map_err(|e| vec![Violation::new("payload", e.to_string())])?;
+
+    // send_at 与 delay 同时设置时以 send_at 为准，与 HTTP 发送接口一致
+    let notification = Notification {
+        from: payload.from,
+        email_profile: payload.email_profile,
+        to: payload.to,
+        cc: payload.cc,
+        bcc: payload.bcc,
+        subject: payload.subject,
+        body: payload.body,
+        html: payload.html,
+        attachments: payload.attachments,
+        sms_template_code: payload.sms_template_code,
+        sms_params: payload.sms_params,
+        template_code: payload.template_code,
+        locale: payload.locale,
+        variables: payload.variables,
+        send_at: schedule_time(payload.send_at, payload.delay),
+        fallback: payload.fallback,
+        ..Notification::new(payload.channel)
+    };
+    validate(&notification, "payload.")?;
+    Ok(notification)
+}
+
+/// 校验旧格式（无信封）消息的内容，规则与 v1 payload 相同
+pub fn validate_legacy(notification: Notification) -> Result<Notification, Rejection> {
+    validate(&notification, "")
+        .map(|()| notification)
+        .map_err(|violations| Rejection {
+            schema_version: None,
+            message_id: None,
+            violations,
+        })
+}
+
+/// 校验通知内容：接收者、渠道专属字段、正文、附件和降级链
+///
+/// `prefix` 为字段路径前缀（如 `payload.`）
+fn validate(notification: &Notification, prefix: &str) -> Result<(), Vec<Violation>> {
+    let mut violations = Vec::new();
+    let mut violation = |field: &str, reason: &str| {
+        violations.push(Violation::new(format!("{}{}", prefix, field), reason))
+    };
+    let channel = notification.channel;
+    let templated = notification.template_code.is_some();
+
+    let needs_recipient = matches!(
+        channel,
+        ChannelType::Email | ChannelType::Sms | ChannelType::Push | ChannelType::SiteMessage
+    );
+    if needs_recipient && notification.to.is_empty() {
+        violation("to", "required for this channel");
+    }
+    if channel != ChannelType::Email {
+        let email_only = [
+            ("cc", !notification.cc.is_empty()),
+            ("bcc", !notification.bcc.is_empty()),
+            ("email_profile", notification.email_profile.is_some()),
+            ("html", notification.html.is_some()),
+            ("attachments", !notification.attachments.is_empty()),
+        ];
+        for (field, present) in email_only {
+            if present {
+                violation(field, "only supported by email channel");
+            }
+        }
+    } else if let Err((index, reason)) = validate_attachments(
+        &notification.attachments,
+        notification.html_body().is_some(),
+    ) {
+        violation(&format!("attachments[{}]", index), reason);
+    }
+    if channel != ChannelType::Sms
+        && (notification.sms_template_code.is_some() || !notification.sms_params.is_empty())
+    {
+        violation("sms_params", "only supported by sms channel");
+    }
+    if !templated {
+        if channel == ChannelType::Email && notification.subject.trim().is_empty() {
+            violation("subject", "required for email");
+        }
+        let has_body = !notification.body.trim().is_empty()
+            || notification
+                .html
+                .as_deref()
+                .is_some_and(|html| !html.trim().is_empty());
+        if channel != ChannelType::Sms && !has_body {
+            violation("body", "required unless template_code is set");
+        }
+        if !notification.variables.is_empty() {
+            violation("variables", "requires template_code");
+        }
+    }
+
+    if let Err((index, reason)) = validate_fallback(channel, &notification.fallback) {
+        violation(&format!("fallback[{}]", index), reason);
+    }
+
+    if violations.is_empty() {
+        Ok(())
+    } else {
+        Err(violations)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn envelope(payload: Value) -> Value {
+        json!({
+            "schema_version": 1,
+            "message_id": "msg-1",
+            "created_at": 1760000000000i64,
+            "payload": payload,
+        })
+    }
+
+    fn fields(data: &Value) -> Vec<String> {
+        decode(data)
+            .unwrap_err()
+            .violations
+            .into_iter()
+            .map(|v| v.field)
+            .collect()
+    }
+
+    #[test]
+    fn decodes_valid_envelope() {
+        let data = json!({
+            "schema_version": 1,
+            "message_id": "msg-1",
+            "tenant": "acme",
+            "created_at": 1760000000000i64,
+            "payload": {"channel": "email", "to": "a@example.com", "subject": "Hi", "body": "Hello"},
+        });
+        let notification = decode(&data).unwrap();
+        assert_eq!(notification.channel, ChannelType::Email);
+        assert_eq!(notification.to, vec!["a@example.com"]);
+        assert_eq!(notification.correlation_id.as_deref(), Some("msg-1"));
+        assert_eq!(notification.tenant.as_deref(), Some("acme"));
+    }
+
+    #[test]
+    fn rejects_non_object() {
+        assert_eq!(fields(&json!([1, 2])), vec!["$"]);
+    }
+
+    #[test]
+    fn reports_all_envelope_violations() {
+        let data = json!({
+            "schema_version": -1,
+            "message_id": " ",
+            "tenant": 1,
+            "key": true,
+        });
+        let rejection = decode(&data).unwrap_err();
+        assert_eq!(rejection.schema_version, None);
+        assert_eq!(rejection.message_id.as_deref(), Some(" "));
+        assert_eq!(
+            fields(&data),
+            vec![
+                "schema_version",
+                "message_id",
+                "created_at",
+                "payload",
+                "tenant",
+                "key"
+            ]
+        );
+    }
+
+    #[test]
+    fn rejects_unsupported_version() {
+        let data = json!({
+            "schema_version": 2,
+            "message_id": "msg-1",
+            "created_at": 1760000000000i64,
+            "payload": {},
+        });
+        let rejection = decode(&data).unwrap_err();
+        assert_eq!(rejection.schema_version, Some(2));
+        assert_eq!(rejection.message_id.as_deref(), Some("msg-1"));
+        assert_eq!(fields(&data), vec!["schema_version"]);
+    }
+
+    #[test]
+    fn rejects_malformed_payload() {
+        assert_eq!(
+            fields(&envelope(json!({"channel": "fax", "to": "x"}))),
+            vec!["payload"]
+        );
+        assert_eq!(
+            fields(&envelope(
+                json!({"channel": "sms", "to": "1", "unknown": 1})
+            )),
+            vec!["payload"]
+        );
+    }
+
+    #[test]
+    fn requires_recipient() {
+        assert_eq!(
+            fields(&envelope(
+                json!({"channel": "sms", "sms_template_code": "T1"})
+            )),
+            vec!["payload.to"]
+        );
+    }
+
+    #[test]
+    fn rejects_email_only_fields_on_other_channels() {
+        let data = envelope(json!({
+            "channel": "push",
+            "to": "device",
+            "body": "Hello",
+            "cc": "a@example.com",
+            "bcc": "b@example.com",
+            "email_profile": "billing",
+            "html": "<p>Hello</p>",
+            "attachments": [{"filename": "a.txt", "content": "YQ=="}],
+        }));
+        assert_eq!(
+            fields(&data),
+            vec![
+                "payload.cc",
+                "payload.bcc",
+                "payload.email_profile",
+                "payload.html",
+                "payload.attachments",
+            ]
+        );
+    }
+
+    #[test]
+    fn validates_email_attachments() {
+        let data = envelope(json!({
+            "channel": "email",
+            "to": "a@example.com",
+            "subject": "Hi",
+            "body": "Hello",
+            "attachments": [
+                {"filename": "a.txt", "content": "YQ=="},
+                {"filename": "b.txt"},
+            ],
+        }));
+        assert_eq!(fields(&data), vec!["payload.attachments[1]"]);
+    }
+
+    #[test]
+    fn rejects_sms_params_on_other_channels() {
+        let data = envelope(json!({
+            "channel": "push",
+            "to": "device",
+            "body": "Hello",
+            "sms_params": {"code": "1234"},
+        }));
+        assert_eq!(fields(&data), vec!["payload.sms_params"]);
+    }
+
+    #[test]
+    fn requires_content_without_template() {
+        let data = envelope(json!({
+            "channel": "email",
+            "to": "a@example.com",
+            "variables": {"name": "A"},
+        }));
+        assert_eq!(
+            fields(&data),
+            vec!["payload.subject", "payload.body", "payload.variables"]
+        );
+
+        let templated = envelope(json!({
+            "channel": "email",
+            "to": "a@example.com",
+            "template_code": "welcome",
+            "variables": {"name": "A"},
+        }));
+        assert!(decode(&templated).is_ok());
+    }
+
+    #[test]
+    fn accepts_html_without_body() {
+        let data = envelope(json!({
+            "channel": "email",
+            "to": "a@example.com",
+            "subject": "Hi",
+            "html": "<p>Hello</p>",
+        }));
+        assert!(decode(&data).is_ok());
+    }
+
+    #[test]
+    fn send_at_takes_precedence_over_delay() {
+        let data = envelope(json!({
+            "channel": "sms",
+            "to": "1",
+            "send_at": 1760000000000i64,
+            "delay": 60,
+        }));
+        assert_eq!(decode(&data).unwrap().send_at, Some(1760000000000));
+    }
+
+    #[test]
+    fn validates_legacy_messages_with_same_rules() {
+        let notification = Notification {
+            cc: vec!["a@example.com".to_string()],
+            body: "hello".to_string(),
+            ..Notification::new(ChannelType::Sms)
+        };
+        let fields: Vec<_> = validate_legacy(notification)
+            .unwrap_err()
+            .violations
+            .into_iter()
+            .map(|v| v.field)
+            .collect();
+        assert_eq!(fields, vec!["to", "cc"]);
+
+        let notification = Notification {
+            to: vec!["1".to_string()],
+            ..Notification::new(ChannelType::Sms)
+        };
+        assert!(validate_legacy(notification).is_ok());
+    }
+
+    #[test]
+    fn validates_fallback_chain() {
+        let data = envelope(json!({
+            "channel": "email",
+            "to": "a@example.com",
+            "subject": "Hi",
+            "body": "Hello",
+            "fallback": [{"channel": "sms", "to": "1", "when": "on_timeout"}],
+        }));
+        assert_eq!(fields(&data), vec!["payload.fallback[0]"]);
+    }
+}
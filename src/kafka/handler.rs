@@ -1,117 +1,639 @@
-use crate::adapters::Sender;
-use crate::adapters::{DingdingSender, EmailSender, FeishuSender, SmsSender};
-use crate::config::NotifyConfig;
-use crate::error::NotifyError;
-use crate::models::{ChannelType, Notification};
+use crate::adapters::{Sender, SenderRegistry};
+use crate::config::NotifyConsumerConfig;
+use crate::error::{NotifyError, NotifyResult};
+use crate::kafka::envelope::{self, Rejection};
+use crate::kafka::{CatchUnwind, RetryPolicy, WorkerPool};
+use crate::models::{
+    one_or_many, schedule_time, ChannelType, Notification, NotificationStatus, RecipientResult,
+    SendResult,
+};
+use crate::services::{
+    Claim, DeadLetterEntry, DeadLetterService, FallbackOrchestrator, IdempotencyService,
+    NotificationRecordService, PoisonMessageService, RateLimiter, ResultEventService, SendAttempt,
+    TemplateService,
+};
+use crate::shutdown::{self, InFlight, ShutdownCoordinator};
 use async_trait::async_trait;
 use fbc_starter::{KafkaMessageHandler, Message as KafkaMessage};
+use std::borrow::Cow;
+use std::collections::BTreeMap;
+use std::future::Future;
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 use tracing::{error, info, warn};
 
 /// Kafka 消息处理器上下文
-/// 包含所有消息发送器
+/// 持有发送器注册表，按渠道类型分发消息
 pub struct NotificationHandlerContext {
-    email_sender: Option<EmailSender>,
-    sms_sender: Option<SmsSender>,
-    feishu_sender: Option<FeishuSender>,
-    dingding_sender: Option<DingdingSender>,
-    /// 邮件配置（用于获取默认发件人）
-    email_config: Option<crate::config::EmailConfig>,
+    registry: SenderRegistry,
+    /// 通知模板服务（未配置数据库时为 None）
+    templates: Option<Arc<TemplateService>>,
+    /// 发送记录服务（未配置数据库时为 None）
+    records: Option<Arc<NotificationRecordService>>,
+    /// 发送结果事件服务（未启用时为 None）
+    events: Option<Arc<ResultEventService>>,
+    /// 幂等去重服务（未配置数据库和 Redis 时为 None）
+    idempotency: Option<Arc<IdempotencyService>>,
+    /// 发送限流（未配置限流规则时为 None）
+    rate_limiter: Option<Arc<RateLimiter>>,
+    /// Kafka 消息超出限流时在消费者内最长等待时间
+    max_rate_limit_wait: Duration,
+}
+
+/// 超出限流时的处理方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OverLimit {
+    /// 直接拒绝（HTTP 请求）
+    Reject,
+    /// 等待令牌，需等待过久时写入定时记录延后发送（Kafka 消息、定时通知）
+    Delay,
+}
+
+/// 重试后仍发送失败的结果
+#[derive(Debug)]
+pub struct SendFailure {
+    /// 通知 ID（未启用发送记录或未写入记录时为 None）
+    pub id: Option<i64>,
+    /// 已尝试次数
+    pub attempts: u32,
+    /// 最后一次失败的错误
+    pub error: NotifyError,
 }
 
 impl NotificationHandlerContext {
     /// 创建处理器上下文
-    pub fn new(config: &NotifyConfig) -> Self {
+    ///
+    /// # 参数
+    /// - `registry`: 已注册发送器的注册表
+    pub fn new(registry: SenderRegistry) -> Self {
         Self {
-            email_sender: config
-                .notify
-                .email
-                .as_ref()
-                .map(|cfg| EmailSender::new(cfg)),
-            sms_sender: config.notify.sms.clone().map(|cfg| SmsSender::new(cfg)),
-            feishu_sender: config
-                .notify
-                .feishu
-                .clone()
-                .map(|cfg| FeishuSender::new(cfg)),
-            dingding_sender: config
-                .notify
-                .dingding
-                .clone()
-                .map(|cfg| DingdingSender::new(cfg)),
-            email_config: config.notify.email.clone(),
-        }
-    }
-
-    /// 发送通知消息
-    /// 供 HTTP handlers 和 Kafka handlers 使用
-    pub async fn send(&self, notification: &Notification) -> Result<(), NotifyError> {
-        match notification.channel {
-            ChannelType::Email => {
-                let sender = self.email_sender.as_ref().ok_or_else(|| {
-                    NotifyError::Config("Email sender not configured".to_string())
-                })?;
-
-                // 如果 from 为空，使用配置的默认发件人
-                let notification = if notification.from.is_empty() {
-                    let from = self
-                        .email_config
-                        .as_ref()
-                        .map(|cfg| cfg.smtp_user.clone())
-                        .unwrap_or_else(|| "noreply@example.com".to_string());
-                    Notification {
-                        from,
-                        ..notification.clone()
-                    }
-                } else {
-                    notification.clone()
+            registry,
+            templates: None,
+            records: None,
+            events: None,
+            idempotency: None,
+            rate_limiter: None,
+            max_rate_limit_wait: Duration::ZERO,
+        }
+    }
+
+    /// 启用通知模板渲染
+    pub fn with_templates(mut self, templates: Arc<TemplateService>) -> Self {
+        self.templates = Some(templates);
+        self
+    }
+
+    /// 启用发送记录
+    pub fn with_records(mut self, records: Arc<NotificationRecordService>) -> Self {
+        self.records = Some(records);
+        self
+    }
+
+    /// 启用发送结果事件
+    pub fn with_events(mut self, events: Arc<ResultEventService>) -> Self {
+        self.events = Some(events);
+        self
+    }
+
+    /// 启用幂等去重
+    pub fn with_idempotency(mut self, idempotency: Arc<IdempotencyService>) -> Self {
+        self.idempotency = Some(idempotency);
+        self
+    }
+
+    /// 启用发送限流
+    ///
+    /// # 参数
+    /// - `rate_limiter`: 发送限流
+    /// - `max_wait`: Kafka 消息超出限流时在消费者内最长等待时间
+    pub fn with_rate_limiter(mut self, rate_limiter: Arc<RateLimiter>, max_wait: Duration) -> Self {
+        self.rate_limiter = Some(rate_limiter);
+        self.max_rate_limit_wait = max_wait;
+        self
+    }
+
+    /// 获取发送器注册表
+    pub fn registry(&self) -> &SenderRegistry {
+        &self.registry
+    }
+
+    /// 发送通知消息（只尝试一次），返回通知 ID 及逐个接收者的发送结果
+    /// 供 HTTP handlers 使用，失败由调用方决定是否重试
+    ///
+    /// 计划发送时间晚于当前时间时只写入定时记录，返回通知 ID 及空的接收者结果；
+    /// 超出限流时返回限流错误
+    pub async fn send(&self, notification: &Notification) -> Result<SendResult, NotifyError> {
+        self.send_with(notification, &RetryPolicy::none(), OverLimit::Reject)
+            .await
+            .map_err(|failure| failure.error)
+    }
+
+    /// 按重试策略发送通知消息，超出限流时按 `over_limit` 处理
+    /// 可重试错误按指数退避重试，耗尽后返回 SendFailure；
+    /// Kafka 消息使用 `OverLimit::Delay`，超出限流时等待或延后发送
+    pub async fn send_with(
+        &self,
+        notification: &Notification,
+        policy: &RetryPolicy,
+        over_limit: OverLimit,
+    ) -> Result<SendResult, SendFailure> {
+        self.deliver(notification, || {
+            self.send_and_record(notification, policy, None, over_limit)
+        })
+        .await
+    }
+
+    /// 异步发送：校验后写入排队记录立即返回通知 ID，由定时调度器尽快发送
+    /// 供 HTTP handlers 使用（`async=true`）
+    ///
+    /// 计划发送时间晚于当前时间时与同步发送相同，写入定时记录
+    pub async fn enqueue(&self, notification: &Notification) -> Result<SendResult, NotifyError> {
+        self.registry.resolve(notification)?;
+        self.deliver(notification, || async {
+            let queued = if notification.is_scheduled() {
+                self.persist(notification, NotificationStatus::Scheduled)
+                    .await
+            } else {
+                let queued = Notification {
+                    send_at: Some(chrono::Utc::now().timestamp_millis()),
+                    ..notification.clone()
                 };
+                self.persist(&queued, NotificationStatus::Pending).await
+            };
+            queued.map_err(|error| SendFailure {
+                id: None,
+                attempts: 0,
+                error,
+            })
+        })
+        .await
+        .map_err(|failure| failure.error)
+    }
 
-                sender.send(&notification).await?;
+    /// 发送到期的定时通知（复用定时记录，不再写入新记录）
+    /// 供定时调度器使用
+    pub async fn send_scheduled(
+        &self,
+        id: i64,
+        notification: &Notification,
+        policy: &RetryPolicy,
+    ) -> Result<SendResult, SendFailure> {
+        self.send_and_record(notification, policy, Some(id), OverLimit::Delay)
+            .await
+    }
+
+    /// 执行 `send`（带幂等键时先去重）
+    ///
+    /// 去重窗口内的重复请求直接返回首次发送结果；
+    /// 发送失败时释放幂等键，相同幂等键可重新发送；因限流延后发送时不保存结果
+    async fn deliver<F, Fut>(
+        &self,
+        notification: &Notification,
+        send: F,
+    ) -> Result<SendResult, SendFailure>
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = Result<SendResult, SendFailure>>,
+    {
+        let failure = |error| SendFailure {
+            id: None,
+            attempts: 0,
+            error,
+        };
+        let Some(key) = IdempotencyService::key(notification).map_err(failure)? else {
+            return send().await;
+        };
+        let idempotency = self.idempotency.as_ref().ok_or_else(|| {
+            failure(NotifyError::Config(
+                "幂等去重未启用（未配置数据库或 Redis）".to_string(),
+            ))
+        })?;
+
+        if let Claim::Completed(result) = idempotency.claim(&key).await.map_err(failure)? {
+            info!(
+                "Duplicate notification skipped: idempotency_key={}, id={:?}",
+                key, result.id
+            );
+            return Ok(result);
+        }
+
+        let outcome = send().await;
+        let stored = match &outcome {
+            // 延后发送尚未完成，保持占用（占用超时后才允许重新发送），避免重复请求得到空结果
+            Ok(result) if result.deferred => Ok(()),
+            Ok(result) => idempotency.complete(&key, result).await,
+            Err(_) => idempotency.release(&key).await,
+        };
+        if let Err(e) = stored {
+            warn!("Failed to update idempotency key {}: {}", key, e);
+        }
+        outcome
+    }
+
+    /// 发送通知消息并记录结果
+    ///
+    /// 启用发送记录时，发送前写入记录、每次重试递增尝试次数、结束后回写各接收者的结果；
+    /// 记录读写失败只记日志，不影响消息发送。
+    /// 启用发送结果事件时，每次尝试后发布各接收者的结果事件。
+    /// 只有全部接收者失败（`send_batch` 返回 Err）时才重试，部分失败视为发送完成。
+    /// `record_id` 为已有的发送记录（定时通知到期发送时），为 None 时写入新记录或定时记录。
+    /// 每次尝试前获取限流令牌，超出限流时按 `over_limit` 拒绝、等待或改为定时发送。
+    async fn send_and_record(
+        &self,
+        notification: &Notification,
+        policy: &RetryPolicy,
+        record_id: Option<i64>,
+        over_limit: OverLimit,
+    ) -> Result<SendResult, SendFailure> {
+        let (instance, sender) = match self.registry.resolve(notification) {
+            Ok(resolved) => resolved,
+            Err(e) => return Err(self.fail_early(notification, record_id, e).await),
+        };
+
+        if record_id.is_none() && notification.is_scheduled() {
+            return self
+                .persist(notification, NotificationStatus::Scheduled)
+                .await
+                .map_err(|error| SendFailure {
+                    id: None,
+                    attempts: 0,
+                    error,
+                });
+        }
+
+        let notification = match self.render(notification).await {
+            Ok(notification) => notification,
+            Err(e) => return Err(self.fail_early(notification, record_id, e).await),
+        };
+        let id = match (record_id, &self.records) {
+            (Some(id), _) => Some(id),
+            (None, Some(records)) => records
+                .create(&notification, NotificationStatus::Sending)
+                .await
+                .map_err(|e| warn!("Failed to create notification record: {}", e))
+                .ok()
+                .inspect(|id| shutdown::report_record(*id)),
+            (None, None) => None,
+        };
+
+        let mut attempts = 1;
+        let outcome = loop {
+            match self
+                .throttle(&notification, &instance, id, over_limit)
+                .await
+            {
+                Ok(true) => {}
+                Ok(false) => {
+                    // 已改为定时发送，由调度器到期后发送
+                    return Ok(SendResult::deferred(id));
+                }
+                Err(e) => break Err(e),
             }
-            ChannelType::Sms => {
-                let sender = self
-                    .sms_sender
-                    .as_ref()
-                    .ok_or_else(|| NotifyError::Config("SMS sender not configured".to_string()))?;
-                sender.send(notification).await?;
+            let started = Instant::now();
+            let outcome = self
+                .send_with_failover(&notification, &instance, sender.clone())
+                .await;
+            let retry = matches!(
+                &outcome,
+                Err(e) if e.is_retryable() && attempts < policy.max_attempts
+            );
+            if let Some(events) = &self.events {
+                events.publish(SendAttempt {
+                    notification: &notification,
+                    notification_id: id,
+                    attempt: attempts,
+                    last: !retry,
+                    outcome: &outcome,
+                    latency: started.elapsed(),
+                });
             }
-            ChannelType::ImFeishu => {
-                let sender = self.feishu_sender.as_ref().ok_or_else(|| {
-                    NotifyError::Config("Feishu sender not configured".to_string())
-                })?;
-                sender.send(notification).await?;
+            let Err(e) = &outcome else {
+                break outcome;
+            };
+            if !retry {
+                break outcome;
+            }
+
+            let delay = policy.backoff(attempts);
+            warn!(
+                "Send attempt {}/{} failed, retrying in {:?}: id={:?}, channel={:?}, error={}",
+                attempts, policy.max_attempts, delay, id, notification.channel, e
+            );
+            tokio::time::sleep(delay).await;
+            attempts += 1;
+            if let (Some(records), Some(id)) = (&self.records, id) {
+                if let Err(e) = records.mark_sending(id).await {
+                    warn!("Failed to update notification record {}: {}", id, e);
+                }
+            }
+        };
+
+        if let (Some(records), Some(id)) = (&self.records, id) {
+            if let Err(e) = records.complete(id, &outcome).await {
+                warn!("Failed to update notification record {}: {}", id, e);
+            }
+        }
+        outcome
+            .map(|results| SendResult::new(id, results))
+            .map_err(|error| SendFailure {
+                id,
+                attempts,
+                error,
+            })
+    }
+
+    /// 通过实例发送，实例返回连接错误或临时错误时依次改用其故障转移实例
+    ///
+    /// 改用故障转移实例前同样获取该实例的限流令牌，超出限流时停止故障转移，返回原错误由重试策略处理
+    async fn send_with_failover(
+        &self,
+        notification: &Notification,
+        instance: &str,
+        sender: Arc<dyn Sender>,
+    ) -> NotifyResult<Vec<RecipientResult>> {
+        let mut outcome = sender.send_batch(notification).await;
+        let mut visited = vec![instance];
+        while let Err(e) = &outcome {
+            if !e.is_retryable() {
+                break;
+            }
+            let current = visited[visited.len() - 1];
+            let Some((failover, sender)) = self.registry.failover(notification.channel, current)
+            else {
+                break;
+            };
+            if visited.contains(&failover) {
+                break;
+            }
+            if let Some(limiter) = &self.rate_limiter {
+                if let Err(limited) = limiter.acquire(
+                    notification.channel,
+                    failover,
+                    &notification.all_recipients(),
+                ) {
+                    warn!(
+                        "Send via {} failed, failover to {} skipped: channel={:?}, error={}, reason={}",
+                        current, failover, notification.channel, e, limited
+                    );
+                    break;
+                }
+            }
+            warn!(
+                "Send via {} failed, failing over to {}: channel={:?}, error={}",
+                current, failover, notification.channel, e
+            );
+            outcome = sender.send_batch(notification).await;
+            visited.push(failover);
+        }
+        outcome
+    }
+
+    /// 获取限流令牌
+    ///
+    /// # 返回
+    /// - `Ok(true)`: 已获取令牌，继续发送
+    /// - `Ok(false)`: 需等待过久，已将发送记录改为定时发送
+    /// - `Err(NotifyError)`: 超出限流（`OverLimit::Reject`）或改为定时发送失败
+    async fn throttle(
+        &self,
+        notification: &Notification,
+        instance: &str,
+        id: Option<i64>,
+        over_limit: OverLimit,
+    ) -> Result<bool, NotifyError> {
+        let Some(limiter) = &self.rate_limiter else {
+            return Ok(true);
+        };
+        let recipients = notification.all_recipients();
+        loop {
+            let error = match limiter.acquire(notification.channel, instance, &recipients) {
+                Ok(()) => return Ok(true),
+                Err(e) => e,
+            };
+            let retry_after = match &error {
+                NotifyError::ChannelRateLimited { retry_after_ms, .. }
+                | NotifyError::RecipientRateLimited { retry_after_ms, .. } => {
+                    Duration::from_millis(*retry_after_ms)
+                }
+                _ => return Err(error),
+            };
+            if over_limit == OverLimit::Reject {
+                return Err(error);
             }
-            ChannelType::ImDingding => {
-                let sender = self.dingding_sender.as_ref().ok_or_else(|| {
-                    NotifyError::Config("Dingding sender not configured".to_string())
-                })?;
-                sender.send(notification).await?;
+
+            if retry_after > self.max_rate_limit_wait {
+                if let (Some(records), Some(id)) = (&self.records, id) {
+                    let send_at =
+                        chrono::Utc::now().timestamp_millis() + retry_after.as_millis() as i64;
+                    records.reschedule(id, send_at).await?;
+                    info!(
+                        "Notification delayed by rate limit: id={}, channel={:?}, send_at={}, reason={}",
+                        id, notification.channel, send_at, error
+                    );
+                    return Ok(false);
+                }
             }
-            _ => {
-                return Err(NotifyError::Config(format!(
-                    "Unsupported channel type: {:?}",
-                    notification.channel
-                )));
+            warn!(
+                "Rate limited, waiting {:?}: id={:?}, channel={:?}, reason={}",
+                retry_after, id, notification.channel, error
+            );
+            tokio::time::sleep(retry_after).await;
+        }
+    }
+
+    /// 发送前失败（无可用发送器、模板渲染失败等）时写入失败记录，保证每条通知都有记录可查
+    async fn fail_early(
+        &self,
+        notification: &Notification,
+        record_id: Option<i64>,
+        error: NotifyError,
+    ) -> SendFailure {
+        let id = match (record_id, &self.records) {
+            (_, None) => None,
+            (Some(id), Some(_)) => Some(id),
+            (None, Some(records)) => records
+                .create(notification, NotificationStatus::Failed)
+                .await
+                .map_err(|e| warn!("Failed to create notification record: {}", e))
+                .ok(),
+        };
+        if let (Some(id), Some(records)) = (id, &self.records) {
+            if let Err(e) = records.fail(id, &error).await {
+                warn!("Failed to update notification record {}: {}", id, e);
             }
         }
-        Ok(())
+        SendFailure {
+            id,
+            attempts: 0,
+            error,
+        }
+    }
+
+    /// 写入定时记录或排队记录，由定时调度器到期后发送（模板在发送时渲染）
+    async fn persist(
+        &self,
+        notification: &Notification,
+        status: NotificationStatus,
+    ) -> Result<SendResult, NotifyError> {
+        let records = self.records.as_ref().ok_or_else(|| {
+            NotifyError::Config("定时发送和异步发送未启用（未配置数据库）".to_string())
+        })?;
+        let id = records.create(notification, status).await?;
+        info!(
+            "Notification {}: id={}, channel={:?}, send_at={:?}",
+            if status == NotificationStatus::Pending {
+                "queued"
+            } else {
+                "scheduled"
+            },
+            id,
+            notification.channel,
+            notification.send_at
+        );
+        Ok(SendResult::new(Some(id), Vec::new()))
+    }
+
+    /// 模板消息先渲染出 subject/body，非模板消息原样返回
+    async fn render<'a>(
+        &self,
+        notification: &'a Notification,
+    ) -> Result<Cow<'a, Notification>, NotifyError> {
+        let Some(code) = notification.template_code.as_deref() else {
+            return Ok(Cow::Borrowed(notification));
+        };
+        let templates = self
+            .templates
+            .as_ref()
+            .ok_or_else(|| NotifyError::Config("通知模板未启用（未配置数据库）".to_string()))?;
+
+        let rendered = templates
+            .render(
+                code,
+                notification.channel,
+                notification.locale.as_deref(),
+                &notification.variables,
+            )
+            .await?;
+        Ok(Cow::Owned(Notification {
+            subject: rendered.subject,
+            body: rendered.body,
+            ..notification.clone()
+        }))
     }
 }
 
 /// Kafka 通知消息处理器
 /// 实现 KafkaMessageHandler trait，由 fbc-starter 自动管理订阅和消息分发
+///
+/// 每个配置的消费者（如 high、bulk）对应一个处理器实例，共享同一个降级编排；
+/// 解码后的通知提交到消费者自己的工作池，按渠道隔离并发发送
 pub struct NotificationHandler {
-    context: Arc<NotificationHandlerContext>,
+    /// 消费者名称
+    name: String,
+    /// 订阅的 topic
+    topics: Vec<String>,
+    /// 消费者组 ID
+    group_id: String,
+    dispatcher: Arc<Dispatcher>,
+    workers: Arc<WorkerPool>,
+    poison: Arc<PoisonMessageService>,
+    /// 优雅关闭协调器（未设置时不参与优雅关闭）
+    shutdown: Option<Arc<ShutdownCoordinator>>,
+}
+
+/// 在工作池中执行的发送逻辑
+struct Dispatcher {
+    /// 消费者名称
+    name: String,
+    /// 重试策略
+    retry: RetryPolicy,
+    orchestrator: Arc<FallbackOrchestrator>,
+    dead_letters: Arc<DeadLetterService>,
 }
 
 impl NotificationHandler {
     /// 创建新的处理器
     ///
     /// # 参数
-    /// - `context`: 处理器上下文
-    pub fn new(context: Arc<NotificationHandlerContext>) -> Self {
-        Self { context }
+    /// - `name`: 消费者名称
+    /// - `config`: 消费者配置（topic、消费者组、重试次数）
+    /// - `retry`: 重试策略（消费者配置了 max_attempts 时覆盖其最大尝试次数）
+    /// - `orchestrator`: 降级编排
+    /// - `dead_letters`: 死信服务（重试耗尽或不可重试的失败消息写入死信）
+    /// - `poison`: 毒消息服务（无法解析或校验失败的消息转发至毒消息 topic）
+    /// - `workers`: 发送工作池
+    pub fn new(
+        name: impl Into<String>,
+        config: &NotifyConsumerConfig,
+        mut retry: RetryPolicy,
+        orchestrator: Arc<FallbackOrchestrator>,
+        dead_letters: Arc<DeadLetterService>,
+        poison: Arc<PoisonMessageService>,
+        workers: Arc<WorkerPool>,
+    ) -> Self {
+        if let Some(max_attempts) = config.max_attempts {
+            retry.max_attempts = max_attempts.max(1);
+        }
+        let name = name.into();
+        Self {
+            topics: config.topic_list(),
+            group_id: config.group_id.clone(),
+            dispatcher: Arc::new(Dispatcher {
+                name: name.clone(),
+                retry,
+                orchestrator,
+                dead_letters,
+            }),
+            workers,
+            poison,
+            shutdown: None,
+            name,
+        }
+    }
+
+    /// 参与优雅关闭：关闭开始后排队中的任务不再发送而是保存待恢复，进行中的任务登记后等待完成
+    pub fn with_shutdown(mut self, shutdown: Arc<ShutdownCoordinator>) -> Self {
+        self.shutdown = Some(shutdown);
+        self
+    }
+}
+
+impl Dispatcher {
+    /// 发送通知，失败时写入死信
+    ///
+    /// 相同幂等键的消息正在由其他消费者处理（如重平衡后重复投递）时直接跳过
+    async fn dispatch(&self, message: &KafkaMessage, notification: Notification) {
+        // 发送过程中 panic 同样按发送失败处理（进入死信），避免消息静默丢失
+        let outcome = CatchUnwind::new(dispatch(&self.orchestrator, notification, &self.retry))
+            .await
+            .unwrap_or_else(|panic| {
+                Err(SendFailure {
+                    id: None,
+                    attempts: 1,
+                    error: NotifyError::Send(format!("send task panicked: {}", panic)),
+                })
+            });
+        if let Err(failure) = outcome {
+            if let NotifyError::IdempotencyInProgress(key) = &failure.error {
+                info!(
+                    "Duplicate notification in progress, skipped: consumer={}, idempotency_key={}",
+                    self.name, key
+                );
+                return;
+            }
+            error!(
+                "Failed to dispatch notification after {} attempt(s): consumer={}, id={:?}, error={}",
+                failure.attempts, self.name, failure.id, failure.error
+            );
+            self.dead_letters
+                .publish(DeadLetterEntry {
+                    message,
+                    error: &failure.error,
+                    attempts: failure.attempts,
+                    notification_id: failure.id,
+                })
+                .await;
+        }
     }
 }
 
@@ -120,55 +642,96 @@ impl KafkaMessageHandler for NotificationHandler {
     /// 返回此 handler 处理的 topic 列表
     /// 框架会自动调用此方法来获取需要订阅的 topics
     fn topics(&self) -> Vec<String> {
-        // 从环境变量获取，或使用默认值
-        vec!["ms-notify-topic".to_string()]
+        self.topics.clone()
     }
 
     /// 返回此 handler 使用的消费者组ID
     /// 框架会自动调用此方法来创建对应的消费者组
     fn group_id(&self) -> String {
-        // 从环境变量获取，或使用默认值
-        "ms-notify-group-1".to_string()
+        self.group_id.clone()
     }
 
     /// 处理接收到的消息（由 fbc-starter 自动调用）
+    ///
+    /// 校验通过后提交到工作池即返回；偏移量由 fbc-starter 在调用前提交，排队中的任务
+    /// 只在优雅关闭时保存，进程崩溃时丢失（至多一次，见 [`crate::config::WorkerPoolConfig`]）
     async fn handle(&self, message: KafkaMessage) {
         info!(
-            "Received Kafka message: topic={}, from={}",
-            message.topic, message.from
+            "Received Kafka message: consumer={}, topic={}, from={}",
+            self.name, message.topic, message.from
         );
 
-        // 解析消息数据（message.data 是 serde_json::Value）
-        // 支持两种格式：
-        // 1. 直接是 Notification 格式：{from, to, subject, body, channel}
-        // 2. 兼容 flare-worker 格式：{id, timestamp, source, channel, payload}
-        match serde_json::from_value::<Notification>(message.data.clone()) {
+        match decode(&message.data) {
             Ok(notification) => {
-                // 直接使用 Notification
-                if let Err(e) = dispatch(&self.context, notification).await {
-                    error!("Failed to dispatch notification: {}", e);
-                }
-            }
-            Err(_) => {
-                // 尝试解析为 flare-worker 格式
-                match parse_flare_format(&message.data) {
-                    Ok(notification) => {
-                        if let Err(e) = dispatch(&self.context, notification).await {
-                            error!("Failed to dispatch notification: {}", e);
+                let channel = notification.channel;
+                let key = ordering_key(&message.data, &notification);
+                let dispatcher = self.dispatcher.clone();
+                // 提交前登记，排队中的任务同样参与优雅关闭
+                let guard = self.shutdown.as_ref().map(|shutdown| {
+                    shutdown.track(InFlight {
+                        notification: notification.clone(),
+                        record_id: None,
+                        message: Some(message.clone()),
+                    })
+                });
+                let job = Box::pin(async move {
+                    match guard {
+                        Some(guard) if guard.is_stopping() => {
+                            guard.persist().await;
                         }
+                        Some(guard) => guard.run(dispatcher.dispatch(&message, notification)).await,
+                        None => dispatcher.dispatch(&message, notification).await,
                     }
-                    Err(e) => {
-                        warn!(
-                            "Failed to parse notification message: {}, data: {}",
-                            e, message.data
-                        );
-                    }
-                }
+                });
+                self.workers.submit(channel, key.as_deref(), job).await;
+            }
+            Err(rejection) => {
+                warn!(
+                    "Rejected notification message: consumer={}, message_id={:?}, reasons={}",
+                    self.name, rejection.message_id, rejection
+                );
+                self.poison.publish(&message, &rejection).await;
             }
         }
     }
 }
 
+/// 解析消息数据（message.data 是 serde_json::Value）
+///
+/// 支持三种格式：
+/// 1. 信封格式：{schema_version, message_id, correlation_id, tenant, created_at, payload}
+/// 2. 直接是 Notification 格式（旧格式）：{from, to, subject, body, channel}
+/// 3. 兼容 flare-worker 格式（旧格式）：{id, timestamp, source, channel, payload}
+///
+/// 旧格式解析后执行与信封 v1 payload 相同的内容校验
+fn decode(data: &serde_json::Value) -> Result<Notification, Rejection> {
+    if envelope::is_envelope(data) {
+        return envelope::decode(data);
+    }
+    serde_json::from_value::<Notification>(data.clone())
+        .or_else(|_| parse_flare_format(data))
+        .map_err(Rejection::legacy)
+        .and_then(envelope::validate_legacy)
+}
+
+/// 消息的顺序键：信封的 `key` 字段，未设置时为渠道及接收者
+/// （发给同一接收者的消息按到达顺序发送），无接收者的消息不保证顺序
+fn ordering_key(data: &serde_json::Value, notification: &Notification) -> Option<String> {
+    if envelope::is_envelope(data) {
+        if let Some(key) = data.get("key").and_then(serde_json::Value::as_str) {
+            return Some(key.to_string());
+        }
+    }
+    if notification.to.is_empty() {
+        return None;
+    }
+    Some(format!(
+        "{}:{}",
+        notification.channel.as_str(),
+        notification.to.join(",")
+    ))
+}
+
 /// 解析 flare-worker 格式的消息
 fn parse_flare_format(data: &serde_json::Value) -> Result<Notification, NotifyError> {
     let channel = data
@@ -179,66 +742,186 @@ fn parse_flare_format(data: &serde_json::Value) -> Result<Notification, NotifyEr
     let payload = data
         .get("payload")
         .ok_or_else(|| NotifyError::Config("missing 'payload' field".to_string()))?;
+    // flare-worker 消息 ID 作为关联 ID 带回结果事件
+    let base = Notification {
+        correlation_id: require_str(data, "id").ok(),
+        idempotency_key: require_str(data, "idempotency_key").ok(),
+        send_at: schedule_time(
+            payload.get("send_at").and_then(|v| v.as_i64()),
+            payload.get("delay").and_then(|v| v.as_u64()),
+        ),
+        ..Notification::new(channel)
+    };
+
+    // 模板消息：subject/body 由通知模板渲染
+    if let Some(template_code) = payload.get("template_code").and_then(|v| v.as_str()) {
+        let variables = match payload.get("variables") {
+            Some(serde_json::Value::Object(map)) => map.clone(),
+            None | Some(serde_json::Value::Null) => serde_json::Map::new(),
+            Some(_) => {
+                return Err(NotifyError::Config(
+                    "invalid 'variables' field in payload: expected object".to_string(),
+                ))
+            }
+        };
+        return Ok(Notification {
+            from: require_str(payload, "from").unwrap_or_default(),
+            to: optional_list(payload, "to")?,
+            cc: optional_list(payload, "cc")?,
+            bcc: optional_list(payload, "bcc")?,
+            sms_template_code: require_str(payload, "sms_template_code").ok(),
+            template_code: Some(template_code.to_string()),
+            locale: require_str(payload, "locale").ok(),
+            variables,
+            ..base
+        });
+    }
 
     match channel {
         ChannelType::Email => {
+            // 未指定发件人时留空，由发送账号的默认发件人发送
             let from = payload
                 .get("from")
                 .and_then(|v| v.as_str())
                 .map(|s| s.to_string())
-                .unwrap_or_else(|| "noreply@example.com".to_string());
-            let to = require_str(payload, "to")?;
+                .unwrap_or_default();
+            let to = require_list(payload, "to")?;
             let subject = require_str(payload, "subject")?;
             let body = require_str(payload, "body")?;
             Ok(Notification {
                 from,
                 to,
+                cc: optional_list(payload, "cc")?,
+                bcc: optional_list(payload, "bcc")?,
                 subject,
                 body,
-                channel,
+                ..base
             })
         }
         ChannelType::Sms => {
-            let to = require_str(payload, "to")?;
-            let body = require_str(payload, "param").or_else(|_| require_str(payload, "body"))?;
+            let to = require_list(payload, "to")?;
+            let sms_template_code = require_str(payload, "sms_template_code").ok();
+            // param 为对象时作为结构化模板参数，为字符串时兼容旧的 JSON 字符串格式
+            let (sms_params, body) = match payload.get("param").or_else(|| payload.get("params")) {
+                Some(serde_json::Value::Object(map)) => (string_map(map)?, String::new()),
+                _ => (
+                    BTreeMap::new(),
+                    require_str(payload, "param")
+                        .or_else(|_| require_str(payload, "body"))
+                        .unwrap_or_default(),
+                ),
+            };
             Ok(Notification {
-                from: String::new(),
                 to,
-                subject: String::new(),
                 body,
-                channel,
+                sms_template_code,
+                sms_params,
+                ..base
             })
         }
-        ChannelType::ImFeishu | ChannelType::ImDingding => {
+        ChannelType::ImFeishu | ChannelType::ImDingding | ChannelType::ImWechat => {
             let body = require_str(payload, "text").or_else(|_| require_str(payload, "body"))?;
+            Ok(Notification { body, ..base })
+        }
+        ChannelType::Push | ChannelType::SiteMessage => {
+            let to = require_list(payload, "to")?;
+            let subject = require_str(payload, "subject")
+                .or_else(|_| require_str(payload, "title"))
+                .unwrap_or_default();
+            let body = require_str(payload, "body")?;
             Ok(Notification {
-                from: String::new(),
-                to: String::new(),
-                subject: String::new(),
+                from: require_str(payload, "from").unwrap_or_default(),
+                to,
+                subject,
                 body,
-                channel,
+                ..base
             })
         }
-        _ => Err(NotifyError::Config(format!(
-            "Unsupported channel type: {:?}",
-            channel
-        ))),
     }
 }
 
 /// 分发消息到对应的处理器
 async fn dispatch(
-    ctx: &NotificationHandlerContext,
+    orchestrator: &FallbackOrchestrator,
     notification: Notification,
-) -> Result<(), NotifyError> {
-    ctx.send(&notification).await?;
-    info!(
-        "Notification sent successfully: channel={:?}, to={}",
-        notification.channel, notification.to
-    );
+    retry: &RetryPolicy,
+) -> Result<(), SendFailure> {
+    let SendResult {
+        id,
+        results,
+        fallback,
+        deferred,
+    } = orchestrator.send_with_retry(&notification, retry).await?;
+    if notification.is_scheduled() || deferred {
+        // 定时通知已写入定时记录，超出限流的通知已延后，到期后由调度器发送
+        return Ok(());
+    }
+    let failed: Vec<_> = results.iter().filter(|r| !r.success).collect();
+    if failed.is_empty() {
+        info!(
+            "Notification sent successfully: id={:?}, channel={:?}, recipients={}",
+            id,
+            notification.channel,
+            results.len()
+        );
+    } else {
+        warn!(
+            "Notification partially failed: id={:?}, channel={:?}, failed={}/{}, details={:?}",
+            id,
+            notification.channel,
+            failed.len(),
+            results.len(),
+            failed
+        );
+    }
+    for attempt in &fallback {
+        info!(
+            "Fallback step {}: id={:?}, channel={:?}, when={:?}, error={:?}",
+            attempt.step, attempt.id, attempt.channel, attempt.when, attempt.error
+        );
+    }
     Ok(())
 }
 
+/// 将 JSON 对象转换为字符串参数表（数字、布尔值转为字符串）
+fn string_map(
+    map: &serde_json::Map<String, serde_json::Value>,
+) -> Result<BTreeMap<String, String>, NotifyError> {
+    map.iter()
+        .map(|(k, v)| match v {
+            serde_json::Value::String(s) => Ok((k.clone(), s.clone())),
+            serde_json::Value::Number(_) | serde_json::Value::Bool(_) => {
+                Ok((k.clone(), v.to_string()))
+            }
+            _ => Err(NotifyError::Config(format!(
+                "invalid value for param '{}': expected string",
+                k
+            ))),
+        })
+        .collect()
+}
+
+/// 从 payload 中获取接收者列表（兼容单个字符串与字符串数组）
+fn require_list(payload: &serde_json::Value, key: &str) -> Result<Vec<String>, NotifyError> {
+    let list = optional_list(payload, key)?;
+    if list.is_empty() {
+        return Err(NotifyError::Config(format!(
+            "missing or invalid '{}' field in payload",
+            key
+        )));
+    }
+    Ok(list)
+}
+
+/// 从 payload 中获取可选的接收者列表，字段缺失时返回空列表
+fn optional_list(payload: &serde_json::Value, key: &str) -> Result<Vec<String>, NotifyError> {
+    match payload.get(key) {
+        None | Some(serde_json::Value::Null) => Ok(Vec::new()),
+        Some(value) => one_or_many(value)
+            .map_err(|e| NotifyError::Config(format!("invalid '{}' field in payload: {}", key, e))),
+    }
+}
+
 /// 从 payload 中获取字符串字段
 fn require_str(payload: &serde_json::Value, key: &str) -> Result<String, NotifyError> {
     payload
@@ -1,3 +1,9 @@
+mod envelope;
 mod handler;
+mod retry;
+mod worker_pool;
 
-pub use handler::{NotificationHandler, NotificationHandlerContext};
+pub use envelope::Rejection;
+pub use handler::{NotificationHandler, NotificationHandlerContext, OverLimit, SendFailure};
+pub use retry::RetryPolicy;
+pub use worker_pool::{CatchUnwind, WorkerPool};
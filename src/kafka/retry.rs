@@ -0,0 +1,112 @@
+use crate::config::RetryConfig;
+use std::time::Duration;
+
+/// 重试策略：指数退避 + 随机抖动
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    /// 最大尝试次数（含首次发送）
+    pub max_attempts: u32,
+    initial_backoff: Duration,
+    max_backoff: Duration,
+    multiplier: f64,
+    jitter: f64,
+}
+
+impl RetryPolicy {
+    /// 只尝试一次、不重试的策略
+    pub fn none() -> Self {
+        Self {
+            max_attempts: 1,
+            initial_backoff: Duration::ZERO,
+            max_backoff: Duration::ZERO,
+            multiplier: 1.0,
+            jitter: 0.0,
+        }
+    }
+
+    /// 第 `attempt` 次尝试失败后的等待时间（attempt 从 1 开始）
+    ///
+    /// `initial * multiplier^(attempt-1)`，不超过最大等待时间，再乘以 `1 ± jitter` 的随机系数
+    pub fn backoff(&self, attempt: u32) -> Duration {
+        let exponent = attempt.saturating_sub(1).min(32) as i32;
+        let base = self.initial_backoff.as_secs_f64() * self.multiplier.powi(exponent);
+        let base = base.min(self.max_backoff.as_secs_f64());
+        let factor = if self.jitter > 0.0 {
+            1.0 + self.jitter * (rand::random::<f64>() * 2.0 - 1.0)
+        } else {
+            1.0
+        };
+        Duration::from_secs_f64((base * factor).max(0.0))
+    }
+}
+
+impl From<&RetryConfig> for RetryPolicy {
+    fn from(config: &RetryConfig) -> Self {
+        Self {
+            max_attempts: config.max_attempts.max(1),
+            initial_backoff: Duration::from_millis(config.initial_backoff_ms),
+            max_backoff: Duration::from_millis(
+                config.max_backoff_ms.max(config.initial_backoff_ms),
+            ),
+            multiplier: config.multiplier.max(1.0),
+            jitter: config.jitter.clamp(0.0, 1.0),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn policy(jitter: f64) -> RetryPolicy {
+        RetryPolicy::from(&RetryConfig {
+            max_attempts: 5,
+            initial_backoff_ms: 100,
+            max_backoff_ms: 1000,
+            multiplier: 2.0,
+            jitter,
+            ..Default::default()
+        })
+    }
+
+    #[test]
+    fn grows_exponentially_up_to_max() {
+        let policy = policy(0.0);
+        let delays: Vec<u128> = (1..=6).map(|n| policy.backoff(n).as_millis()).collect();
+        assert_eq!(delays, vec![100, 200, 400, 800, 1000, 1000]);
+        assert_eq!(policy.backoff(0), Duration::from_millis(100));
+        assert_eq!(policy.backoff(u32::MAX), Duration::from_millis(1000));
+    }
+
+    #[test]
+    fn jitter_stays_within_bounds() {
+        let policy = policy(0.5);
+        for _ in 0..100 {
+            let delay = policy.backoff(2).as_millis();
+            assert!((100..=300).contains(&delay), "delay {} out of range", delay);
+        }
+    }
+
+    #[test]
+    fn normalizes_config() {
+        let policy = RetryPolicy::from(&RetryConfig {
+            max_attempts: 0,
+            initial_backoff_ms: 500,
+            max_backoff_ms: 100,
+            multiplier: 0.5,
+            jitter: 2.0,
+            ..Default::default()
+        });
+        assert_eq!(policy.max_attempts, 1);
+        assert_eq!(policy.max_backoff, Duration::from_millis(500));
+        assert_eq!(policy.multiplier, 1.0);
+        assert_eq!(policy.jitter, 1.0);
+    }
+
+    #[test]
+    fn none_does_not_retry() {
+        let policy = RetryPolicy::none();
+        assert_eq!(policy.max_attempts, 1);
+        assert_eq!(policy.backoff(3), Duration::ZERO);
+    }
+}
@@ -0,0 +1,244 @@
+use crate::config::WorkerPoolConfig;
+use crate::models::ChannelType;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::future::Future;
+use std::hash::{Hash, Hasher};
+use std::panic::AssertUnwindSafe;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use tokio::sync::mpsc;
+use tracing::{error, info, warn};
+
+/// 待执行的发送任务
+pub type Job = Pin<Box<dyn Future<Output = ()> + Send>>;
+
+/// 捕获 future 执行中的 panic，返回 panic 信息
+pub struct CatchUnwind<F>(Pin<Box<F>>);
+
+impl<F: Future> CatchUnwind<F> {
+    /// 包装 future
+    pub fn new(future: F) -> Self {
+        Self(Box::pin(future))
+    }
+}
+
+impl<F: Future> Future for CatchUnwind<F> {
+    type Output = Result<F::Output, String>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let inner = self.0.as_mut();
+        match std::panic::catch_unwind(AssertUnwindSafe(|| inner.poll(cx))) {
+            Ok(poll) => poll.map(Ok),
+            Err(payload) => Poll::Ready(Err(payload
+                .downcast_ref::<&str>()
+                .map(|s| s.to_string())
+                .or_else(|| payload.downcast_ref::<String>().cloned())
+                .unwrap_or_else(|| "unknown panic".to_string()))),
+        }
+    }
+}
+
+/// 隔离舱名称
+const BULKHEADS: [&str; 5] = ["email", "sms", "im", "push", "site_message"];
+
+/// 渠道所属的隔离舱
+fn bulkhead(channel: ChannelType) -> &'static str {
+    match channel {
+        ChannelType::Email => "email",
+        ChannelType::Sms => "sms",
+        ChannelType::ImFeishu | ChannelType::ImDingding | ChannelType::ImWechat => "im",
+        ChannelType::Push => "push",
+        ChannelType::SiteMessage => "site_message",
+    }
+}
+
+/// 隔离舱：固定数量的工作协程，每个工作协程有独立的有界队列并按顺序执行任务；
+/// 任务 panic 时计为失败，工作协程继续处理后续任务
+struct Bulkhead {
+    lanes: Vec<mpsc::Sender<Job>>,
+    /// 因 panic 或工作协程停止而丢失的任务数
+    failed: Arc<AtomicU64>,
+    /// 无顺序键的任务轮询分配
+    next: AtomicUsize,
+}
+
+impl Bulkhead {
+    fn spawn(name: &str, concurrency: usize, queue_size: usize) -> Self {
+        let concurrency = concurrency.max(1);
+        let lane_capacity = (queue_size / concurrency).max(1);
+        let failed = Arc::new(AtomicU64::new(0));
+        let lanes = (0..concurrency)
+            .map(|_| {
+                let (sender, mut receiver) = mpsc::channel::<Job>(lane_capacity);
+                let name = name.to_string();
+                let failed = failed.clone();
+                tokio::spawn(async move {
+                    while let Some(job) = receiver.recv().await {
+                        if let Err(panic) = CatchUnwind::new(job).await {
+                            let total = failed.fetch_add(1, Ordering::Relaxed) + 1;
+                            error!(
+                                "Job panicked: bulkhead={}, panic={}, failed_total={}",
+                                name, panic, total
+                            );
+                        }
+                    }
+                });
+                sender
+            })
+            .collect();
+        info!(
+            "Bulkhead started: name={}, concurrency={}, queue_size={}",
+            name,
+            concurrency,
+            lane_capacity * concurrency
+        );
+        Self {
+            lanes,
+            failed,
+            next: AtomicUsize::new(0),
+        }
+    }
+
+    /// 选择工作协程：相同顺序键总是进入同一队列
+    fn lane(&self, key: Option<&str>) -> &mpsc::Sender<Job> {
+        let index = match key {
+            Some(key) => {
+                let mut hasher = DefaultHasher::new();
+                key.hash(&mut hasher);
+                hasher.finish() as usize
+            }
+            None => self.next.fetch_add(1, Ordering::Relaxed),
+        };
+        &self.lanes[index % self.lanes.len()]
+    }
+}
+
+/// 发送工作池
+///
+/// 每个渠道隔离舱独立限制并发数和排队数；排队已满时提交任务会等待空位。
+/// 相同顺序键的任务在同一工作协程内按提交顺序执行
+///
+/// 注意：fbc-starter 为每条消息启动独立的处理任务，并在调用处理器时即提交偏移量，
+/// 排队已满不会暂停 Kafka 消费，等待提交和排队中的任务在进程崩溃时丢失（至多一次）
+pub struct WorkerPool {
+    bulkheads: HashMap<&'static str, Bulkhead>,
+}
+
+impl WorkerPool {
+    /// 创建工作池并启动全部工作协程（需在 tokio 运行时内调用）
+    ///
+    /// # 参数
+    /// - `name`: 工作池名称（消费者名称）
+    /// - `config`: 工作池配置
+    pub fn new(name: &str, config: &WorkerPoolConfig) -> Self {
+        for key in config.bulkheads.keys() {
+            if !BULKHEADS.contains(&key.as_str()) {
+                warn!("Unknown bulkhead in worker pool config ignored: {}", key);
+            }
+        }
+        let bulkheads = BULKHEADS
+            .into_iter()
+            .map(|bulkhead| {
+                let overrides = config.bulkheads.get(bulkhead).cloned().unwrap_or_default();
+                let pool = Bulkhead::spawn(
+                    &format!("{}/{}", name, bulkhead),
+                    overrides.concurrency.unwrap_or(config.concurrency),
+                    overrides.queue_size.unwrap_or(config.queue_size),
+                );
+                (bulkhead, pool)
+            })
+            .collect();
+        Self { bulkheads }
+    }
+
+    /// 提交任务到渠道所属的隔离舱，队列已满时等待
+    ///
+    /// # 参数
+    /// - `channel`: 渠道类型
+    /// - `key`: 顺序键（为 None 时不保证顺序，轮询分配）
+    /// - `job`: 发送任务
+    pub async fn submit(&self, channel: ChannelType, key: Option<&str>, job: Job) {
+        let bulkhead = &self.bulkheads[bulkhead(channel)];
+        if bulkhead.lane(key).send(job).await.is_err() {
+            let total = bulkhead.failed.fetch_add(1, Ordering::Relaxed) + 1;
+            error!(
+                "Worker stopped, job dropped: channel={:?}, failed_total={}",
+                channel, total
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+    use std::time::Duration;
+    use tokio::sync::oneshot;
+
+    fn pool(concurrency: usize) -> WorkerPool {
+        WorkerPool::new(
+            "test",
+            &WorkerPoolConfig {
+                concurrency,
+                queue_size: 64,
+                bulkheads: HashMap::new(),
+            },
+        )
+    }
+
+    #[tokio::test]
+    async fn same_key_jobs_run_in_submission_order() {
+        let pool = pool(4);
+        let order = Arc::new(Mutex::new(Vec::new()));
+        let (done_tx, done_rx) = oneshot::channel();
+        let mut done_tx = Some(done_tx);
+        for i in 0..20u64 {
+            let order = order.clone();
+            let done_tx = if i == 19 { done_tx.take() } else { None };
+            let job: Job = Box::pin(async move {
+                // 先提交的任务耗时更长，并发执行时会被后提交的任务超过
+                tokio::time::sleep(Duration::from_millis(20 - i)).await;
+                order.lock().unwrap().push(i);
+                if let Some(done_tx) = done_tx {
+                    let _ = done_tx.send(());
+                }
+            });
+            pool.submit(ChannelType::Email, Some("user-1"), job).await;
+        }
+        tokio::time::timeout(Duration::from_secs(5), done_rx)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(*order.lock().unwrap(), (0..20).collect::<Vec<_>>());
+    }
+
+    #[tokio::test]
+    async fn lane_survives_panicking_job() {
+        let pool = pool(1);
+        let job: Job = Box::pin(async { panic!("boom") });
+        pool.submit(ChannelType::Sms, Some("user-1"), job).await;
+
+        let (done_tx, done_rx) = oneshot::channel();
+        let job: Job = Box::pin(async move {
+            let _ = done_tx.send(());
+        });
+        pool.submit(ChannelType::Sms, Some("user-1"), job).await;
+
+        tokio::time::timeout(Duration::from_secs(5), done_rx)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(pool.bulkheads["sms"].failed.load(Ordering::Relaxed), 1);
+    }
+
+    #[tokio::test]
+    async fn catch_unwind_returns_panic_message() {
+        let result = CatchUnwind::new(async { panic!("send failed: {}", 42) }).await;
+        assert_eq!(result.unwrap_err(), "send failed: 42");
+        assert_eq!(CatchUnwind::new(async { 7 }).await.unwrap(), 7);
+    }
+}
@@ -1,5 +1,8 @@
+use crate::adapters::SenderRegistry;
 use crate::config::NotifyConfig;
-use crate::kafka::{NotificationHandler, NotificationHandlerContext};
+use crate::kafka::{NotificationHandler, RetryPolicy, WorkerPool};
+use crate::services::{NotificationScheduler, RateLimiter};
+use crate::state::AppState;
 use fbc_starter::{AppResult, Server};
 use std::sync::Arc;
 
@@ -10,23 +13,88 @@ mod handlers;
 mod kafka;
 mod models;
 mod router;
+mod services;
+mod shutdown;
+mod state;
 
 #[tokio::main]
 async fn main() -> AppResult<()> {
     // 加载配置
     let config = NotifyConfig::from_env()?;
 
-    // 创建 Kafka 处理器上下文
-    let context = Arc::new(NotificationHandlerContext::new(&config));
+    // 创建发送器和限流器（配置错误时直接启动失败）
+    let registry = SenderRegistry::from_config(&config.notify)?;
+    let rate_limiter = RateLimiter::new(&config.notify.rate_limit)?;
 
-    // 创建 HTTP 路由（需要在创建 handler 之前克隆 context）
-    let http_router = router::create_router(context.clone());
+    // 启动服务器，注册 Kafka 处理器和 HTTP 路由
+    let mut shutdown = None;
+    let result = Server::run(|builder| {
+        // 创建应用状态（需要框架初始化好的数据库连接池）
+        let app_state = Arc::new(AppState::new(
+            builder.app_state(),
+            &config,
+            registry,
+            rate_limiter,
+        ));
+        app_state.shutdown.listen();
+        shutdown = Some(app_state.shutdown.clone());
 
-    // 创建 Kafka 消息处理器
-    // topic 和 group_id 会由框架自动从 handler.topics() 和 handler.group_id() 获取
-    let handler: Arc<dyn fbc_starter::KafkaMessageHandler> =
-        Arc::new(NotificationHandler::new(context));
+        // 创建 HTTP 路由
+        let http_router = router::create_router(app_state.clone());
 
-    // 启动服务器，注册 Kafka 处理器和 HTTP 路由
-    Server::run(|builder| builder.with_kafka_handler(handler).http_router(http_router)).await
+        // 按配置为每个消费者创建 Kafka 消息处理器
+        // topic 和 group_id 会由框架自动从 handler.topics() 和 handler.group_id() 获取
+        let retry = RetryPolicy::from(&config.notify.retry);
+        let handlers: Vec<Arc<dyn fbc_starter::KafkaMessageHandler>> = config
+            .notify
+            .kafka
+            .consumers()
+            .into_iter()
+            .map(|(name, consumer)| {
+                tracing::info!(
+                    "Registering Kafka consumer: name={}, topics={}, group_id={}",
+                    name,
+                    consumer.topics,
+                    consumer.group_id
+                );
+                let workers = Arc::new(WorkerPool::new(&name, &config.notify.kafka.workers));
+                Arc::new(
+                    NotificationHandler::new(
+                        name,
+                        &consumer,
+                        retry.clone(),
+                        app_state.orchestrator.clone(),
+                        app_state.dead_letter_service.clone(),
+                        app_state.poison_service.clone(),
+                        workers,
+                    )
+                    .with_shutdown(app_state.shutdown.clone()),
+                ) as Arc<dyn fbc_starter::KafkaMessageHandler>
+            })
+            .collect();
+
+        // 定时发送调度器（需要数据库持久化定时通知）
+        if let Some(records) = &app_state.record_service {
+            NotificationScheduler::new(
+                app_state.orchestrator.clone(),
+                records.clone(),
+                app_state.shutdown.clone(),
+                app_state.scheduler_wakeup.clone(),
+                retry,
+                &config.notify.scheduler,
+            )
+            .spawn();
+        }
+
+        builder
+            .with_kafka_handlers(handlers)
+            .http_router(http_router)
+    })
+    .await;
+
+    // HTTP 服务已停止接收请求，等待 Kafka 和定时发送中的通知完成
+    if let Some(shutdown) = shutdown {
+        shutdown.drain().await;
+    }
+    result
 }
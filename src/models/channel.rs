@@ -19,3 +19,42 @@ pub enum ChannelType {
     /// 站内消息
     SiteMessage,
 }
+
+impl ChannelType {
+    /// 所有渠道类型
+    pub const ALL: [ChannelType; 7] = [
+        ChannelType::Email,
+        ChannelType::Sms,
+        ChannelType::ImFeishu,
+        ChannelType::ImDingding,
+        ChannelType::ImWechat,
+        ChannelType::Push,
+        ChannelType::SiteMessage,
+    ];
+
+    /// 渠道标识（与序列化值一致）
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ChannelType::Email => "email",
+            ChannelType::Sms => "sms",
+            ChannelType::ImFeishu => "im_feishu",
+            ChannelType::ImDingding => "im_dingding",
+            ChannelType::ImWechat => "im_wechat",
+            ChannelType::Push => "push",
+            ChannelType::SiteMessage => "site_message",
+        }
+    }
+
+    /// 渠道显示名称
+    pub fn display_name(&self) -> &'static str {
+        match self {
+            ChannelType::Email => "邮件",
+            ChannelType::Sms => "短信",
+            ChannelType::ImFeishu => "飞书",
+            ChannelType::ImDingding => "钉钉",
+            ChannelType::ImWechat => "企业微信",
+            ChannelType::Push => "推送通知",
+            ChannelType::SiteMessage => "站内消息",
+        }
+    }
+}
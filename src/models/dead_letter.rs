@@ -0,0 +1,50 @@
+use serde::{Deserialize, Serialize};
+
+/// 死信状态：待处理
+pub const DEAD_LETTER_PENDING: &str = "pending";
+/// 死信状态：已重放
+pub const DEAD_LETTER_REPLAYED: &str = "replayed";
+
+/// 死信实体（表 notify_dead_letter），记录重试耗尽或不可重试而发送失败的 Kafka 消息
+#[derive(
+    Debug,
+    Default,
+    Clone,
+    sqlx::FromRow,
+    Serialize,
+    Deserialize,
+    sqlxplus::ModelMeta,
+    sqlxplus::CRUD,
+)]
+#[model(table = "notify_dead_letter", pk = "id", soft_delete = "is_del")]
+pub struct DeadLetter {
+    /// 主键
+    pub id: Option<i64>,
+    /// 原消息 topic
+    pub topic: Option<String>,
+    /// 原消息来源
+    pub source: Option<String>,
+    /// 原消息数据（JSON）
+    pub payload: Option<String>,
+    /// 关联的通知 ID
+    pub notification_id: Option<i64>,
+    /// 失败原因
+    pub error: Option<String>,
+    /// 失败是否可重试（0 否，1 是）
+    pub retryable: Option<i32>,
+    /// 已尝试次数
+    pub attempts: Option<i32>,
+    /// 状态（pending、replayed）
+    pub status: Option<String>,
+    /// 重放次数
+    pub replay_count: Option<i32>,
+    /// 最近重放时间（毫秒时间戳）
+    pub replayed_at: Option<i64>,
+    /// 创建时间（毫秒时间戳）
+    pub created_at: Option<i64>,
+    /// 更新时间（毫秒时间戳）
+    pub updated_at: Option<i64>,
+    /// 逻辑删除标记（0 正常，1 已删除）
+    #[serde(skip_serializing)]
+    pub is_del: Option<i32>,
+}
@@ -0,0 +1,257 @@
+use base64::Engine;
+use serde::{Deserialize, Serialize};
+
+/// 单封邮件最多附件数（含内联图片）
+pub const MAX_EMAIL_ATTACHMENTS: usize = 20;
+
+/// 邮件附件（含内联图片）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EmailAttachment {
+    /// 文件名
+    pub filename: String,
+    /// MIME 类型（可选，未设置时按文件扩展名推断，无法推断时为 application/octet-stream）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub content_type: Option<String>,
+    /// Base64 编码的文件内容（与 `url` 二选一）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub content: Option<String>,
+    /// 文件下载地址（与 `content` 二选一，发送时下载）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+    /// 内联资源的 Content-ID（设置后作为内联图片，HTML 正文中以 `cid:<content_id>` 引用）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub content_id: Option<String>,
+}
+
+impl EmailAttachment {
+    /// 是否为内联资源
+    pub fn is_inline(&self) -> bool {
+        self.content_id.is_some()
+    }
+
+    /// MIME 类型：优先使用请求中的类型，否则按文件扩展名推断
+    pub fn mime_type(&self) -> &str {
+        if let Some(content_type) = self.content_type.as_deref() {
+            return content_type;
+        }
+        let extension = self
+            .filename
+            .rsplit_once('.')
+            .map(|(_, ext)| ext.to_ascii_lowercase())
+            .unwrap_or_default();
+        match extension.as_str() {
+            "png" => "image/png",
+            "jpg" | "jpeg" => "image/jpeg",
+            "gif" => "image/gif",
+            "svg" => "image/svg+xml",
+            "webp" => "image/webp",
+            "pdf" => "application/pdf",
+            "txt" | "log" => "text/plain",
+            "csv" => "text/csv",
+            "html" | "htm" => "text/html",
+            "json" => "application/json",
+            "xml" => "application/xml",
+            "zip" => "application/zip",
+            "doc" => "application/msword",
+            "docx" => "application/vnd.openxmlformats-officedocument.wordprocessingml.document",
+            "xls" => "application/vnd.ms-excel",
+            "xlsx" => "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet",
+            "ppt" => "application/vnd.ms-powerpoint",
+            "pptx" => "application/vnd.openxmlformats-officedocument.presentationml.presentation",
+            "ics" => "text/calendar",
+            _ => "application/octet-stream",
+        }
+    }
+}
+
+/// 正文是否为 HTML（未提供 `html` 时兼容以 HTML 标签开头的 `body`）
+pub fn looks_like_html(body: &str) -> bool {
+    let body = body.trim_start();
+    body.starts_with('<') && (body.contains("</") || body.contains("/>"))
+}
+
+/// 校验邮件附件，返回出错附件的下标及原因
+///
+/// # 参数
+/// - `attachments`: 附件列表
+/// - `has_html`: 是否有 HTML 正文（内联图片只能在 HTML 正文中引用）
+pub fn validate_attachments(
+    attachments: &[EmailAttachment],
+    has_html: bool,
+) -> Result<(), (usize, &'static str)> {
+    if attachments.len() > MAX_EMAIL_ATTACHMENTS {
+        return Err((MAX_EMAIL_ATTACHMENTS, "too many attachments (max 20)"));
+    }
+    for (index, attachment) in attachments.iter().enumerate() {
+        if attachment.filename.trim().is_empty() {
+            return Err((index, "filename is required"));
+        }
+        match (&attachment.content, &attachment.url) {
+            (Some(content), None) => {
+                if base64::engine::general_purpose::STANDARD
+                    .decode(content)
+                    .is_err()
+                {
+                    return Err((index, "content must be base64 encoded"));
+                }
+            }
+            (None, Some(url)) => {
+                if !(url.starts_with("https://") || url.starts_with("http://")) {
+                    return Err((index, "url must be an http(s) URL"));
+                }
+            }
+            _ => return Err((index, "exactly one of content or url is required")),
+        }
+        if let Some(content_id) = &attachment.content_id {
+            if content_id.is_empty() || content_id.contains(['<', '>', ' ']) {
+                return Err((
+                    index,
+                    "content_id must not be empty or contain '<', '>' or spaces",
+                ));
+            }
+            if !has_html {
+                return Err((index, "inline attachments require an html body"));
+            }
+        }
+        if attachment
+            .mime_type()
+            .parse::<lettre::message::header::ContentType>()
+            .is_err()
+        {
+            return Err((index, "invalid content_type"));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn attachment(filename: &str) -> EmailAttachment {
+        EmailAttachment {
+            filename: filename.to_string(),
+            content_type: None,
+            content: Some("aGVsbG8=".to_string()),
+            url: None,
+            content_id: None,
+        }
+    }
+
+    #[test]
+    fn accepts_content_and_url_attachments() {
+        let inline = EmailAttachment {
+            content_id: Some("logo".to_string()),
+            ..attachment("logo.png")
+        };
+        let remote = EmailAttachment {
+            content: None,
+            url: Some("https://cdn.example.com/report.pdf".to_string()),
+            ..attachment("report.pdf")
+        };
+        assert_eq!(
+            validate_attachments(&[attachment("a.txt"), inline, remote], true),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn limits_attachment_count() {
+        let attachments = vec![attachment("a.txt"); MAX_EMAIL_ATTACHMENTS + 1];
+        assert_eq!(
+            validate_attachments(&attachments, false).unwrap_err().0,
+            MAX_EMAIL_ATTACHMENTS
+        );
+    }
+
+    #[test]
+    fn reports_invalid_attachment() {
+        let cases = [
+            (attachment(" "), "filename is required"),
+            (
+                EmailAttachment {
+                    content: Some("not base64!".to_string()),
+                    ..attachment("a.txt")
+                },
+                "content must be base64 encoded",
+            ),
+            (
+                EmailAttachment {
+                    content: None,
+                    url: Some("ftp://example.com/a.txt".to_string()),
+                    ..attachment("a.txt")
+                },
+                "url must be an http(s) URL",
+            ),
+            (
+                EmailAttachment {
+                    url: Some("https://example.com/a.txt".to_string()),
+                    ..attachment("a.txt")
+                },
+                "exactly one of content or url is required",
+            ),
+            (
+                EmailAttachment {
+                    content: None,
+                    ..attachment("a.txt")
+                },
+                "exactly one of content or url is required",
+            ),
+            (
+                EmailAttachment {
+                    content_id: Some("<logo>".to_string()),
+                    ..attachment("logo.png")
+                },
+                "content_id must not be empty or contain '<', '>' or spaces",
+            ),
+            (
+                EmailAttachment {
+                    content_type: Some("not a mime type".to_string()),
+                    ..attachment("a.bin")
+                },
+                "invalid content_type",
+            ),
+        ];
+        for (invalid, reason) in cases {
+            assert_eq!(
+                validate_attachments(&[attachment("ok.txt"), invalid], true),
+                Err((1, reason))
+            );
+        }
+    }
+
+    #[test]
+    fn inline_attachments_require_html() {
+        let inline = EmailAttachment {
+            content_id: Some("logo".to_string()),
+            ..attachment("logo.png")
+        };
+        assert_eq!(
+            validate_attachments(std::slice::from_ref(&inline), false),
+            Err((0, "inline attachments require an html body"))
+        );
+        assert_eq!(validate_attachments(&[inline], true), Ok(()));
+    }
+
+    #[test]
+    fn infers_mime_type_from_extension() {
+        assert_eq!(attachment("Photo.JPG").mime_type(), "image/jpeg");
+        assert_eq!(
+            attachment("archive").mime_type(),
+            "application/octet-stream"
+        );
+        let explicit = EmailAttachment {
+            content_type: Some("text/x-custom".to_string()),
+            ..attachment("a.png")
+        };
+        assert_eq!(explicit.mime_type(), "text/x-custom");
+    }
+
+    #[test]
+    fn detects_html_body() {
+        assert!(looks_like_html("  <p>Hello</p>"));
+        assert!(looks_like_html("<img src=\"cid:logo\"/>"));
+        assert!(!looks_like_html("Hello <b>"));
+        assert!(!looks_like_html("1 < 2"));
+    }
+}
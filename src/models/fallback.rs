@@ -0,0 +1,197 @@
+use super::{one_or_many, ChannelType, RecipientResult};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// 降级链最多步骤数
+pub const MAX_FALLBACK_STEPS: usize = 5;
+
+/// 降级触发条件（根据上一步的发送结果判断是否执行本步）
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum FallbackCondition {
+    /// 上一步发送失败（含部分接收者失败、超时）
+    #[default]
+    #[serde(rename = "on_error")]
+    Error,
+    /// 上一步在 `timeout_ms` 内未完成
+    #[serde(rename = "on_timeout")]
+    Timeout,
+    /// 上一步站内消息发送成功，但 `unread_minutes` 分钟后仍未读
+    #[serde(rename = "on_unread")]
+    Unread,
+}
+
+/// 降级步骤
+///
+/// 未设置的字段沿用上一步的内容：如 `im_feishu → email` 时只需指定邮箱和主题，
+/// 正文、模板及模板变量沿用上一步（模板按本步渠道渲染）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FallbackStep {
+    /// 本步渠道
+    pub channel: ChannelType,
+    /// 触发条件
+    #[serde(default)]
+    pub when: FallbackCondition,
+    /// 上一步最长等待时间（毫秒），超时视为失败；`on_timeout` 时必填
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timeout_ms: Option<u64>,
+    /// 未读等待时间（分钟），`on_unread` 时必填
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub unread_minutes: Option<u64>,
+    /// 接收者（为空沿用上一步的接收者）
+    #[serde(
+        default,
+        deserialize_with = "one_or_many",
+        skip_serializing_if = "Vec::is_empty"
+    )]
+    pub to: Vec<String>,
+    /// 主题（为空沿用上一步）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub subject: Option<String>,
+    /// 消息内容（设置后不再沿用上一步的通知模板）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub body: Option<String>,
+    /// 通知模板代码（为空且未设置 body 时沿用上一步）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub template_code: Option<String>,
+    /// 短信模板（为空沿用上一步）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sms_template_code: Option<String>,
+    /// 短信模板参数（为空沿用上一步）
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub sms_params: BTreeMap<String, String>,
+}
+
+/// 降级来源（由降级编排写入，记录本步在降级链中的位置）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FallbackOrigin {
+    /// 上一步的通知 ID
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub parent_id: Option<i64>,
+    /// 第几个降级步骤（从 1 开始）
+    pub step: u32,
+    /// 到期发送前需检查的站内消息 ID，任一已读则不再降级
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub unless_read: Vec<i64>,
+}
+
+/// 单个降级步骤的执行结果
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FallbackAttempt {
+    /// 第几个降级步骤（从 1 开始）
+    pub step: u32,
+    /// 本步渠道
+    pub channel: ChannelType,
+    /// 触发条件
+    pub when: FallbackCondition,
+    /// 通知 ID（未启用发送记录时为空）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub id: Option<i64>,
+    /// 各接收者发送结果
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub results: Vec<RecipientResult>,
+    /// 整体失败原因（发送失败、超时）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    /// 计划发送时间（未读降级时为到期检查时间）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub scheduled_at: Option<i64>,
+}
+
+/// 校验降级链，返回出错步骤的下标及原因
+pub fn validate_fallback(
+    channel: ChannelType,
+    steps: &[FallbackStep],
+) -> Result<(), (usize, &'static str)> {
+    if steps.len() > MAX_FALLBACK_STEPS {
+        return Err((MAX_FALLBACK_STEPS, "too many fallback steps (max 5)"));
+    }
+    let mut previous = channel;
+    for (index, step) in steps.iter().enumerate() {
+        match step.when {
+            FallbackCondition::Timeout if step.timeout_ms.is_none_or(|ms| ms == 0) => {
+                return Err((index, "timeout_ms is required for on_timeout"));
+            }
+            FallbackCondition::Unread if previous != ChannelType::SiteMessage => {
+                return Err((
+                    index,
+                    "on_unread requires the previous step to be site_message",
+                ));
+            }
+            FallbackCondition::Unread if step.unread_minutes.is_none_or(|m| m == 0) => {
+                return Err((index, "unread_minutes is required for on_unread"));
+            }
+            _ => {}
+        }
+        previous = step.channel;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn steps(value: serde_json::Value) -> Vec<FallbackStep> {
+        serde_json::from_value(value).unwrap()
+    }
+
+    #[test]
+    fn accepts_valid_chain() {
+        let chain = steps(json!([
+            {"channel": "site_message", "to": "u1"},
+            {"channel": "sms", "when": "on_unread", "unread_minutes": 30, "to": "13800000000"},
+            {"channel": "email", "when": "on_timeout", "timeout_ms": 5000, "to": "a@example.com"},
+        ]));
+        assert_eq!(validate_fallback(ChannelType::ImFeishu, &chain), Ok(()));
+        assert_eq!(validate_fallback(ChannelType::Email, &[]), Ok(()));
+    }
+
+    #[test]
+    fn defaults_to_on_error() {
+        let chain = steps(json!([{"channel": "sms"}]));
+        assert_eq!(chain[0].when, FallbackCondition::Error);
+    }
+
+    #[test]
+    fn limits_step_count() {
+        let chain = vec![steps(json!([{"channel": "sms"}])).remove(0); MAX_FALLBACK_STEPS + 1];
+        assert_eq!(
+            validate_fallback(ChannelType::Email, &chain).unwrap_err().0,
+            MAX_FALLBACK_STEPS
+        );
+    }
+
+    #[test]
+    fn requires_timeout_for_on_timeout() {
+        for timeout in [json!(null), json!(0)] {
+            let chain = steps(json!([
+                {"channel": "sms"},
+                {"channel": "email", "when": "on_timeout", "timeout_ms": timeout},
+            ]));
+            assert_eq!(
+                validate_fallback(ChannelType::Push, &chain).unwrap_err().0,
+                1
+            );
+        }
+    }
+
+    #[test]
+    fn requires_site_message_before_on_unread() {
+        let chain = steps(json!([{"channel": "sms", "when": "on_unread", "unread_minutes": 10}]));
+        assert_eq!(
+            validate_fallback(ChannelType::Email, &chain),
+            Err((0, "on_unread requires the previous step to be site_message"))
+        );
+        assert_eq!(validate_fallback(ChannelType::SiteMessage, &chain), Ok(()));
+    }
+
+    #[test]
+    fn requires_unread_minutes_for_on_unread() {
+        let chain = steps(json!([{"channel": "sms", "when": "on_unread", "unread_minutes": 0}]));
+        assert_eq!(
+            validate_fallback(ChannelType::SiteMessage, &chain),
+            Err((0, "unread_minutes is required for on_unread"))
+        );
+    }
+}
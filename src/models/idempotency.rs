@@ -0,0 +1,40 @@
+use serde::{Deserialize, Serialize};
+
+/// 幂等键状态：发送中
+pub const IDEMPOTENCY_PROCESSING: &str = "processing";
+/// 幂等键状态：已完成
+pub const IDEMPOTENCY_COMPLETED: &str = "completed";
+
+/// 幂等键实体（表 notify_idempotency），记录幂等键对应的首次发送结果
+#[derive(
+    Debug,
+    Default,
+    Clone,
+    sqlx::FromRow,
+    Serialize,
+    Deserialize,
+    sqlxplus::ModelMeta,
+    sqlxplus::CRUD,
+)]
+#[model(table = "notify_idempotency", pk = "id", soft_delete = "is_del")]
+pub struct IdempotencyRecord {
+    /// 主键
+    pub id: Option<i64>,
+    /// 幂等键（带租户前缀）
+    pub idempotency_key: Option<String>,
+    /// 状态（processing、completed）
+    pub status: Option<String>,
+    /// 关联的通知 ID
+    pub notification_id: Option<i64>,
+    /// 首次发送结果（JSON）
+    pub result: Option<String>,
+    /// 过期时间（毫秒时间戳）
+    pub expires_at: Option<i64>,
+    /// 创建时间（毫秒时间戳）
+    pub created_at: Option<i64>,
+    /// 更新时间（毫秒时间戳）
+    pub updated_at: Option<i64>,
+    /// 逻辑删除标记（0 正常，1 已删除）
+    #[serde(skip_serializing)]
+    pub is_del: Option<i32>,
+}
@@ -123,3 +123,49 @@ impl DingdingMessageType {
         }
     }
 }
+
+/// 企业微信群机器人消息类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WechatMessageType {
+    /// 文本
+    Text,
+    /// Markdown
+    Markdown,
+    /// 图片（base64 + md5）
+    Image,
+    /// 图文
+    News,
+    /// 文件（需先上传获取 media_id）
+    File,
+    /// 模板卡片
+    TemplateCard,
+}
+
+impl From<WechatMessageType> for &'static str {
+    fn from(t: WechatMessageType) -> Self {
+        match t {
+            WechatMessageType::Text => "text",
+            WechatMessageType::Markdown => "markdown",
+            WechatMessageType::Image => "image",
+            WechatMessageType::News => "news",
+            WechatMessageType::File => "file",
+            WechatMessageType::TemplateCard => "template_card",
+        }
+    }
+}
+
+impl WechatMessageType {
+    /// 从字符串转换为枚举（不区分大小写）
+    pub fn from_str(s: &str) -> Option<Self> {
+        match s.to_lowercase().as_str() {
+            "text" => Some(Self::Text),
+            "markdown" => Some(Self::Markdown),
+            "image" => Some(Self::Image),
+            "news" => Some(Self::News),
+            "file" => Some(Self::File),
+            "template_card" | "templatecard" => Some(Self::TemplateCard),
+            _ => None,
+        }
+    }
+}
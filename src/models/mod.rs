@@ -1,7 +1,28 @@
 mod channel;
+mod dead_letter;
+mod email;
+mod fallback;
+mod idempotency;
 mod message;
 mod notification;
+mod notification_record;
+mod site_message;
+mod template;
 
 pub use channel::ChannelType;
-pub use message::{DingdingMessageType, FeishuMessageType};
-pub use notification::Notification;
+pub use dead_letter::{DeadLetter, DEAD_LETTER_PENDING, DEAD_LETTER_REPLAYED};
+pub use email::{looks_like_html, validate_attachments, EmailAttachment};
+pub use fallback::{
+    validate_fallback, FallbackAttempt, FallbackCondition, FallbackOrigin, FallbackStep,
+};
+pub use idempotency::{IdempotencyRecord, IDEMPOTENCY_COMPLETED, IDEMPOTENCY_PROCESSING};
+pub use message::{DingdingMessageType, FeishuMessageType, WechatMessageType};
+pub use notification::{
+    one_or_many, schedule_time, BatchItemResult, BatchSendResult, Notification, RecipientResult,
+    SendReceipt, SendResult,
+};
+pub use notification_record::{
+    NotificationDetail, NotificationFilter, NotificationStatus, NotifyRecord, NotifyRecordRecipient,
+};
+pub use site_message::SiteMessage;
+pub use template::NotifyTemplate;
@@ -1,30 +1,361 @@
-use super::ChannelType;
-use serde::{Deserialize, Serialize};
+use super::{
+    looks_like_html, ChannelType, EmailAttachment, FallbackAttempt, FallbackOrigin, FallbackStep,
+};
+use crate::error::NotifyError;
+use serde::{Deserialize, Deserializer, Serialize};
+use std::collections::BTreeMap;
 
 /// 通知消息结构
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Notification {
+    /// 关联 ID（上游服务提供，原样带回发送结果事件，便于上游关联自己的业务请求）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub correlation_id: Option<String>,
+    /// 租户（来自消息信封，原样带回发送结果事件）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tenant: Option<String>,
+    /// 幂等键（可选，去重窗口内相同幂等键的重复请求直接返回首次发送结果）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub idempotency_key: Option<String>,
+    /// 计划发送时间（毫秒时间戳，可选，晚于当前时间时定时发送）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub send_at: Option<i64>,
+    /// 渠道降级链（可选，本次发送未成功时按顺序尝试）
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub fallback: Vec<FallbackStep>,
+    /// 降级来源（降级步骤由降级编排生成，请求方无需设置）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fallback_of: Option<FallbackOrigin>,
     /// 发送者（邮件时使用）
     pub from: String,
-    /// 接收者（邮件、短信时使用）
-    pub to: String,
+    /// 邮件账号名称（邮件时使用，可选，未设置时按 `from` 的地址或域名选择账号）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub email_profile: Option<String>,
+    /// 接收者列表（邮件、短信、推送、站内消息时使用）
+    ///
+    /// 反序列化时兼容单个字符串与字符串数组
+    #[serde(deserialize_with = "one_or_many")]
+    pub to: Vec<String>,
+    /// 抄送（邮件时使用）
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub cc: Vec<String>,
+    /// 密送（邮件时使用）
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub bcc: Vec<String>,
     /// 主题（邮件时使用）
     pub subject: String,
     /// 消息内容
     ///
     /// 根据渠道类型有不同的用途：
-    /// - **邮件渠道 (Email)**：作为邮件正文内容，支持纯文本或 HTML 格式
-    /// - **短信渠道 (Sms)**：作为 JSON 格式的模板参数字符串，用于填充短信模板变量
+    /// - **邮件渠道 (Email)**：纯文本正文；同时设置 `html` 时作为 text/plain 备选部分，
+    ///   未设置 `html` 且内容以 HTML 标签开头时按 HTML 发送
+    /// - **短信渠道 (Sms)**：作为 JSON 格式的模板参数字符串（兼容旧格式，优先使用 `sms_params`）
     /// - **钉钉渠道 (ImDingding)**：支持两种格式：
     ///   - JSON 对象格式：`{"msg_type": "text|markdown|link|actionCard|...", "content": {...}}`
     ///   - 纯文本格式：直接作为文本消息发送
     /// - **飞书渠道 (ImFeishu)**：支持两种格式：
     ///   - JSON 对象格式：`{"msg_type": "text|post|image|interactive|...", "content": {...}}`
     ///   - 纯文本格式：直接作为文本消息发送
-    /// - **企业微信渠道 (ImWechat)**：消息内容（具体格式待实现）
-    /// - **推送通知渠道 (Push)**：推送消息内容（具体格式待实现）
-    /// - **站内消息渠道 (SiteMessage)**：站内消息内容（具体格式待实现）
+    /// - **企业微信渠道 (ImWechat)**：支持两种格式：
+    ///   - JSON 对象格式：`{"msg_type": "text|markdown|image|news|file|template_card", "content": {...}}`
+    ///   - 纯文本格式：直接作为文本消息发送
+    /// - **推送通知渠道 (Push)**：`to` 为设备令牌（可带 `apns:` / `fcm:` 前缀），body 支持两种格式：
+    ///   - JSON 对象格式：`{"title": "...", "body": "...", "badge": 1, "sound": "default", "data": {...}}`
+    ///   - 纯文本格式：作为推送正文，`subject` 作为标题
+    /// - **站内消息渠道 (SiteMessage)**：站内消息正文，`to` 为接收用户 ID，`subject` 为标题
     pub body: String,
+    /// HTML 正文（邮件时使用，可选，与 `body` 同时设置时组成 multipart/alternative）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub html: Option<String>,
+    /// 附件及内联图片（邮件时使用，可选）
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub attachments: Vec<EmailAttachment>,
     /// 消息渠道类型
     pub channel: ChannelType,
+    /// 短信模板（短信时使用，模板别名或模板代码，为空则使用配置的默认模板）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sms_template_code: Option<String>,
+    /// 短信模板参数（短信时使用，参数名需在模板配置中登记）
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub sms_params: BTreeMap<String, String>,
+    /// 通知模板代码（设置后由模板渲染 subject/body，忽略请求中的 subject/body）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub template_code: Option<String>,
+    /// 模板语言（如 zh-CN，为空使用默认语言）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub locale: Option<String>,
+    /// 模板变量
+    #[serde(default, skip_serializing_if = "serde_json::Map::is_empty")]
+    pub variables: serde_json::Map<String, serde_json::Value>,
+}
+
+impl Notification {
+    /// 创建指定渠道的空通知，其余字段按需填充
+    pub fn new(channel: ChannelType) -> Self {
+        Self {
+            correlation_id: None,
+            tenant: None,
+            idempotency_key: None,
+            send_at: None,
+            fallback: Vec::new(),
+            fallback_of: None,
+            from: String::new(),
+            email_profile: None,
+            to: Vec::new(),
+            cc: Vec::new(),
+            bcc: Vec::new(),
+            subject: String::new(),
+            body: String::new(),
+            html: None,
+            attachments: Vec::new(),
+            channel,
+            sms_template_code: None,
+            sms_params: BTreeMap::new(),
+            template_code: None,
+            locale: None,
+            variables: serde_json::Map::new(),
+        }
+    }
+
+    /// 是否需要定时发送（计划发送时间晚于当前时间）
+    pub fn is_scheduled(&self) -> bool {
+        self.send_at
+            .is_some_and(|at| at > chrono::Utc::now().timestamp_millis())
+    }
+
+    /// 邮件的 HTML 正文：`html` 字段，未设置时为以 HTML 标签开头的 `body`
+    pub fn html_body(&self) -> Option<&str> {
+        self.html
+            .as_deref()
+            .or_else(|| looks_like_html(&self.body).then_some(self.body.as_str()))
+    }
+
+    /// 首个接收者，适用于单接收者发送（无接收者时返回空字符串）
+    pub fn recipient(&self) -> &str {
+        self.to.first().map(String::as_str).unwrap_or_default()
+    }
+
+    /// 全部接收者（to、cc、bcc），群机器人等无接收者的渠道返回一个空字符串
+    pub fn all_recipients(&self) -> Vec<String> {
+        let recipients: Vec<String> = self
+            .to
+            .iter()
+            .chain(&self.cc)
+            .chain(&self.bcc)
+            .cloned()
+            .collect();
+        if recipients.is_empty() {
+            vec![String::new()]
+        } else {
+            recipients
+        }
+    }
+
+    /// 复制一份仅发送给指定接收者的通知（不含抄送、密送）
+    pub fn for_recipient(&self, recipient: &str) -> Self {
+        Self {
+            to: vec![recipient.to_string()],
+            cc: Vec::new(),
+            bcc: Vec::new(),
+            ..self.clone()
+        }
+    }
+}
+
+/// 计算计划发送时间：优先使用 `send_at`（毫秒时间戳），否则为当前时间加 `delay` 秒
+pub fn schedule_time(send_at: Option<i64>, delay: Option<u64>) -> Option<i64> {
+    send_at.or_else(|| {
+        delay
+            .filter(|d| *d > 0)
+            .map(|d| chrono::Utc::now().timestamp_millis() + d as i64 * 1000)
+    })
+}
+
+/// 反序列化单个字符串或字符串数组，空字符串视为无接收者
+pub fn one_or_many<'de, D>(deserializer: D) -> Result<Vec<String>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum OneOrMany {
+        One(String),
+        Many(Vec<String>),
+    }
+
+    let list = match OneOrMany::deserialize(deserializer)? {
+        OneOrMany::One(s) => vec![s],
+        OneOrMany::Many(v) => v,
+    };
+    Ok(list
+        .into_iter()
+        .map(|s| s.trim().to_string())
+        .filter(|s| !s.is_empty())
+        .collect())
+}
+
+/// 发送回执
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SendReceipt {
+    /// 服务商返回的消息 ID（如阿里云短信 BizId、APNs apns-id），可用于回执查询
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub provider_message_id: Option<String>,
+}
+
+impl SendReceipt {
+    /// 携带服务商消息 ID 的回执
+    pub fn with_provider_id(id: impl Into<String>) -> Self {
+        Self {
+            provider_message_id: Some(id.into()),
+        }
+    }
+}
+
+/// 单个接收者的发送结果
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecipientResult {
+    /// 接收者（群机器人等无接收者的渠道为空字符串）
+    pub recipient: String,
+    /// 是否发送成功
+    pub success: bool,
+    /// 服务商返回的消息 ID
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub provider_message_id: Option<String>,
+    /// 失败原因
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    /// 失败错误码（见 `error_code`）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error_code: Option<i32>,
+}
+
+impl RecipientResult {
+    /// 发送成功的结果
+    pub fn sent(recipient: impl Into<String>, receipt: SendReceipt) -> Self {
+        Self {
+            recipient: recipient.into(),
+            success: true,
+            provider_message_id: receipt.provider_message_id,
+            error: None,
+            error_code: None,
+        }
+    }
+
+    /// 发送失败的结果
+    pub fn failed(recipient: impl Into<String>, error: &NotifyError) -> Self {
+        Self {
+            recipient: recipient.into(),
+            success: false,
+            provider_message_id: None,
+            error: Some(error.to_string()),
+            error_code: Some(error.code()),
+        }
+    }
+}
+
+/// 通知发送结果
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SendResult {
+    /// 通知 ID（发送记录 ID，未启用发送记录时为空）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub id: Option<i64>,
+    /// 各接收者发送结果
+    pub results: Vec<RecipientResult>,
+    /// 降级步骤执行结果（未配置降级链或无需降级时为空）
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub fallback: Vec<FallbackAttempt>,
+    /// 因超出限流已延后发送（发送记录改为定时发送，由调度器到期后发送），此时接收者结果为空
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub deferred: bool,
+}
+
+impl SendResult {
+    /// 发送结果（无降级步骤）
+    pub fn new(id: Option<i64>, results: Vec<RecipientResult>) -> Self {
+        Self {
+            id,
+            results,
+            fallback: Vec::new(),
+            deferred: false,
+        }
+    }
+
+    /// 已延后发送的结果
+    pub fn deferred(id: Option<i64>) -> Self {
+        Self {
+            deferred: true,
+            ..Self::new(id, Vec::new())
+        }
+    }
+
+    /// 是否有接收者发送成功
+    pub fn any_success(&self) -> bool {
+        self.results.iter().any(|r| r.success)
+    }
+}
+
+/// 批量发送中单条通知的结果
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BatchItemResult {
+    /// 客户端提供的条目 ID
+    pub id: String,
+    /// 是否发送成功（异步发送时为是否已写入排队记录），部分接收者失败见 `result.results`
+    pub success: bool,
+    /// 发送结果
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub result: Option<SendResult>,
+    /// 失败原因
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    /// 失败错误码（见 `error_code`）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error_code: Option<i32>,
+}
+
+impl BatchItemResult {
+    /// 根据单条通知的发送结果生成条目结果
+    pub fn new(id: String, outcome: Result<SendResult, NotifyError>) -> Self {
+        match outcome {
+            Ok(result) => Self {
+                id,
+                success: true,
+                result: Some(result),
+                error: None,
+                error_code: None,
+            },
+            Err(e) => Self {
+                id,
+                success: false,
+                result: None,
+                error: Some(e.to_string()),
+                error_code: Some(e.code()),
+            },
+        }
+    }
+}
+
+/// 批量发送结果
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BatchSendResult {
+    /// 通知总数
+    pub total: usize,
+    /// 发送成功的通知数
+    pub succeeded: usize,
+    /// 发送失败的通知数
+    pub failed: usize,
+    /// 各条通知的结果（与请求顺序一致）
+    pub items: Vec<BatchItemResult>,
+}
+
+impl BatchSendResult {
+    /// 汇总各条通知的结果
+    pub fn new(items: Vec<BatchItemResult>) -> Self {
+        let succeeded = items.iter().filter(|item| item.success).count();
+        Self {
+            total: items.len(),
+            succeeded,
+            failed: items.len() - succeeded,
+            items,
+        }
+    }
 }
@@ -0,0 +1,151 @@
+use super::ChannelType;
+use serde::{Deserialize, Serialize};
+
+/// 通知发送状态
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NotificationStatus {
+    /// 待发送
+    Pending,
+    /// 等待定时发送
+    Scheduled,
+    /// 发送中
+    Sending,
+    /// 已发送（全部接收者成功）
+    Sent,
+    /// 部分接收者发送失败
+    Partial,
+    /// 发送失败
+    Failed,
+    /// 已取消（定时发送在发送前被取消）
+    Cancelled,
+}
+
+impl NotificationStatus {
+    /// 状态标识（与序列化值一致）
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            NotificationStatus::Pending => "pending",
+            NotificationStatus::Scheduled => "scheduled",
+            NotificationStatus::Sending => "sending",
+            NotificationStatus::Sent => "sent",
+            NotificationStatus::Partial => "partial",
+            NotificationStatus::Failed => "failed",
+            NotificationStatus::Cancelled => "cancelled",
+        }
+    }
+}
+
+/// 通知发送记录（表 notify_record），每次发送请求一条
+#[derive(
+    Debug,
+    Default,
+    Clone,
+    sqlx::FromRow,
+    Serialize,
+    Deserialize,
+    sqlxplus::ModelMeta,
+    sqlxplus::CRUD,
+)]
+#[model(table = "notify_record", pk = "id", soft_delete = "is_del")]
+pub struct NotifyRecord {
+    /// 主键（通知 ID）
+    pub id: Option<i64>,
+    /// 渠道（与 ChannelType 序列化值一致）
+    pub channel: Option<String>,
+    /// 主题
+    pub subject: Option<String>,
+    /// 通知模板代码
+    pub template_code: Option<String>,
+    /// 状态（pending、scheduled、sending、sent、partial、failed、cancelled）
+    pub status: Option<String>,
+    /// 已尝试次数
+    pub attempts: Option<i32>,
+    /// 接收者数量
+    pub recipient_count: Option<i32>,
+    /// 发送成功的接收者数量
+    pub success_count: Option<i32>,
+    /// 最近一次失败原因
+    pub error: Option<String>,
+    /// 通知内容（Notification JSON），可能含验证码等敏感信息，不对外输出
+    #[serde(skip_serializing)]
+    pub payload: Option<String>,
+    /// 计划发送时间（毫秒时间戳，定时发送时有值）
+    pub scheduled_at: Option<i64>,
+    /// 调度器取出时间（毫秒时间戳，发送中租约的起点，每次发送尝试续期）
+    pub claimed_at: Option<i64>,
+    /// 降级链中上一步的通知 ID
+    pub parent_id: Option<i64>,
+    /// 第几个降级步骤（从 1 开始，首次发送为空）
+    pub fallback_step: Option<i32>,
+    /// 发送完成时间（毫秒时间戳）
+    pub sent_at: Option<i64>,
+    /// 创建时间（毫秒时间戳）
+    pub created_at: Option<i64>,
+    /// 更新时间（毫秒时间戳）
+    pub updated_at: Option<i64>,
+    /// 逻辑删除标记（0 正常，1 已删除）
+    #[serde(skip_serializing)]
+    pub is_del: Option<i32>,
+}
+
+/// 通知接收者发送记录（表 notify_record_recipient），每个接收者一条
+#[derive(
+    Debug,
+    Default,
+    Clone,
+    sqlx::FromRow,
+    Serialize,
+    Deserialize,
+    sqlxplus::ModelMeta,
+    sqlxplus::CRUD,
+)]
+#[model(table = "notify_record_recipient", pk = "id", soft_delete = "is_del")]
+pub struct NotifyRecordRecipient {
+    /// 主键
+    pub id: Option<i64>,
+    /// 所属通知 ID
+    pub record_id: Option<i64>,
+    /// 渠道
+    pub channel: Option<String>,
+    /// 接收者（手机号、邮箱、设备令牌、用户 ID 等，群机器人为空）
+    pub recipient: Option<String>,
+    /// 状态（pending、sending、sent、failed）
+    pub status: Option<String>,
+    /// 服务商消息 ID
+    pub provider_message_id: Option<String>,
+    /// 失败原因
+    pub error: Option<String>,
+    /// 创建时间（毫秒时间戳）
+    pub created_at: Option<i64>,
+    /// 更新时间（毫秒时间戳）
+    pub updated_at: Option<i64>,
+    /// 逻辑删除标记（0 正常，1 已删除）
+    #[serde(skip_serializing)]
+    pub is_del: Option<i32>,
+}
+
+/// 通知详情（发送记录及各接收者结果）
+#[derive(Debug, Clone, Serialize)]
+pub struct NotificationDetail {
+    /// 发送记录
+    #[serde(flatten)]
+    pub record: NotifyRecord,
+    /// 各接收者发送结果
+    pub recipients: Vec<NotifyRecordRecipient>,
+}
+
+/// 发送记录查询条件
+#[derive(Debug, Clone, Default)]
+pub struct NotificationFilter {
+    /// 渠道
+    pub channel: Option<ChannelType>,
+    /// 状态
+    pub status: Option<NotificationStatus>,
+    /// 接收者（精确匹配）
+    pub recipient: Option<String>,
+    /// 创建时间起（毫秒时间戳，含）
+    pub start_time: Option<i64>,
+    /// 创建时间止（毫秒时间戳，不含）
+    pub end_time: Option<i64>,
+}
@@ -0,0 +1,37 @@
+use serde::{Deserialize, Serialize};
+
+/// 站内消息实体（表 notify_site_message）
+#[derive(
+    Debug,
+    Default,
+    Clone,
+    sqlx::FromRow,
+    Serialize,
+    Deserialize,
+    sqlxplus::ModelMeta,
+    sqlxplus::CRUD,
+)]
+#[model(table = "notify_site_message", pk = "id", soft_delete = "is_del")]
+pub struct SiteMessage {
+    /// 主键
+    pub id: Option<i64>,
+    /// 接收用户 ID
+    pub user_id: Option<i64>,
+    /// 发送者
+    pub sender: Option<String>,
+    /// 标题
+    pub title: Option<String>,
+    /// 内容
+    pub content: Option<String>,
+    /// 是否已读（0 未读，1 已读）
+    pub is_read: Option<i32>,
+    /// 阅读时间（毫秒时间戳）
+    pub read_at: Option<i64>,
+    /// 创建时间（毫秒时间戳）
+    pub created_at: Option<i64>,
+    /// 更新时间（毫秒时间戳）
+    pub updated_at: Option<i64>,
+    /// 逻辑删除标记（0 正常，1 已删除）
+    #[serde(skip_serializing)]
+    pub is_del: Option<i32>,
+}
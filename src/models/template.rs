@@ -0,0 +1,41 @@
+use serde::{Deserialize, Serialize};
+
+/// 通知模板实体（表 notify_template）
+///
+/// 同一 `code` 可按渠道、语言分别维护模板，发送时按 code + channel + locale 查找
+#[derive(
+    Debug,
+    Default,
+    Clone,
+    sqlx::FromRow,
+    Serialize,
+    Deserialize,
+    sqlxplus::ModelMeta,
+    sqlxplus::CRUD,
+)]
+#[model(table = "notify_template", pk = "id", soft_delete = "is_del")]
+pub struct NotifyTemplate {
+    /// 主键
+    pub id: Option<i64>,
+    /// 模板代码（业务标识，如 order_shipped）
+    pub code: Option<String>,
+    /// 渠道（与 ChannelType 序列化值一致，如 email、sms、im_feishu）
+    pub channel: Option<String>,
+    /// 语言（如 zh-CN、en-US）
+    pub locale: Option<String>,
+    /// 主题模板（邮件主题、推送/站内消息标题）
+    pub subject_template: Option<String>,
+    /// 正文模板
+    pub body_template: Option<String>,
+    /// 版本号（每次修改递增）
+    pub version: Option<i32>,
+    /// 备注
+    pub remark: Option<String>,
+    /// 创建时间（毫秒时间戳）
+    pub created_at: Option<i64>,
+    /// 更新时间（毫秒时间戳）
+    pub updated_at: Option<i64>,
+    /// 逻辑删除标记（0 正常，1 已删除）
+    #[serde(skip_serializing)]
+    pub is_del: Option<i32>,
+}
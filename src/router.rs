@@ -1,7 +1,13 @@
-use crate::handlers::{list_channels, send_notification};
-use crate::kafka::NotificationHandlerContext;
+use crate::handlers::{
+    cancel_notification, count_unread_site_messages, create_template, delete_site_message,
+    delete_template, get_notification, get_template, list_channels, list_dead_letters,
+    list_notifications, list_site_messages, list_templates, mark_all_site_messages_read,
+    mark_site_message_read, replay_dead_letter, send_notification, send_notification_batch,
+    update_template,
+};
+use crate::state::AppState;
 use axum::{
-    routing::{get, post},
+    routing::{delete, get, post, put},
     Router,
 };
 use std::sync::Arc;
@@ -9,17 +15,51 @@ use std::sync::Arc;
 /// 创建 HTTP 路由
 ///
 /// # 参数
-/// - `context`: 通知处理器上下文，包含所有消息发送器
+/// - `state`: 应用状态，包含通知处理器上下文和各业务服务
 ///
 /// 注意：健康检查路由 `/health` 已由 fbc-starter 在基础路由中提供
-pub fn create_router(context: Arc<NotificationHandlerContext>) -> Router {
+pub fn create_router(state: Arc<AppState>) -> Router {
     Router::new()
         // API v1 路由
         .nest(
             "/api/v1",
             Router::new()
-                .route("/notifications", post(send_notification))
+                .route(
+                    "/notifications",
+                    post(send_notification).get(list_notifications),
+                )
+                .route("/notifications/batch", post(send_notification_batch))
+                .route(
+                    "/notifications/{id}",
+                    get(get_notification).delete(cancel_notification),
+                )
                 .route("/channels", get(list_channels))
-                .with_state(context),
+                .nest(
+                    "/site-messages",
+                    Router::new()
+                        .route("/", get(list_site_messages))
+                        .route("/unread-count", get(count_unread_site_messages))
+                        .route("/read-all", put(mark_all_site_messages_read))
+                        .route("/{id}/read", put(mark_site_message_read))
+                        .route("/{id}", delete(delete_site_message)),
+                )
+                .nest(
+                    "/dead-letters",
+                    Router::new()
+                        .route("/", get(list_dead_letters))
+                        .route("/{id}/replay", post(replay_dead_letter)),
+                )
+                .nest(
+                    "/templates",
+                    Router::new()
+                        .route("/", get(list_templates).post(create_template))
+                        .route(
+                            "/{id}",
+                            get(get_template)
+                                .put(update_template)
+                                .delete(delete_template),
+                        ),
+                )
+                .with_state(state),
         )
 }
@@ -0,0 +1,209 @@
+use crate::error::{NotifyError, NotifyResult};
+use crate::models::{DeadLetter, DEAD_LETTER_PENDING, DEAD_LETTER_REPLAYED};
+use fbc_starter::{CursorPageBaseResp, Message, MessageProducerType};
+use serde_json::json;
+use sqlxplus::{Crud, DbPool, QueryBuilder};
+use std::sync::Arc;
+
+/// 单页最大条数
+const MAX_PAGE_SIZE: u32 = 100;
+/// 默认每页条数
+const DEFAULT_PAGE_SIZE: u32 = 20;
+/// 发布到 Kafka 时使用的消息来源
+const SOURCE: &str = "ms-notify";
+
+/// 发送失败的消息及失败上下文
+pub struct DeadLetterEntry<'a> {
+    /// 原始 Kafka 消息
+    pub message: &'a Message,
+    /// 最后一次失败的错误
+    pub error: &'a NotifyError,
+    /// 已尝试次数
+    pub attempts: u32,
+    /// 关联的通知 ID
+    pub notification_id: Option<i64>,
+}
+
+/// 死信服务
+///
+/// 死信同时发布到死信 topic（供下游监控、告警）和写入数据库（供查询、重放），
+/// 两者均为可选：未配置 Kafka 生产者或数据库时跳过对应步骤
+pub struct DeadLetterService {
+    db_pool: Option<Arc<DbPool>>,
+    producer: Option<MessageProducerType>,
+    topic: String,
+}
+
+impl DeadLetterService {
+    /// 创建死信服务
+    ///
+    /// # 参数
+    /// - `db_pool`: 数据库连接池（可选）
+    /// - `producer`: Kafka 生产者（可选）
+    /// - `topic`: 死信 topic
+    pub fn new(
+        db_pool: Option<Arc<DbPool>>,
+        producer: Option<MessageProducerType>,
+        topic: impl Into<String>,
+    ) -> Self {
+        Self {
+            db_pool,
+            producer,
+            topic: topic.into(),
+        }
+    }
+
+    /// 记录一条死信，失败只记日志
+    pub async fn publish(&self, entry: DeadLetterEntry<'_>) {
+        let id = match self.persist(&entry).await {
+            Ok(id) => id,
+            Err(e) => {
+                tracing::error!("死信写入数据库失败: {}", e);
+                None
+            }
+        };
+
+        let Some(producer) = &self.producer else {
+            if id.is_none() {
+                tracing::error!(
+                    topic = %entry.message.topic,
+                    "死信无处保存（未配置 Kafka 生产者和数据库），消息已丢弃: {}",
+                    entry.message.data
+                );
+            }
+            return;
+        };
+
+        let data = json!({
+            "dead_letter_id": id,
+            "original_topic": entry.message.topic,
+            "original_from": entry.message.from,
+            "original": entry.message.data,
+            "notification_id": entry.notification_id,
+            "error": entry.error.to_string(),
+            "retryable": entry.error.is_retryable(),
+            "attempts": entry.attempts,
+            "failed_at": chrono::Utc::now().timestamp_millis(),
+        });
+        if let Err(e) = producer
+            .publish(&self.topic, Message::new(&self.topic, SOURCE, data))
+            .await
+        {
+            tracing::error!(topic = %self.topic, "死信发布失败: {}", e);
+        }
+    }
+
+    /// 按游标分页查询死信（按 ID 倒序）
+    ///
+    /// # 参数
+    /// - `cursor`: 上一页返回的游标（首页为 None）
+    /// - `page_size`: 每页条数
+    /// - `status`: 状态过滤（pending、replayed）
+    pub async fn list(
+        &self,
+        cursor: Option<u32>,
+        page_size: Option<u32>,
+        status: Option<String>,
+    ) -> NotifyResult<CursorPageBaseResp<DeadLetter>> {
+        let pool = self.db_pool()?.mysql_pool();
+        let page_size = page_size
+            .unwrap_or(DEFAULT_PAGE_SIZE)
+            .clamp(1, MAX_PAGE_SIZE);
+
+        let filter = |builder: QueryBuilder| match &status {
+            Some(status) => builder.and_eq("status", status.as_str()),
+            None => builder,
+        };
+
+        let total = DeadLetter::count(pool, filter(QueryBuilder::new(""))).await?;
+
+        let mut builder = filter(QueryBuilder::new(""));
+        if let Some(cursor) = cursor.filter(|c| *c > 0) {
+            builder = builder.and_lt("id", cursor as i64);
+        }
+        let builder = builder.order_by("id", false);
+
+        // 倒序翻页由 builder 自行追加 id < cursor 条件，因此不向 paginate_cursor 传游标
+        let page = DeadLetter::paginate_cursor(pool, builder, None, page_size).await?;
+        let next_cursor = page
+            .next_cursor
+            .filter(|_| page.has_next)
+            .and_then(|id| u32::try_from(id).ok());
+
+        Ok(CursorPageBaseResp::init(
+            next_cursor,
+            page.has_next,
+            page.items,
+            total as i64,
+        ))
+    }
+
+    /// 将死信中的原消息重新发布到原 topic，由消费链路重新处理
+    pub async fn replay(&self, id: i64) -> NotifyResult<()> {
+        let pool = self.db_pool()?.mysql_pool();
+        let producer = self
+            .producer
+            .as_ref()
+            .ok_or_else(|| NotifyError::Config("未配置 Kafka 生产者，无法重放死信".to_string()))?;
+
+        let letter = DeadLetter::find_by_id(pool, id)
+            .await?
+            .ok_or(NotifyError::DeadLetterNotFound(id))?;
+        let topic = letter.topic.unwrap_or_default();
+        let data: serde_json::Value = serde_json::from_str(letter.payload.as_deref().unwrap_or(""))
+            .map_err(|e| NotifyError::InvalidMessage(format!("死信消息数据损坏: {}", e)))?;
+
+        producer
+            .publish(
+                &topic,
+                Message::new(&topic, letter.source.unwrap_or_default(), data),
+            )
+            .await
+            .map_err(|e| NotifyError::Kafka(e.to_string()))?;
+
+        let now = chrono::Utc::now().timestamp_millis();
+        let update = DeadLetter {
+            id: Some(id),
+            status: Some(DEAD_LETTER_REPLAYED.to_string()),
+            replay_count: Some(letter.replay_count.unwrap_or(0) + 1),
+            replayed_at: Some(now),
+            updated_at: Some(now),
+            ..Default::default()
+        };
+        update.update(pool).await?;
+        tracing::info!(id, topic = %topic, "死信已重放");
+        Ok(())
+    }
+
+    /// 写入数据库，未配置数据库时返回 None
+    async fn persist(&self, entry: &DeadLetterEntry<'_>) -> NotifyResult<Option<i64>> {
+        let Some(db_pool) = &self.db_pool else {
+            return Ok(None);
+        };
+        let now = chrono::Utc::now().timestamp_millis();
+        let letter = DeadLetter {
+            topic: Some(entry.message.topic.clone()),
+            source: Some(entry.message.from.clone()),
+            payload: Some(entry.message.data.to_string()),
+            notification_id: entry.notification_id,
+            error: Some(entry.error.to_string()),
+            retryable: Some(i32::from(entry.error.is_retryable())),
+            attempts: Some(entry.attempts as i32),
+            status: Some(DEAD_LETTER_PENDING.to_string()),
+            replay_count: Some(0),
+            created_at: Some(now),
+            updated_at: Some(now),
+            is_del: Some(0),
+            ..Default::default()
+        };
+        let id = letter.insert(db_pool.mysql_pool()).await?;
+        Ok(Some(id))
+    }
+
+    /// 获取数据库连接池，未配置数据库时返回配置错误
+    fn db_pool(&self) -> NotifyResult<&Arc<DbPool>> {
+        self.db_pool
+            .as_ref()
+            .ok_or_else(|| NotifyError::Config("死信查询未启用（未配置数据库）".to_string()))
+    }
+}
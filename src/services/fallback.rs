@@ -0,0 +1,558 @@
+use crate::error::NotifyError;
+use crate::kafka::{NotificationHandlerContext, OverLimit, RetryPolicy, SendFailure};
+use crate::models::{
+    validate_fallback, ChannelType, FallbackAttempt, FallbackCondition, FallbackOrigin,
+    FallbackStep, Notification, RecipientResult, SendResult,
+};
+use crate::services::{NotificationRecordService, SiteMessageService};
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::{info, warn};
+
+/// 降级编排
+///
+/// 在处理器上下文之上按降级链逐步发送：上一步满足本步的触发条件时改用本步渠道重发，
+/// 每一步都作为独立通知写入发送记录（`parent_id` 指向上一步），结果汇总到 `SendResult.fallback`。
+/// 未读降级不等待，而是写入一条定时通知，到期时站内消息仍未读才继续发送
+pub struct FallbackOrchestrator {
+    context: Arc<NotificationHandlerContext>,
+    /// 发送记录服务（未配置数据库时为 None）
+    records: Option<Arc<NotificationRecordService>>,
+    /// 站内消息服务（未配置数据库时为 None）
+    site_messages: Option<Arc<SiteMessageService>>,
+}
+
+/// 单步发送结果
+enum Outcome {
+    /// 发送完成（可能部分接收者失败）
+    Sent(SendResult),
+    /// 发送失败
+    Failed(SendFailure),
+    /// 在下一步的 `timeout_ms` 内未完成（发送仍在后台继续）
+    TimedOut(u64),
+}
+
+impl Outcome {
+    /// 全部接收者发送成功
+    fn succeeded(&self) -> bool {
+        matches!(self, Outcome::Sent(result) if result.results.iter().all(|r| r.success))
+    }
+
+    /// 相同幂等键的请求正在处理中
+    fn in_progress(&self) -> bool {
+        matches!(
+            self,
+            Outcome::Failed(SendFailure {
+                error: NotifyError::IdempotencyInProgress(_),
+                ..
+            })
+        )
+    }
+
+    /// 部分接收者失败时的失败接收者（全部失败或未完成时为 None）
+    fn failed_recipients(&self) -> Option<Vec<String>> {
+        match self {
+            Outcome::Sent(result) if result.any_success() => Some(
+                result
+                    .results
+                    .iter()
+                    .filter(|r| !r.success)
+                    .map(|r| r.recipient.clone())
+                    .collect(),
+            ),
+            _ => None,
+        }
+    }
+
+    fn id(&self) -> Option<i64> {
+        match self {
+            Outcome::Sent(result) => result.id,
+            Outcome::Failed(failure) => failure.id,
+            Outcome::TimedOut(_) => None,
+        }
+    }
+}
+
+impl FallbackOrchestrator {
+    /// 创建降级编排
+    ///
+    /// # 参数
+    /// - `context`: 处理器上下文
+    pub fn new(context: Arc<NotificationHandlerContext>) -> Self {
+        Self {
+            context,
+            records: None,
+            site_messages: None,
+        }
+    }
+
+    /// 启用发送记录（站内消息已读时取消到期的未读降级）
+    pub fn with_records(mut self, records: Arc<NotificationRecordService>) -> Self {
+        self.records = Some(records);
+        self
+    }
+
+    /// 启用站内消息已读检查（未读降级）
+    pub fn with_site_messages(mut self, site_messages: Arc<SiteMessageService>) -> Self {
+        self.site_messages = Some(site_messages);
+        self
+    }
+
+    /// 发送通知（每步只尝试一次，超出限流时拒绝），供 HTTP handlers 使用
+    pub async fn send(&self, notification: &Notification) -> Result<SendResult, NotifyError> {
+        self.run(notification, &RetryPolicy::none(), None, OverLimit::Reject)
+            .await
+            .map_err(|failure| failure.error)
+    }
+
+    /// 异步发送：校验降级链后写入排队记录立即返回通知 ID，降级链在调度器发送时执行
+    pub async fn enqueue(&self, notification: &Notification) -> Result<SendResult, NotifyError> {
+        validate_fallback(notification.channel, &notification.fallback).map_err(
+            |(index, reason)| {
+                NotifyError::InvalidMessage(format!("fallback[{}]: {}", index, reason))
+            },
+        )?;
+        self.context.enqueue(notification).await
+    }
+
+    /// 按重试策略发送通知（每步各自重试），供 Kafka handlers 使用
+    pub async fn send_with_retry(
+        &self,
+        notification: &Notification,
+        policy: &RetryPolicy,
+    ) -> Result<SendResult, SendFailure> {
+        self.run(notification, policy, None, OverLimit::Delay).await
+    }
+
+    /// 发送到期的定时通知，供定时调度器使用
+    ///
+    /// 未读降级到期时，若关联的站内消息已读则不再发送，记录标记为已取消
+    pub async fn send_scheduled(
+        &self,
+        id: i64,
+        notification: &Notification,
+        policy: &RetryPolicy,
+    ) -> Result<SendResult, SendFailure> {
+        if let Some(origin) = &notification.fallback_of {
+            if self.any_read(&origin.unless_read).await {
+                info!(
+                    "Site message already read, fallback skipped: id={}, step={}",
+                    id, origin.step
+                );
+                if let Some(records) = &self.records {
+                    if let Err(e) = records.skip(id, "site message already read").await {
+                        warn!("Failed to update notification record {}: {}", id, e);
+                    }
+                }
+                return Ok(SendResult::new(Some(id), Vec::new()));
+            }
+        }
+        self.run(notification, policy, Some(id), OverLimit::Delay)
+            .await
+    }
+
+    /// 发送通知并按降级链逐步降级
+    ///
+    /// 只要有一步发送成功即返回 Ok；全部失败时返回首步的失败结果
+    async fn run(
+        &self,
+        notification: &Notification,
+        policy: &RetryPolicy,
+        scheduled_id: Option<i64>,
+        over_limit: OverLimit,
+    ) -> Result<SendResult, SendFailure> {
+        let steps = &notification.fallback;
+        // 未到期的定时通知连同降级链写入定时记录，到期后再执行
+        if steps.is_empty() || (scheduled_id.is_none() && notification.is_scheduled()) {
+            return self
+                .attempt(notification, policy, scheduled_id, over_limit)
+                .await;
+        }
+        validate_fallback(notification.channel, steps).map_err(|(index, reason)| SendFailure {
+            id: None,
+            attempts: 0,
+            error: NotifyError::InvalidMessage(format!("fallback[{}]: {}", index, reason)),
+        })?;
+
+        let first = self
+            .step(
+                notification.clone(),
+                policy,
+                scheduled_id,
+                over_limit,
+                steps[0].timeout_ms,
+            )
+            .await;
+
+        let offset = notification.fallback_of.as_ref().map_or(0, |o| o.step);
+        let mut attempts = Vec::new();
+        let mut previous = notification.clone();
+        let mut last = None;
+        for (index, step) in steps.iter().enumerate() {
+            let outcome = last.as_ref().unwrap_or(&first);
+            let proceed = match step.when {
+                // 相同幂等键的请求正在处理中时由首次请求负责降级
+                FallbackCondition::Error => !outcome.succeeded() && !outcome.in_progress(),
+                FallbackCondition::Timeout => matches!(outcome, Outcome::TimedOut(_)),
+                FallbackCondition::Unread => {
+                    previous.channel == ChannelType::SiteMessage
+                        && matches!(outcome, Outcome::Sent(result) if result.any_success())
+                }
+            };
+            if !proceed {
+                break;
+            }
+
+            let number = offset + index as u32 + 1;
+            // 出错降级只发给上一步失败的接收者，已成功的接收者不重复发送
+            let failed = match step.when {
+                FallbackCondition::Error => outcome.failed_recipients(),
+                _ => None,
+            };
+            let mut next =
+                next_notification(&previous, step, outcome.id(), number, failed.as_deref());
+            if step.when == FallbackCondition::Unread {
+                let minutes = step.unread_minutes.unwrap_or_default();
+                next.send_at =
+                    Some(chrono::Utc::now().timestamp_millis() + minutes as i64 * 60_000);
+                next.fallback = steps[index + 1..].to_vec();
+                if let Some(origin) = &mut next.fallback_of {
+                    origin.unless_read = site_message_ids(outcome);
+                }
+                attempts.push(self.defer(&next, step, number).await);
+                break;
+            }
+
+            let timeout = steps.get(index + 1).and_then(|s| s.timeout_ms);
+            let current = self
+                .step(next.clone(), policy, None, over_limit, timeout)
+                .await;
+            info!(
+                "Fallback step {} executed: channel={:?}, when={:?}, id={:?}",
+                number,
+                step.channel,
+                step.when,
+                current.id()
+            );
+            attempts.push(attempt(&current, step, number));
+            previous = next;
+            last = Some(current);
+        }
+
+        let fallback_succeeded = attempts.iter().any(|a| a.results.iter().any(|r| r.success));
+        let mut result = match first {
+            Outcome::Sent(result) => result,
+            Outcome::Failed(failure) if !fallback_succeeded => return Err(failure),
+            Outcome::TimedOut(ms) if !fallback_succeeded => {
+                return Err(SendFailure {
+                    id: None,
+                    attempts: 0,
+                    error: timeout_error(ms),
+                })
+            }
+            Outcome::Failed(failure) => {
+                SendResult::new(failure.id, failed_results(notification, &failure.error))
+            }
+            Outcome::TimedOut(ms) => {
+                SendResult::new(None, failed_results(notification, &timeout_error(ms)))
+            }
+        };
+        result.fallback = attempts;
+        Ok(result)
+    }
+
+    /// 发送单步通知（不含降级）
+    async fn attempt(
+        &self,
+        notification: &Notification,
+        policy: &RetryPolicy,
+        scheduled_id: Option<i64>,
+        over_limit: OverLimit,
+    ) -> Result<SendResult, SendFailure> {
+        match scheduled_id {
+            Some(id) => self.context.send_scheduled(id, notification, policy).await,
+            None => {
+                self.context
+                    .send_with(notification, policy, over_limit)
+                    .await
+            }
+        }
+    }
+
+    /// 发送单步通知，设置了超时时间时超时即返回（发送在后台继续完成并写入记录）
+    async fn step(
+        &self,
+        notification: Notification,
+        policy: &RetryPolicy,
+        scheduled_id: Option<i64>,
+        over_limit: OverLimit,
+        timeout_ms: Option<u64>,
+    ) -> Outcome {
+        let Some(ms) = timeout_ms else {
+            return match self
+                .attempt(&notification, policy, scheduled_id, over_limit)
+                .await
+            {
+                Ok(result) => Outcome::Sent(result),
+                Err(failure) => Outcome::Failed(failure),
+            };
+        };
+
+        let context = self.context.clone();
+        let policy = policy.clone();
+        let task = tokio::spawn(async move {
+            match scheduled_id {
+                Some(id) => context.send_scheduled(id, &notification, &policy).await,
+                None => context.send_with(&notification, &policy, over_limit).await,
+            }
+        });
+        match tokio::time::timeout(Duration::from_millis(ms), task).await {
+            Ok(Ok(Ok(result))) => Outcome::Sent(result),
+            Ok(Ok(Err(failure))) => Outcome::Failed(failure),
+            Ok(Err(e)) => Outcome::Failed(SendFailure {
+                id: None,
+                attempts: 0,
+                error: NotifyError::Send(format!("发送任务异常退出: {}", e)),
+            }),
+            Err(_) => Outcome::TimedOut(ms),
+        }
+    }
+
+    /// 写入未读降级的定时通知
+    async fn defer(
+        &self,
+        notification: &Notification,
+        step: &FallbackStep,
+        number: u32,
+    ) -> FallbackAttempt {
+        let (id, error) = match self.context.send(notification).await {
+            Ok(result) => (result.id, None),
+            Err(e) => {
+                warn!("Failed to schedule unread fallback step {}: {}", number, e);
+                (None, Some(e.to_string()))
+            }
+        };
+        FallbackAttempt {
+            step: number,
+            channel: step.channel,
+            when: step.when,
+            id,
+            results: Vec::new(),
+            error,
+            scheduled_at: notification.send_at,
+        }
+    }
+
+    /// 站内消息中是否有已读消息，查询失败视为未读
+    async fn any_read(&self, ids: &[i64]) -> bool {
+        let Some(site_messages) = &self.site_messages else {
+            return false;
+        };
+        site_messages.any_read(ids).await.unwrap_or_else(|e| {
+            warn!("Failed to check site message read status: {}", e);
+            false
+        })
+    }
+}
+
+/// 根据上一步的通知生成本步的通知，未设置的字段沿用上一步
+///
+/// `failed` 为上一步部分失败时的失败接收者，沿用的接收者只保留其中的接收者
+fn next_notification(
+    previous: &Notification,
+    step: &FallbackStep,
+    parent_id: Option<i64>,
+    number: u32,
+    failed: Option<&[String]>,
+) -> Notification {
+    let inherit = |recipients: &[String]| -> Vec<String> {
+        recipients
+            .iter()
+            .filter(|r| failed.is_none_or(|failed| failed.contains(r)))
+            .cloned()
+            .collect()
+    };
+    let email = step.channel == ChannelType::Email && previous.channel == ChannelType::Email;
+    let template_code = match (&step.template_code, &step.body) {
+        (Some(code), _) => Some(code.clone()),
+        (None, Some(_)) => None,
+        (None, None) => previous.template_code.clone(),
+    };
+    let sms = step.channel == ChannelType::Sms;
+    Notification {
+        from: if email {
+            previous.from.clone()
+        } else {
+            String::new()
+        },
+        email_profile: if email {
+            previous.email_profile.clone()
+        } else {
+            None
+        },
+        to: if step.to.is_empty() {
+            inherit(&previous.to)
+        } else {
+            step.to.clone()
+        },
+        cc: if email {
+            inherit(&previous.cc)
+        } else {
+            Vec::new()
+        },
+        bcc: if email {
+            inherit(&previous.bcc)
+        } else {
+            Vec::new()
+        },
+        subject: step
+            .subject
+            .clone()
+            .unwrap_or_else(|| previous.subject.clone()),
+        body: step.body.clone().unwrap_or_else(|| previous.body.clone()),
+        // 邮件降级到邮件时沿用 HTML 正文（本步未指定 body 时）和附件
+        html: if email && step.body.is_none() {
+            previous.html.clone()
+        } else {
+            None
+        },
+        attachments: if email {
+            previous.attachments.clone()
+        } else {
+            Vec::new()
+        },
+        sms_template_code: if sms {
+            step.sms_template_code
+                .clone()
+                .or_else(|| previous.sms_template_code.clone())
+        } else {
+            None
+        },
+        sms_params: if !sms {
+            Default::default()
+        } else if step.sms_params.is_empty() {
+            previous.sms_params.clone()
+        } else {
+            step.sms_params.clone()
+        },
+        template_code,
+        locale: previous.locale.clone(),
+        variables: previous.variables.clone(),
+        correlation_id: previous.correlation_id.clone(),
+        tenant: previous.tenant.clone(),
+        // 各步使用独立的幂等键，重复请求时已完成的步骤不会重复发送
+        idempotency_key: previous.idempotency_key.as_deref().map(|key| {
+            let root = key.split(":fallback:").next().unwrap_or(key);
+            format!("{}:fallback:{}", root, number)
+        }),
+        fallback_of: Some(FallbackOrigin {
+            parent_id,
+            step: number,
+            unless_read: Vec::new(),
+        }),
+        ..Notification::new(step.channel)
+    }
+}
+
+/// 单步发送结果转换为降级步骤结果
+fn attempt(outcome: &Outcome, step: &FallbackStep, number: u32) -> FallbackAttempt {
+    let (results, error) = match outcome {
+        Outcome::Sent(result) => (result.results.clone(), None),
+        Outcome::Failed(failure) => (Vec::new(), Some(failure.error.to_string())),
+        Outcome::TimedOut(ms) => (Vec::new(), Some(timeout_error(*ms).to_string())),
+    };
+    FallbackAttempt {
+        step: number,
+        channel: step.channel,
+        when: step.when,
+        id: outcome.id(),
+        results,
+        error,
+        scheduled_at: None,
+    }
+}
+
+/// 发送成功的站内消息 ID
+fn site_message_ids(outcome: &Outcome) -> Vec<i64> {
+    let Outcome::Sent(result) = outcome else {
+        return Vec::new();
+    };
+    result
+        .results
+        .iter()
+        .filter(|r| r.success)
+        .filter_map(|r| r.provider_message_id.as_deref()?.parse().ok())
+        .collect()
+}
+
+/// 为全部接收者生成失败结果
+fn failed_results(notification: &Notification, error: &NotifyError) -> Vec<RecipientResult> {
+    notification
+        .all_recipients()
+        .into_iter()
+        .map(|recipient| RecipientResult::failed(recipient, error))
+        .collect()
+}
+
+fn timeout_error(ms: u64) -> NotifyError {
+    NotifyError::Send(format!("{} ms 内未完成发送", ms))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn step(value: serde_json::Value) -> FallbackStep {
+        serde_json::from_value(value).unwrap()
+    }
+
+    fn email() -> Notification {
+        Notification {
+            to: vec!["a@example.com".to_string(), "b@example.com".to_string()],
+            cc: vec!["c@example.com".to_string()],
+            bcc: vec!["d@example.com".to_string()],
+            subject: "Hi".to_string(),
+            body: "Hello".to_string(),
+            ..Notification::new(ChannelType::Email)
+        }
+    }
+
+    #[test]
+    fn inherits_all_recipients_without_failures() {
+        let next = next_notification(&email(), &step(json!({"channel": "email"})), None, 1, None);
+        assert_eq!(next.to, email().to);
+        assert_eq!(next.cc, email().cc);
+        assert_eq!(next.bcc, email().bcc);
+    }
+
+    #[test]
+    fn inherits_only_failed_recipients() {
+        let failed = ["b@example.com".to_string(), "d@example.com".to_string()];
+        let next = next_notification(
+            &email(),
+            &step(json!({"channel": "email"})),
+            Some(1),
+            1,
+            Some(&failed),
+        );
+        assert_eq!(next.to, vec!["b@example.com"]);
+        assert!(next.cc.is_empty());
+        assert_eq!(next.bcc, vec!["d@example.com"]);
+        assert_eq!(next.fallback_of.unwrap().parent_id, Some(1));
+    }
+
+    #[test]
+    fn explicit_recipients_are_kept() {
+        let failed = ["b@example.com".to_string()];
+        let next = next_notification(
+            &email(),
+            &step(json!({"channel": "sms", "to": "13800000000"})),
+            None,
+            1,
+            Some(&failed),
+        );
+        assert_eq!(next.to, vec!["13800000000"]);
+        assert!(next.cc.is_empty());
+    }
+}
@@ -0,0 +1,300 @@
+use crate::config::IdempotencyConfig;
+use crate::error::{NotifyError, NotifyResult};
+use crate::models::{
+    IdempotencyRecord, Notification, SendResult, IDEMPOTENCY_COMPLETED, IDEMPOTENCY_PROCESSING,
+};
+use async_trait::async_trait;
+use deadpool_redis::redis;
+use sqlxplus::{Crud, DbPool, QueryBuilder, SqlxPlusError, UpdateBuilder};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// 幂等键最大长度
+const MAX_KEY_LEN: usize = 128;
+/// Redis 键前缀
+const REDIS_KEY_PREFIX: &str = "ms-notify:idempotency:";
+
+/// 占用幂等键的结果
+pub enum Claim {
+    /// 首次请求（或上次请求已过期、失败），已占用幂等键，继续发送
+    Acquired,
+    /// 重复请求，返回首次发送结果
+    Completed(SendResult),
+}
+
+/// 幂等键存储
+#[async_trait]
+pub trait IdempotencyStore: Send + Sync {
+    /// 占用幂等键，`lease` 内未完成（如进程崩溃）则允许重新占用
+    ///
+    /// 相同幂等键的请求正在处理中时返回 `IdempotencyInProgress`
+    async fn claim(&self, key: &str, lease: Duration) -> NotifyResult<Claim>;
+
+    /// 保存发送结果，`window` 内的重复请求返回该结果
+    async fn complete(&self, key: &str, result: &SendResult, window: Duration) -> NotifyResult<()>;
+
+    /// 释放幂等键（发送失败时调用，允许相同幂等键重新发送）
+    async fn release(&self, key: &str) -> NotifyResult<()>;
+}
+
+/// 幂等去重服务
+///
+/// 同一幂等键在去重窗口内只发送一次，重复请求返回首次发送结果；
+/// 发送失败会释放幂等键，调用方可使用相同幂等键重试
+pub struct IdempotencyService {
+    store: Arc<dyn IdempotencyStore>,
+    window: Duration,
+    lease: Duration,
+}
+
+impl IdempotencyService {
+    /// 创建幂等去重服务
+    ///
+    /// # 参数
+    /// - `store`: 幂等键存储（Redis 或数据库）
+    /// - `config`: 幂等去重配置
+    pub fn new(store: Arc<dyn IdempotencyStore>, config: &IdempotencyConfig) -> Self {
+        Self {
+            store,
+            window: Duration::from_secs(config.window_secs),
+            lease: Duration::from_secs(config.processing_timeout_secs),
+        }
+    }
+
+    /// 通知的幂等键（带租户前缀，不同租户的相同幂等键互不影响），未设置时返回 None
+    pub fn key(notification: &Notification) -> NotifyResult<Option<String>> {
+        let Some(key) = notification.idempotency_key.as_deref().map(str::trim) else {
+            return Ok(None);
+        };
+        if key.is_empty() || key.len() > MAX_KEY_LEN {
+            return Err(NotifyError::InvalidMessage(format!(
+                "idempotency_key must be 1-{} characters",
+                MAX_KEY_LEN
+            )));
+        }
+        Ok(Some(match notification.tenant.as_deref() {
+            Some(tenant) => format!("{}:{}", tenant, key),
+            None => key.to_string(),
+        }))
+    }
+
+    /// 占用幂等键
+    pub async fn claim(&self, key: &str) -> NotifyResult<Claim> {
+        self.store.claim(key, self.lease).await
+    }
+
+    /// 保存发送结果
+    pub async fn complete(&self, key: &str, result: &SendResult) -> NotifyResult<()> {
+        self.store.complete(key, result, self.window).await
+    }
+
+    /// 释放幂等键
+    pub async fn release(&self, key: &str) -> NotifyResult<()> {
+        self.store.release(key).await
+    }
+}
+
+/// 基于数据库唯一键的幂等键存储
+pub struct MySqlIdempotencyStore {
+    db_pool: Arc<DbPool>,
+}
+
+impl MySqlIdempotencyStore {
+    /// 创建数据库幂等键存储
+    pub fn new(db_pool: Arc<DbPool>) -> Self {
+        Self { db_pool }
+    }
+}
+
+#[async_trait]
+impl IdempotencyStore for MySqlIdempotencyStore {
+    async fn claim(&self, key: &str, lease: Duration) -> NotifyResult<Claim> {
+        let pool = self.db_pool.mysql_pool();
+        let now = chrono::Utc::now().timestamp_millis();
+        let expires_at = now + lease.as_millis() as i64;
+
+        let record = IdempotencyRecord {
+            idempotency_key: Some(key.to_string()),
+            status: Some(IDEMPOTENCY_PROCESSING.to_string()),
+            expires_at: Some(expires_at),
+            created_at: Some(now),
+            updated_at: Some(now),
+            is_del: Some(0),
+            ..Default::default()
+        };
+        match record.insert(pool).await {
+            Ok(_) => return Ok(Claim::Acquired),
+            Err(e) if is_unique_violation(&e) => {}
+            Err(e) => return Err(e.into()),
+        }
+
+        let existing =
+            IdempotencyRecord::find_one(pool, QueryBuilder::new("").and_eq("idempotency_key", key))
+                .await?
+                .ok_or_else(|| NotifyError::IdempotencyInProgress(key.to_string()))?;
+        let previous_expires_at = existing.expires_at.unwrap_or_default();
+        if previous_expires_at > now {
+            return match existing.status.as_deref() {
+                Some(IDEMPOTENCY_COMPLETED) => parse_result(existing.result.as_deref()),
+                _ => Err(NotifyError::IdempotencyInProgress(key.to_string())),
+            };
+        }
+
+        // 已过期：以原过期时间为条件接管，并发请求中只有一个能接管成功
+        let takeover = IdempotencyRecord {
+            status: Some(IDEMPOTENCY_PROCESSING.to_string()),
+            expires_at: Some(expires_at),
+            updated_at: Some(now),
+            ..Default::default()
+        };
+        let updated = UpdateBuilder::new(takeover)
+            .fields(&["status", "expires_at", "updated_at"])
+            .condition(|b| {
+                b.and_eq("idempotency_key", key)
+                    .and_eq("expires_at", previous_expires_at)
+            })
+            .execute(pool)
+            .await?;
+        if updated == 0 {
+            return Err(NotifyError::IdempotencyInProgress(key.to_string()));
+        }
+        Ok(Claim::Acquired)
+    }
+
+    async fn complete(&self, key: &str, result: &SendResult, window: Duration) -> NotifyResult<()> {
+        let now = chrono::Utc::now().timestamp_millis();
+        let update = IdempotencyRecord {
+            status: Some(IDEMPOTENCY_COMPLETED.to_string()),
+            notification_id: result.id,
+            result: Some(serde_json::to_string(result).unwrap_or_default()),
+            expires_at: Some(now + window.as_millis() as i64),
+            updated_at: Some(now),
+            ..Default::default()
+        };
+        UpdateBuilder::new(update)
+            .fields(&[
+                "status",
+                "notification_id",
+                "result",
+                "expires_at",
+                "updated_at",
+            ])
+            .condition(|b| b.and_eq("idempotency_key", key))
+            .execute(self.db_pool.mysql_pool())
+            .await?;
+        Ok(())
+    }
+
+    async fn release(&self, key: &str) -> NotifyResult<()> {
+        // 置为已过期，下次请求可直接接管
+        let update = IdempotencyRecord {
+            expires_at: Some(0),
+            updated_at: Some(chrono::Utc::now().timestamp_millis()),
+            ..Default::default()
+        };
+        UpdateBuilder::new(update)
+            .fields(&["expires_at", "updated_at"])
+            .condition(|b| {
+                b.and_eq("idempotency_key", key)
+                    .and_eq("status", IDEMPOTENCY_PROCESSING)
+            })
+            .execute(self.db_pool.mysql_pool())
+            .await?;
+        Ok(())
+    }
+}
+
+/// 基于 Redis `SET NX` 的幂等键存储
+pub struct RedisIdempotencyStore {
+    pool: deadpool_redis::Pool,
+}
+
+impl RedisIdempotencyStore {
+    /// 创建 Redis 幂等键存储
+    pub fn new(pool: deadpool_redis::Pool) -> Self {
+        Self { pool }
+    }
+
+    async fn connection(&self) -> NotifyResult<deadpool_redis::Connection> {
+        self.pool
+            .get()
+            .await
+            .map_err(|e| NotifyError::Redis(e.to_string()))
+    }
+}
+
+#[async_trait]
+impl IdempotencyStore for RedisIdempotencyStore {
+    async fn claim(&self, key: &str, lease: Duration) -> NotifyResult<Claim> {
+        let mut conn = self.connection().await?;
+        let redis_key = format!("{}{}", REDIS_KEY_PREFIX, key);
+
+        // 占用失败后读取时键可能恰好过期，再尝试占用一次
+        for _ in 0..2 {
+            let acquired: Option<String> = redis::cmd("SET")
+                .arg(&redis_key)
+                .arg(IDEMPOTENCY_PROCESSING)
+                .arg("NX")
+                .arg("PX")
+                .arg(lease.as_millis() as u64)
+                .query_async(&mut conn)
+                .await
+                .map_err(redis_error)?;
+            if acquired.is_some() {
+                return Ok(Claim::Acquired);
+            }
+
+            let value: Option<String> = redis::cmd("GET")
+                .arg(&redis_key)
+                .query_async(&mut conn)
+                .await
+                .map_err(redis_error)?;
+            match value.as_deref() {
+                None => continue,
+                Some(IDEMPOTENCY_PROCESSING) => break,
+                Some(result) => return parse_result(Some(result)),
+            }
+        }
+        Err(NotifyError::IdempotencyInProgress(key.to_string()))
+    }
+
+    async fn complete(&self, key: &str, result: &SendResult, window: Duration) -> NotifyResult<()> {
+        let mut conn = self.connection().await?;
+        redis::cmd("SET")
+            .arg(format!("{}{}", REDIS_KEY_PREFIX, key))
+            .arg(serde_json::to_string(result).unwrap_or_default())
+            .arg("PX")
+            .arg(window.as_millis() as u64)
+            .query_async::<()>(&mut conn)
+            .await
+            .map_err(redis_error)
+    }
+
+    async fn release(&self, key: &str) -> NotifyResult<()> {
+        let mut conn = self.connection().await?;
+        redis::cmd("DEL")
+            .arg(format!("{}{}", REDIS_KEY_PREFIX, key))
+            .query_async::<()>(&mut conn)
+            .await
+            .map_err(redis_error)
+    }
+}
+
+/// 解析保存的首次发送结果
+fn parse_result(result: Option<&str>) -> NotifyResult<Claim> {
+    serde_json::from_str(result.unwrap_or_default())
+        .map(Claim::Completed)
+        .map_err(|e| NotifyError::Send(format!("幂等键保存的发送结果无法解析: {}", e)))
+}
+
+/// 是否为唯一键冲突
+pub(super) fn is_unique_violation(error: &SqlxPlusError) -> bool {
+    matches!(
+        error,
+        SqlxPlusError::DatabaseError(sqlx::Error::Database(e)) if e.is_unique_violation()
+    )
+}
+
+fn redis_error(error: redis::RedisError) -> NotifyError {
+    NotifyError::Redis(error.to_string())
+}
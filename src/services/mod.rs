@@ -0,0 +1,23 @@
+mod dead_letter;
+mod fallback;
+mod idempotency;
+mod notification_record;
+mod poison;
+mod rate_limit;
+mod result_event;
+mod scheduler;
+mod site_message;
+mod template;
+
+pub use dead_letter::{DeadLetterEntry, DeadLetterService};
+pub use fallback::FallbackOrchestrator;
+pub use idempotency::{
+    Claim, IdempotencyService, IdempotencyStore, MySqlIdempotencyStore, RedisIdempotencyStore,
+};
+pub use notification_record::NotificationRecordService;
+pub use poison::PoisonMessageService;
+pub use rate_limit::RateLimiter;
+pub use result_event::{ResultEventService, SendAttempt};
+pub use scheduler::NotificationScheduler;
+pub use site_message::SiteMessageService;
+pub use template::TemplateService;
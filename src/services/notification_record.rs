@@ -0,0 +1,491 @@
+use crate::error::{NotifyError, NotifyResult};
+use crate::models::{
+    Notification, NotificationDetail, NotificationFilter, NotificationStatus, NotifyRecord,
+    NotifyRecordRecipient, RecipientResult,
+};
+use fbc_starter::CursorPageBaseResp;
+use sqlxplus::{Crud, DbPool, QueryBuilder, UpdateBuilder};
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// 单页最大条数
+const MAX_PAGE_SIZE: u32 = 100;
+/// 默认每页条数
+const DEFAULT_PAGE_SIZE: u32 = 20;
+/// 按接收者过滤时最多匹配的通知数
+const MAX_RECIPIENT_MATCHES: u64 = 1000;
+
+/// 通知发送记录服务
+pub struct NotificationRecordService {
+    db_pool: Arc<DbPool>,
+}
+
+impl NotificationRecordService {
+    /// 创建发送记录服务
+    ///
+    /// # 参数
+    /// - `db_pool`: 数据库连接池
+    pub fn new(db_pool: Arc<DbPool>) -> Self {
+        Self { db_pool }
+    }
+
+    /// 写入发送记录及各接收者记录，返回通知 ID
+    ///
+    /// 以 `Sending` 状态写入时视为已开始第一次发送尝试；
+    /// 以 `Scheduled` 状态写入时记录计划发送时间，由调度器到期后发送；
+    /// 以 `Pending` 状态写入时为异步发送的排队通知，由调度器尽快发送
+    pub async fn create(
+        &self,
+        notification: &Notification,
+        status: NotificationStatus,
+    ) -> NotifyResult<i64> {
+        let pool = self.db_pool.mysql_pool();
+        let payload = serde_json::to_string(notification)
+            .map_err(|e| NotifyError::Send(format!("通知序列化失败: {}", e)))?;
+        let recipients = notification.all_recipients();
+        let channel = notification.channel.as_str();
+        let now = chrono::Utc::now().timestamp_millis();
+
+        let record = NotifyRecord {
+            channel: Some(channel.to_string()),
+            subject: Some(notification.subject.clone()),
+            template_code: notification.template_code.clone(),
+            status: Some(status.as_str().to_string()),
+            attempts: Some(i32::from(status == NotificationStatus::Sending)),
+            recipient_count: Some(recipients.len() as i32),
+            success_count: Some(0),
+            payload: Some(payload),
+            scheduled_at: notification.send_at.filter(|_| {
+                matches!(
+                    status,
+                    NotificationStatus::Scheduled | NotificationStatus::Pending
+                )
+            }),
+            parent_id: notification
+                .fallback_of
+                .as_ref()
+                .and_then(|origin| origin.parent_id),
+            fallback_step: notification
+                .fallback_of
+                .as_ref()
+                .map(|origin| origin.step as i32),
+            created_at: Some(now),
+            updated_at: Some(now),
+            is_del: Some(0),
+            ..Default::default()
+        };
+        let id = record.insert(pool).await?;
+
+        for recipient in recipients {
+            let row = NotifyRecordRecipient {
+                record_id: Some(id),
+                channel: Some(channel.to_string()),
+                recipient: Some(recipient),
+                status: Some(status.as_str().to_string()),
+                created_at: Some(now),
+                updated_at: Some(now),
+                is_del: Some(0),
+                ..Default::default()
+            };
+            row.insert(pool).await?;
+        }
+        Ok(id)
+    }
+
+    /// 开始一次新的发送尝试：状态置为 sending，尝试次数加 1，调度器取出的通知续期租约
+    pub async fn mark_sending(&self, id: i64) -> NotifyResult<()> {
+        let record = self.find(id).await?;
+        let now = chrono::Utc::now().timestamp_millis();
+        let update = NotifyRecord {
+            id: Some(id),
+            status: Some(NotificationStatus::Sending.as_str().to_string()),
+            attempts: Some(record.attempts.unwrap_or(0) + 1),
+            claimed_at: record.claimed_at.map(|_| now),
+            updated_at: Some(now),
+            ..Default::default()
+        };
+        update.update(self.db_pool.mysql_pool()).await?;
+        Ok(())
+    }
+
+    /// 取出已到期的定时通知和排队中的异步通知，逐条以状态为条件置为 sending 并记录取出时间
+    /// （多实例部署时每条只会被一个实例取出）
+    ///
+    /// 取出后超过 `lease` 仍处于发送中的通知（如实例崩溃）以取出时间为条件重新取出；
+    /// 通知内容无法解析的记录直接标记为失败
+    pub async fn claim_due(
+        &self,
+        limit: u64,
+        lease: Duration,
+    ) -> NotifyResult<Vec<(i64, Notification)>> {
+        let pool = self.db_pool.mysql_pool();
+        let now = chrono::Utc::now().timestamp_millis();
+        let mut due = NotifyRecord::find_all(
+            pool,
+            Some(
+                QueryBuilder::new("")
+                    .and_in("status", queued_statuses())
+                    .and_le("scheduled_at", now)
+                    .order_by("scheduled_at", true)
+                    .limit(limit),
+            ),
+        )
+        .await?;
+        let remaining = limit.saturating_sub(due.len() as u64);
+        if remaining > 0 {
+            due.extend(
+                NotifyRecord::find_all(
+                    pool,
+                    Some(
+                        QueryBuilder::new("")
+                            .and_eq("status", NotificationStatus::Sending.as_str())
+                            .and_le("claimed_at", now - lease.as_millis() as i64)
+                            .order_by("claimed_at", true)
+                            .limit(remaining),
+                    ),
+                )
+                .await?,
+            );
+        }
+
+        let mut claimed = Vec::with_capacity(due.len());
+        for record in due {
+            let Some(id) = record.id else { continue };
+            let expired = record
+                .claimed_at
+                .filter(|_| record.status.as_deref() == Some(NotificationStatus::Sending.as_str()));
+            let update = NotifyRecord {
+                status: Some(NotificationStatus::Sending.as_str().to_string()),
+                attempts: Some(record.attempts.unwrap_or(0) + 1),
+                claimed_at: Some(now),
+                updated_at: Some(now),
+                ..Default::default()
+            };
+            let updated = UpdateBuilder::new(update)
+                .fields(&["status", "attempts", "claimed_at", "updated_at"])
+                .condition(|b| match expired {
+                    Some(claimed_at) => b
+                        .and_eq("id", id)
+                        .and_eq("status", NotificationStatus::Sending.as_str())
+                        .and_eq("claimed_at", claimed_at),
+                    None => b.and_eq("id", id).and_in("status", queued_statuses()),
+                })
+                .execute(pool)
+                .await?;
+            if updated == 0 {
+                continue;
+            }
+            if let Some(claimed_at) = expired {
+                tracing::warn!(
+                    "Notification lease expired, reclaimed: id={}, claimed_at={}",
+                    id,
+                    claimed_at
+                );
+            }
+
+            match serde_json::from_str::<Notification>(record.payload.as_deref().unwrap_or("")) {
+                Ok(notification) => claimed.push((id, notification)),
+                Err(e) => {
+                    let error = NotifyError::InvalidMessage(format!("定时通知内容无法解析: {}", e));
+                    self.complete(id, &Err(error)).await?;
+                }
+            }
+        }
+        Ok(claimed)
+    }
+
+    /// 将发送中的通知改为定时发送（如超出限流需延后），由调度器到期后重新取出
+    pub async fn reschedule(&self, id: i64, send_at: i64) -> NotifyResult<()> {
+        let update = NotifyRecord {
+            status: Some(NotificationStatus::Scheduled.as_str().to_string()),
+            scheduled_at: Some(send_at),
+            updated_at: Some(chrono::Utc::now().timestamp_millis()),
+            ..Default::default()
+        };
+        let updated = UpdateBuilder::new(update)
+            .fields(&["status", "scheduled_at", "updated_at"])
+            .condition(|b| {
+                b.and_eq("id", id)
+                    .and_eq("status", NotificationStatus::Sending.as_str())
+            })
+            .execute(self.db_pool.mysql_pool())
+            .await?;
+        // 已完成（或已被改为其他状态）的记录保持不变
+        if updated == 0 {
+            return Ok(());
+        }
+        self.update_recipients(id, None, NotificationStatus::Scheduled, None, None)
+            .await
+    }
+
+    /// 取消待定时发送或排队中的通知，已开始发送或已完成的通知不可取消
+    pub async fn cancel(&self, id: i64) -> NotifyResult<()> {
+        self.find(id).await?;
+        let mut updated = self
+            .mark_cancelled(id, NotificationStatus::Scheduled, None)
+            .await?;
+        if updated == 0 {
+            updated = self
+                .mark_cancelled(id, NotificationStatus::Pending, None)
+                .await?;
+        }
+        if updated == 0 {
+            return Err(NotifyError::NotificationNotCancellable(id));
+        }
+        Ok(())
+    }
+
+    /// 已取出的定时通知到期后无需发送（如未读降级时站内消息已读），标记为已取消
+    pub async fn skip(&self, id: i64, reason: &str) -> NotifyResult<()> {
+        self.mark_cancelled(id, NotificationStatus::Sending, Some(reason))
+            .await?;
+        Ok(())
+    }
+
+    /// 以当前状态为条件将通知及其接收者标记为已取消，返回更新的通知数
+    async fn mark_cancelled(
+        &self,
+        id: i64,
+        from: NotificationStatus,
+        reason: Option<&str>,
+    ) -> NotifyResult<u64> {
+        let update = NotifyRecord {
+            status: Some(NotificationStatus::Cancelled.as_str().to_string()),
+            error: reason.map(str::to_string),
+            updated_at: Some(chrono::Utc::now().timestamp_millis()),
+            ..Default::default()
+        };
+        let updated = UpdateBuilder::new(update)
+            .fields(&["status", "error", "updated_at"])
+            .condition(|b| b.and_eq("id", id).and_eq("status", from.as_str()))
+            .execute(self.db_pool.mysql_pool())
+            .await?;
+        if updated > 0 {
+            self.update_recipients(id, None, NotificationStatus::Cancelled, None, reason)
+                .await?;
+        }
+        Ok(updated)
+    }
+
+    /// 记录一次发送尝试的结果
+    ///
+    /// 结果相同的接收者合并为一次批量更新（批量短信、单封邮件通常只需一次更新）
+    pub async fn complete(
+        &self,
+        id: i64,
+        outcome: &NotifyResult<Vec<RecipientResult>>,
+    ) -> NotifyResult<()> {
+        let pool = self.db_pool.mysql_pool();
+        let now = chrono::Utc::now().timestamp_millis();
+
+        let (status, success_count, error) = match outcome {
+            Err(e) => return self.fail(id, e).await,
+            Ok(results) => {
+                let mut groups: BTreeMap<_, Vec<String>> = BTreeMap::new();
+                for r in results {
+                    groups
+                        .entry((r.success, r.provider_message_id.clone(), r.error.clone()))
+                        .or_default()
+                        .push(r.recipient.clone());
+                }
+                for ((success, provider_id, error), recipients) in groups {
+                    let status = if success {
+                        NotificationStatus::Sent
+                    } else {
+                        NotificationStatus::Failed
+                    };
+                    self.update_recipients(
+                        id,
+                        Some(recipients),
+                        status,
+                        provider_id.as_deref(),
+                        error.as_deref(),
+                    )
+                    .await?;
+                }
+
+                let success_count = results.iter().filter(|r| r.success).count();
+                let status = match success_count {
+                    0 => NotificationStatus::Failed,
+                    n if n == results.len() => NotificationStatus::Sent,
+                    _ => NotificationStatus::Partial,
+                };
+                let error = results.iter().find_map(|r| r.error.clone());
+                (status, success_count as i32, error)
+            }
+        };
+
+        let update = NotifyRecord {
+            id: Some(id),
+            status: Some(status.as_str().to_string()),
+            success_count: Some(success_count),
+            error,
+            sent_at: (status != NotificationStatus::Failed).then_some(now),
+            updated_at: Some(now),
+            ..Default::default()
+        };
+        update.update(pool).await?;
+        Ok(())
+    }
+
+    /// 记录全部接收者发送失败
+    pub async fn fail(&self, id: i64, error: &NotifyError) -> NotifyResult<()> {
+        let error = error.to_string();
+        self.update_recipients(id, None, NotificationStatus::Failed, None, Some(&error))
+            .await?;
+        let update = NotifyRecord {
+            id: Some(id),
+            status: Some(NotificationStatus::Failed.as_str().to_string()),
+            success_count: Some(0),
+            error: Some(error),
+            updated_at: Some(chrono::Utc::now().timestamp_millis()),
+            ..Default::default()
+        };
+        update.update(self.db_pool.mysql_pool()).await?;
+        Ok(())
+    }
+
+    /// 查询通知详情
+    pub async fn get(&self, id: i64) -> NotifyResult<NotificationDetail> {
+        let record = self.find(id).await?;
+        let recipients = NotifyRecordRecipient::find_all(
+            self.db_pool.mysql_pool(),
+            Some(
+                QueryBuilder::new("")
+                    .and_eq("record_id", id)
+                    .order_by("id", true),
+            ),
+        )
+        .await?;
+        Ok(NotificationDetail { record, recipients })
+    }
+
+    /// 按游标分页查询发送记录（按 ID 倒序）
+    ///
+    /// # 参数
+    /// - `cursor`: 上一页返回的游标（首页为 None）
+    /// - `page_size`: 每页条数
+    /// - `filter`: 查询条件
+    pub async fn list(
+        &self,
+        cursor: Option<u32>,
+        page_size: Option<u32>,
+        filter: NotificationFilter,
+    ) -> NotifyResult<CursorPageBaseResp<NotifyRecord>> {
+        let pool = self.db_pool.mysql_pool();
+        let page_size = page_size
+            .unwrap_or(DEFAULT_PAGE_SIZE)
+            .clamp(1, MAX_PAGE_SIZE);
+
+        // 按接收者过滤时先从接收者记录中找出对应的通知 ID
+        let record_ids = match filter.recipient.as_deref().filter(|r| !r.is_empty()) {
+            Some(recipient) => {
+                let rows = NotifyRecordRecipient::find_all(
+                    pool,
+                    Some(
+                        QueryBuilder::new("")
+                            .and_eq("recipient", recipient)
+                            .order_by("id", false)
+                            .limit(MAX_RECIPIENT_MATCHES),
+                    ),
+                )
+                .await?;
+                let mut ids: Vec<i64> = rows.into_iter().filter_map(|r| r.record_id).collect();
+                ids.sort_unstable();
+                ids.dedup();
+                if ids.is_empty() {
+                    return Ok(CursorPageBaseResp::init(None, false, Vec::new(), 0));
+                }
+                Some(ids)
+            }
+            None => None,
+        };
+
+        let apply = |mut builder: QueryBuilder| {
+            if let Some(channel) = filter.channel {
+                builder = builder.and_eq("channel", channel.as_str());
+            }
+            if let Some(status) = filter.status {
+                builder = builder.and_eq("status", status.as_str());
+            }
+            if let Some(start) = filter.start_time {
+                builder = builder.and_ge("created_at", start);
+            }
+            if let Some(end) = filter.end_time {
+                builder = builder.and_lt("created_at", end);
+            }
+            if let Some(ids) = &record_ids {
+                builder = builder.and_in("id", ids.clone());
+            }
+            builder
+        };
+
+        let total = NotifyRecord::count(pool, apply(QueryBuilder::new(""))).await?;
+
+        let mut builder = apply(QueryBuilder::new(""));
+        if let Some(cursor) = cursor.filter(|c| *c > 0) {
+            builder = builder.and_lt("id", cursor as i64);
+        }
+        let builder = builder.order_by("id", false);
+
+        // 倒序翻页由 builder 自行追加 id < cursor 条件，因此不向 paginate_cursor 传游标
+        let page = NotifyRecord::paginate_cursor(pool, builder, None, page_size).await?;
+        let next_cursor = page
+            .next_cursor
+            .filter(|_| page.has_next)
+            .and_then(|id| u32::try_from(id).ok());
+
+        Ok(CursorPageBaseResp::init(
+            next_cursor,
+            page.has_next,
+            page.items,
+            total as i64,
+        ))
+    }
+
+    /// 查询发送记录，不存在时返回 NotificationNotFound
+    async fn find(&self, id: i64) -> NotifyResult<NotifyRecord> {
+        NotifyRecord::find_by_id(self.db_pool.mysql_pool(), id)
+            .await?
+            .ok_or(NotifyError::NotificationNotFound(id))
+    }
+
+    /// 更新接收者记录状态，`recipients` 为 None 时更新该通知的全部接收者
+    async fn update_recipients(
+        &self,
+        record_id: i64,
+        recipients: Option<Vec<String>>,
+        status: NotificationStatus,
+        provider_message_id: Option<&str>,
+        error: Option<&str>,
+    ) -> NotifyResult<()> {
+        let update = NotifyRecordRecipient {
+            status: Some(status.as_str().to_string()),
+            provider_message_id: provider_message_id.map(str::to_string),
+            error: error.map(str::to_string),
+            updated_at: Some(chrono::Utc::now().timestamp_millis()),
+            ..Default::default()
+        };
+        UpdateBuilder::new(update)
+            .fields(&["status", "provider_message_id", "error", "updated_at"])
+            .condition(|b| {
+                let b = b.and_eq("record_id", record_id).and_eq("is_del", 0);
+                match recipients {
+                    Some(recipients) => b.and_in("recipient", recipients),
+                    None => b,
+                }
+            })
+            .execute(self.db_pool.mysql_pool())
+            .await?;
+        Ok(())
+    }
+}
+
+/// 等待调度器取出的状态（定时通知、异步发送排队中的通知）
+fn queued_statuses() -> Vec<&'static str> {
+    vec![
+        NotificationStatus::Scheduled.as_str(),
+        NotificationStatus::Pending.as_str(),
+    ]
+}
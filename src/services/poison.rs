@@ -0,0 +1,58 @@
+use crate::kafka::Rejection;
+use fbc_starter::{Message, MessageProducerType};
+use serde_json::json;
+
+/// 发布到 Kafka 时使用的消息来源
+const SOURCE: &str = "ms-notify";
+
+/// 毒消息服务
+///
+/// 无法解析或校验失败的消息重试也不会成功，不进入死信，
+/// 而是连同失败原因转发到毒消息 topic，供上游排查后修正重发
+pub struct PoisonMessageService {
+    producer: Option<MessageProducerType>,
+    topic: String,
+}
+
+impl PoisonMessageService {
+    /// 创建毒消息服务
+    ///
+    /// # 参数
+    /// - `producer`: Kafka 生产者（可选）
+    /// - `topic`: 毒消息 topic
+    pub fn new(producer: Option<MessageProducerType>, topic: impl Into<String>) -> Self {
+        Self {
+            producer,
+            topic: topic.into(),
+        }
+    }
+
+    /// 转发一条毒消息，失败只记日志
+    pub async fn publish(&self, message: &Message, rejection: &Rejection) {
+        let Some(producer) = &self.producer else {
+            tracing::error!(
+                topic = %message.topic,
+                "毒消息无处保存（未配置 Kafka 生产者），消息已丢弃: {}, data: {}",
+                rejection,
+                message.data
+            );
+            return;
+        };
+
+        let data = json!({
+            "original_topic": message.topic,
+            "original_from": message.from,
+            "original": message.data,
+            "schema_version": rejection.schema_version,
+            "message_id": rejection.message_id,
+            "violations": rejection.violations,
+            "rejected_at": chrono::Utc::now().timestamp_millis(),
+        });
+        if let Err(e) = producer
+            .publish(&self.topic, Message::new(&self.topic, SOURCE, data))
+            .await
+        {
+            tracing::error!(topic = %self.topic, "毒消息发布失败: {}, data: {}", e, message.data);
+        }
+    }
+}
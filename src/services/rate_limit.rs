@@ -0,0 +1,342 @@
+use crate::config::{RateLimitConfig, RateLimitRule};
+use crate::error::{NotifyError, NotifyResult};
+use crate::models::ChannelType;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// 接收者令牌桶数量超过该值时清理已回满的令牌桶
+const MAX_TRACKED_RECIPIENTS: usize = 10_000;
+
+/// 令牌桶：容量为窗口内最多条数，按窗口匀速补充
+struct Bucket {
+    capacity: f64,
+    /// 每秒补充的令牌数
+    rate: f64,
+    tokens: f64,
+    updated_at: Instant,
+}
+
+impl Bucket {
+    fn new(limit: u32, window_secs: u64, now: Instant) -> Self {
+        Self {
+            capacity: f64::from(limit),
+            rate: f64::from(limit) / window_secs as f64,
+            tokens: f64::from(limit),
+            updated_at: now,
+        }
+    }
+
+    fn refill(&mut self, now: Instant) {
+        let elapsed = now.duration_since(self.updated_at).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * self.rate).min(self.capacity);
+        self.updated_at = now;
+    }
+
+    /// 获得一个令牌还需等待的时间
+    fn wait(&self) -> Duration {
+        if self.tokens >= 1.0 {
+            Duration::ZERO
+        } else {
+            Duration::from_secs_f64((1.0 - self.tokens) / self.rate)
+        }
+    }
+
+    fn is_full(&self) -> bool {
+        self.tokens >= self.capacity
+    }
+}
+
+/// 一组令牌桶（同一规则的多个时间窗口）
+type Buckets = Vec<Bucket>;
+
+fn buckets(rule: &RateLimitRule, now: Instant) -> Buckets {
+    rule.windows()
+        .into_iter()
+        .map(|(limit, secs)| Bucket::new(limit, secs, now))
+        .collect()
+}
+
+/// 发送限流
+///
+/// 发送前按渠道实例和接收者检查令牌桶：所有相关令牌桶都有令牌时才一并扣除，
+/// 否则不扣除并返回需等待的时间。计数保存在进程内，多实例部署时按实例数折算配置
+pub struct RateLimiter {
+    channel_rules: HashMap<ChannelType, RateLimitRule>,
+    recipient_rules: HashMap<ChannelType, RateLimitRule>,
+    state: Mutex<State>,
+}
+
+#[derive(Default)]
+struct State {
+    /// 键为 (渠道, 实例名称)
+    channels: HashMap<(ChannelType, String), Buckets>,
+    /// 键为 (渠道, 接收者)
+    recipients: HashMap<(ChannelType, String), Buckets>,
+}
+
+impl RateLimiter {
+    /// 创建发送限流
+    ///
+    /// # 返回
+    /// - `Err(NotifyError::Config)`: 配置了未知的渠道
+    pub fn new(config: &RateLimitConfig) -> NotifyResult<Self> {
+        Ok(Self {
+            channel_rules: rules(&config.channels)?,
+            recipient_rules: rules(&config.recipients)?,
+            state: Mutex::new(State::default()),
+        })
+    }
+
+    /// 是否配置了任何限流规则
+    pub fn is_enabled(&self) -> bool {
+        !self.channel_rules.is_empty() || !self.recipient_rules.is_empty()
+    }
+
+    /// 为一次发送获取令牌（渠道实例一个，每个接收者各一个）
+    ///
+    /// # 返回
+    /// - `Err(NotifyError::ChannelRateLimited | RecipientRateLimited)`: 超出限制，未扣除任何令牌
+    pub fn acquire(
+        &self,
+        channel: ChannelType,
+        instance: &str,
+        recipients: &[String],
+    ) -> NotifyResult<()> {
+        let channel_rule = self.channel_rules.get(&channel);
+        let recipient_rule = self.recipient_rules.get(&channel);
+        if channel_rule.is_none() && recipient_rule.is_none() {
+            return Ok(());
+        }
+
+        let now = Instant::now();
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        let State {
+            channels,
+            recipients: recipient_buckets,
+        } = &mut *state;
+
+        let mut groups: Vec<&mut Buckets> = Vec::new();
+        if let Some(rule) = channel_rule {
+            let buckets = channels
+                .entry((channel, instance.to_string()))
+                .or_insert_with(|| buckets(rule, now));
+            if let Some(wait) = shortfall(buckets, now) {
+                return Err(NotifyError::ChannelRateLimited {
+                    channel: channel.as_str().to_string(),
+                    retry_after_ms: millis(wait),
+                });
+            }
+            groups.push(buckets);
+        }
+        if let Some(rule) = recipient_rule {
+            if recipient_buckets.len() > MAX_TRACKED_RECIPIENTS {
+                recipient_buckets.retain(|_, buckets| {
+                    buckets.iter_mut().any(|b| {
+                        b.refill(now);
+                        !b.is_full()
+                    })
+                });
+            }
+            for recipient in recipients {
+                let buckets = recipient_buckets
+                    .entry((channel, recipient.clone()))
+                    .or_insert_with(|| buckets(rule, now));
+                if let Some(wait) = shortfall(buckets, now) {
+                    return Err(NotifyError::RecipientRateLimited {
+                        recipient: recipient.clone(),
+                        retry_after_ms: millis(wait),
+                    });
+                }
+            }
+            // 所有接收者都有令牌后再扣除（同一批接收者重复时各扣一次）
+            for recipient in recipients {
+                if let Some(buckets) = recipient_buckets.get_mut(&(channel, recipient.clone())) {
+                    take(buckets);
+                }
+            }
+        }
+        for buckets in groups {
+            take(buckets);
+        }
+        Ok(())
+    }
+}
+
+/// 补充令牌后检查是否每个令牌桶都有令牌，不足时返回需等待的最长时间
+fn shortfall(buckets: &mut Buckets, now: Instant) -> Option<Duration> {
+    let wait = buckets
+        .iter_mut()
+        .map(|b| {
+            b.refill(now);
+            b.wait()
+        })
+        .max()
+        .unwrap_or_default();
+    (!wait.is_zero()).then_some(wait)
+}
+
+fn take(buckets: &mut Buckets) {
+    for bucket in buckets {
+        bucket.tokens -= 1.0;
+    }
+}
+
+fn millis(wait: Duration) -> u64 {
+    (wait.as_millis() as u64).max(1)
+}
+
+/// 按渠道标识解析限流规则，忽略未设置任何窗口的规则
+fn rules(
+    config: &HashMap<String, RateLimitRule>,
+) -> NotifyResult<HashMap<ChannelType, RateLimitRule>> {
+    let mut rules = HashMap::new();
+    for (key, rule) in config {
+        let channel = ChannelType::ALL
+            .into_iter()
+            .find(|c| c.as_str() == key.as_str())
+            .ok_or_else(|| NotifyError::Config(format!("限流配置中的渠道未知: {}", key)))?;
+        if !rule.windows().is_empty() {
+            rules.insert(channel, rule.clone());
+        }
+    }
+    Ok(rules)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rule(per_second: Option<u32>, per_minute: Option<u32>) -> RateLimitRule {
+        RateLimitRule {
+            per_second,
+            per_minute,
+            ..Default::default()
+        }
+    }
+
+    fn limiter(
+        channels: &[(&str, RateLimitRule)],
+        recipients: &[(&str, RateLimitRule)],
+    ) -> RateLimiter {
+        let to_map = |rules: &[(&str, RateLimitRule)]| {
+            rules
+                .iter()
+                .map(|(key, rule)| (key.to_string(), rule.clone()))
+                .collect()
+        };
+        RateLimiter::new(&RateLimitConfig {
+            channels: to_map(channels),
+            recipients: to_map(recipients),
+            ..Default::default()
+        })
+        .unwrap()
+    }
+
+    fn to(recipients: &[&str]) -> Vec<String> {
+        recipients.iter().map(|r| r.to_string()).collect()
+    }
+
+    #[test]
+    fn bucket_refills_at_window_rate() {
+        let start = Instant::now();
+        let mut bucket = Bucket::new(2, 1, start);
+        bucket.tokens = 0.0;
+        assert_eq!(bucket.wait(), Duration::from_millis(500));
+
+        bucket.refill(start + Duration::from_millis(250));
+        assert!((bucket.tokens - 0.5).abs() < 1e-9);
+        assert_eq!(bucket.wait(), Duration::from_millis(250));
+
+        bucket.refill(start + Duration::from_secs(10));
+        assert_eq!(bucket.tokens, 2.0);
+        assert!(bucket.is_full());
+        assert_eq!(bucket.wait(), Duration::ZERO);
+    }
+
+    #[test]
+    fn longest_window_decides_wait() {
+        let now = Instant::now();
+        let mut buckets = buckets(&rule(Some(10), Some(2)), now);
+        assert_eq!(shortfall(&mut buckets, now), None);
+        take(&mut buckets);
+        take(&mut buckets);
+
+        // 每秒窗口仍有令牌，每分钟窗口需等待 30 秒补充一个令牌
+        let wait = shortfall(&mut buckets, now).unwrap();
+        assert!(wait > Duration::from_secs(29) && wait <= Duration::from_secs(30));
+    }
+
+    #[test]
+    fn rejects_unknown_channel() {
+        let config = RateLimitConfig {
+            channels: HashMap::from([("fax".to_string(), rule(Some(1), None))]),
+            ..Default::default()
+        };
+        assert!(matches!(
+            RateLimiter::new(&config),
+            Err(NotifyError::Config(_))
+        ));
+    }
+
+    #[test]
+    fn disabled_without_windows() {
+        let limiter = limiter(&[("sms", RateLimitRule::default())], &[]);
+        assert!(!limiter.is_enabled());
+        for _ in 0..100 {
+            limiter
+                .acquire(ChannelType::Sms, "default", &to(&["a"]))
+                .unwrap();
+        }
+    }
+
+    #[test]
+    fn counts_channel_instances_separately() {
+        let limiter = limiter(&[("im_dingding", rule(None, Some(1)))], &[]);
+        limiter
+            .acquire(ChannelType::ImDingding, "ops", &[])
+            .unwrap();
+        assert!(matches!(
+            limiter.acquire(ChannelType::ImDingding, "ops", &[]),
+            Err(NotifyError::ChannelRateLimited { .. })
+        ));
+        limiter
+            .acquire(ChannelType::ImDingding, "dev", &[])
+            .unwrap();
+    }
+
+    #[test]
+    fn deducts_nothing_when_any_bucket_is_empty() {
+        let limiter = limiter(
+            &[("sms", rule(None, Some(3)))],
+            &[("sms", rule(None, Some(1)))],
+        );
+        limiter
+            .acquire(ChannelType::Sms, "default", &to(&["a"]))
+            .unwrap();
+
+        // a 已无令牌：整次发送被拒绝，b 和渠道实例的令牌都不扣除
+        match limiter.acquire(ChannelType::Sms, "default", &to(&["b", "a"])) {
+            Err(NotifyError::RecipientRateLimited {
+                recipient,
+                retry_after_ms,
+            }) => {
+                assert_eq!(recipient, "a");
+                assert!(retry_after_ms > 0);
+            }
+            other => panic!("unexpected result: {:?}", other),
+        }
+        limiter
+            .acquire(ChannelType::Sms, "default", &to(&["b"]))
+            .unwrap();
+        limiter
+            .acquire(ChannelType::Sms, "default", &to(&["c"]))
+            .unwrap();
+
+        // 渠道实例只扣除了成功的 3 次
+        assert!(matches!(
+            limiter.acquire(ChannelType::Sms, "default", &to(&["d"])),
+            Err(NotifyError::ChannelRateLimited { .. })
+        ));
+    }
+}
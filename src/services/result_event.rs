@@ -0,0 +1,122 @@
+use crate::error::NotifyResult;
+use crate::models::{Notification, RecipientResult};
+use fbc_starter::{Message, MessageProducerType};
+use serde_json::json;
+use std::time::Duration;
+
+/// 发布到 Kafka 时使用的消息来源
+const SOURCE: &str = "ms-notify";
+/// 事件类型
+const EVENT_TYPE: &str = "notification.result";
+
+/// 一次发送尝试的结果
+pub struct SendAttempt<'a> {
+    /// 发送的通知（模板已渲染）
+    pub notification: &'a Notification,
+    /// 通知 ID（未启用发送记录时为 None）
+    pub notification_id: Option<i64>,
+    /// 第几次尝试（从 1 开始）
+    pub attempt: u32,
+    /// 是否为最后一次尝试（发送成功、不可重试或重试次数耗尽）
+    pub last: bool,
+    /// 本次尝试的结果
+    pub outcome: &'a NotifyResult<Vec<RecipientResult>>,
+    /// 本次尝试的耗时
+    pub latency: Duration,
+}
+
+/// 发送结果事件服务
+///
+/// 每次发送尝试后按接收者发布一条 `notification.result` 事件，
+/// 上游服务据此得知验证码、邮件等是否送达。
+/// 未配置 Kafka 生产者或 topic 为空时不发布
+pub struct ResultEventService {
+    producer: Option<MessageProducerType>,
+    topic: String,
+}
+
+impl ResultEventService {
+    /// 创建发送结果事件服务
+    ///
+    /// # 参数
+    /// - `producer`: Kafka 生产者（可选）
+    /// - `topic`: 结果事件 topic
+    pub fn new(producer: Option<MessageProducerType>, topic: impl Into<String>) -> Self {
+        Self {
+            producer,
+            topic: topic.into(),
+        }
+    }
+
+    /// 发布一次发送尝试的结果事件
+    ///
+    /// 在后台任务中发布，不阻塞发送流程；发布失败只记日志
+    pub fn publish(&self, attempt: SendAttempt<'_>) {
+        let Some(producer) = self.producer.clone() else {
+            return;
+        };
+        if self.topic.is_empty() {
+            return;
+        }
+
+        let events = events(&attempt);
+        let topic = self.topic.clone();
+        tokio::spawn(async move {
+            for event in events {
+                if let Err(e) = producer
+                    .publish(&topic, Message::new(&topic, SOURCE, event))
+                    .await
+                {
+                    tracing::warn!(topic = %topic, "发送结果事件发布失败: {}", e);
+                }
+            }
+        });
+    }
+}
+
+/// 按接收者生成结果事件，整体失败时每个接收者各生成一条失败事件
+fn events(attempt: &SendAttempt<'_>) -> Vec<serde_json::Value> {
+    let notification = attempt.notification;
+    let event = |recipient: &str,
+                 success: bool,
+                 provider_message_id: Option<&str>,
+                 error_code: Option<i32>,
+                 error: Option<&str>| {
+        json!({
+            "event": EVENT_TYPE,
+            "notification_id": attempt.notification_id,
+            "correlation_id": notification.correlation_id,
+            "tenant": notification.tenant,
+            "channel": notification.channel,
+            "recipient": recipient,
+            "status": if success { "sent" } else { "failed" },
+            "provider_message_id": provider_message_id,
+            "error_code": error_code,
+            "error": error,
+            "attempt": attempt.attempt,
+            "final": attempt.last,
+            "latency_ms": attempt.latency.as_millis() as u64,
+            "timestamp": chrono::Utc::now().timestamp_millis(),
+        })
+    };
+
+    match attempt.outcome {
+        Ok(results) => results
+            .iter()
+            .map(|r| {
+                event(
+                    &r.recipient,
+                    r.success,
+                    r.provider_message_id.as_deref(),
+                    r.error_code,
+                    r.error.as_deref(),
+                )
+            })
+            .collect(),
+        Err(e) => notification
+            .all_recipients()
+            .iter()
+            .map(|r| event(r, false, None, Some(e.code()), Some(&e.to_string())))
+            .collect(),
+    }
+}
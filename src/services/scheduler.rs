@@ -0,0 +1,127 @@
+use crate::config::SchedulerConfig;
+use crate::kafka::RetryPolicy;
+use crate::services::{FallbackOrchestrator, NotificationRecordService};
+use crate::shutdown::{InFlight, ShutdownCoordinator};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::Notify;
+use tokio::task::JoinSet;
+
+/// 定时发送调度器
+///
+/// 定时通知持久化在发送记录表中（状态 scheduled），服务重启后继续调度；
+/// 按固定间隔取出到期的通知，通过降级编排并发发送，本批发送完成后再取下一批；
+/// 异步发送的通知（状态 pending）同样由调度器发送，写入后立即唤醒调度器；
+/// 关闭开始后不再取出新的到期通知
+pub struct NotificationScheduler {
+    orchestrator: Arc<FallbackOrchestrator>,
+    records: Arc<NotificationRecordService>,
+    shutdown: Arc<ShutdownCoordinator>,
+    wakeup: Arc<Notify>,
+    retry: RetryPolicy,
+    poll_interval: Duration,
+    batch_size: u64,
+    claim_lease: Duration,
+}
+
+impl NotificationScheduler {
+    /// 创建定时发送调度器
+    ///
+    /// # 参数
+    /// - `orchestrator`: 降级编排
+    /// - `records`: 发送记录服务
+    /// - `shutdown`: 优雅关闭协调器
+    /// - `wakeup`: 写入异步发送的通知后唤醒调度器，无需等待下一次轮询
+    /// - `retry`: 到期发送时使用的重试策略
+    /// - `config`: 调度配置
+    pub fn new(
+        orchestrator: Arc<FallbackOrchestrator>,
+        records: Arc<NotificationRecordService>,
+        shutdown: Arc<ShutdownCoordinator>,
+        wakeup: Arc<Notify>,
+        retry: RetryPolicy,
+        config: &SchedulerConfig,
+    ) -> Self {
+        Self {
+            orchestrator,
+            records,
+            shutdown,
+            wakeup,
+            retry,
+            poll_interval: Duration::from_millis(config.poll_interval_ms.max(100)),
+            batch_size: config.batch_size.max(1),
+            claim_lease: Duration::from_secs(config.claim_lease_secs.max(1)),
+        }
+    }
+
+    /// 在后台任务中运行调度循环
+    pub fn spawn(self) -> tokio::task::JoinHandle<()> {
+        tokio::spawn(async move {
+            tracing::info!(
+                "定时发送调度器已启动: poll_interval={:?}, batch_size={}",
+                self.poll_interval,
+                self.batch_size
+            );
+            let mut interval = tokio::time::interval(self.poll_interval);
+            interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+            loop {
+                tokio::select! {
+                    _ = interval.tick() => {}
+                    _ = self.wakeup.notified() => {}
+                    _ = self.shutdown.stopped() => break,
+                }
+                // 一批取满说明可能还有到期通知，立即取下一批
+                while !self.shutdown.is_stopping() && self.fire_due().await {}
+            }
+            tracing::info!("定时发送调度器已停止");
+        })
+    }
+
+    /// 发送一批到期通知，返回是否取满一批
+    async fn fire_due(&self) -> bool {
+        let due = match self
+            .records
+            .claim_due(self.batch_size, self.claim_lease)
+            .await
+        {
+            Ok(due) => due,
+            Err(e) => {
+                tracing::error!("取出到期定时通知失败: {}", e);
+                return false;
+            }
+        };
+        let full = due.len() as u64 >= self.batch_size;
+
+        let mut tasks = JoinSet::new();
+        for (id, notification) in due {
+            let orchestrator = self.orchestrator.clone();
+            let retry = self.retry.clone();
+            let guard = self.shutdown.track(InFlight {
+                notification: notification.clone(),
+                record_id: Some(id),
+                message: None,
+            });
+            tasks.spawn(async move {
+                guard
+                    .run(async {
+                        match orchestrator.send_scheduled(id, &notification, &retry).await {
+                            Ok(_) => tracing::info!(
+                                "Scheduled notification sent: id={}, channel={:?}",
+                                id,
+                                notification.channel
+                            ),
+                            Err(failure) => tracing::error!(
+                        "Scheduled notification failed after {} attempt(s): id={}, error={}",
+                        failure.attempts,
+                        id,
+                        failure.error
+                    ),
+                        }
+                    })
+                    .await;
+            });
+        }
+        while tasks.join_next().await.is_some() {}
+        full
+    }
+}
@@ -0,0 +1,179 @@
+use crate::error::{NotifyError, NotifyResult};
+use crate::models::{Notification, SiteMessage};
+use fbc_starter::CursorPageBaseResp;
+use sqlxplus::{Crud, DbPool, QueryBuilder, UpdateBuilder};
+use std::sync::Arc;
+
+/// 单页最大条数
+const MAX_PAGE_SIZE: u32 = 100;
+/// 默认每页条数
+const DEFAULT_PAGE_SIZE: u32 = 10;
+
+/// 站内消息服务
+pub struct SiteMessageService {
+    db_pool: Arc<DbPool>,
+}
+
+impl SiteMessageService {
+    /// 创建站内消息服务
+    ///
+    /// # 参数
+    /// - `db_pool`: 数据库连接池
+    pub fn new(db_pool: Arc<DbPool>) -> Self {
+        Self { db_pool }
+    }
+
+    /// 写入一条站内消息，返回消息 ID
+    ///
+    /// `notification.to` 的首个接收者为接收用户 ID
+    pub async fn create(&self, notification: &Notification) -> NotifyResult<i64> {
+        let recipient = notification.recipient();
+        let user_id = recipient.parse::<i64>().map_err(|_| {
+            NotifyError::InvalidMessage(format!("站内消息接收者必须为用户 ID: {}", recipient))
+        })?;
+
+        let now = chrono::Utc::now().timestamp_millis();
+        let message = SiteMessage {
+            user_id: Some(user_id),
+            sender: Some(notification.from.clone()),
+            title: Some(notification.subject.clone()),
+            content: Some(notification.body.clone()),
+            is_read: Some(0),
+            created_at: Some(now),
+            updated_at: Some(now),
+            is_del: Some(0),
+            ..Default::default()
+        };
+
+        let id = message.insert(self.db_pool.mysql_pool()).await?;
+        Ok(id)
+    }
+
+    /// 按游标分页查询用户收件箱（按 ID 倒序）
+    ///
+    /// # 参数
+    /// - `user_id`: 用户 ID
+    /// - `cursor`: 上一页返回的游标（首页为 None）
+    /// - `page_size`: 每页条数
+    /// - `is_read`: 已读状态过滤（None 表示全部）
+    pub async fn list(
+        &self,
+        user_id: i64,
+        cursor: Option<u32>,
+        page_size: Option<u32>,
+        is_read: Option<i32>,
+    ) -> NotifyResult<CursorPageBaseResp<SiteMessage>> {
+        let pool = self.db_pool.mysql_pool();
+        let page_size = page_size
+            .unwrap_or(DEFAULT_PAGE_SIZE)
+            .clamp(1, MAX_PAGE_SIZE);
+
+        let filter = |builder: QueryBuilder| {
+            let builder = builder.and_eq("user_id", user_id);
+            match is_read {
+                Some(is_read) => builder.and_eq("is_read", is_read),
+                None => builder,
+            }
+        };
+
+        let total = SiteMessage::count(pool, filter(QueryBuilder::new(""))).await?;
+
+        let mut builder = filter(QueryBuilder::new(""));
+        if let Some(cursor) = cursor.filter(|c| *c > 0) {
+            builder = builder.and_lt("id", cursor as i64);
+        }
+        let builder = builder.order_by("id", false);
+
+        // 倒序翻页由 builder 自行追加 id < cursor 条件，因此不向 paginate_cursor 传游标
+        let page = SiteMessage::paginate_cursor(pool, builder, None, page_size).await?;
+        let next_cursor = page
+            .next_cursor
+            .filter(|_| page.has_next)
+            .and_then(|id| u32::try_from(id).ok());
+
+        Ok(CursorPageBaseResp::init(
+            next_cursor,
+            page.has_next,
+            page.items,
+            total as i64,
+        ))
+    }
+
+    /// 统计用户未读消息数
+    pub async fn count_unread(&self, user_id: i64) -> NotifyResult<u64> {
+        let builder = QueryBuilder::new("")
+            .and_eq("user_id", user_id)
+            .and_eq("is_read", 0);
+        let count = SiteMessage::count(self.db_pool.mysql_pool(), builder).await?;
+        Ok(count)
+    }
+
+    /// 指定消息中是否有已读消息（用于未读降级判断）
+    pub async fn any_read(&self, ids: &[i64]) -> NotifyResult<bool> {
+        if ids.is_empty() {
+            return Ok(false);
+        }
+        let builder = QueryBuilder::new("")
+            .and_in("id", ids.to_vec())
+            .and_eq("is_read", 1);
+        let count = SiteMessage::count(self.db_pool.mysql_pool(), builder).await?;
+        Ok(count > 0)
+    }
+
+    /// 将用户的单条消息标记为已读
+    pub async fn mark_read(&self, user_id: i64, id: i64) -> NotifyResult<()> {
+        let pool = self.db_pool.mysql_pool();
+        let message = self.find_owned(user_id, id).await?;
+        if message.is_read == Some(1) {
+            return Ok(());
+        }
+
+        let now = chrono::Utc::now().timestamp_millis();
+        let update = SiteMessage {
+            id: Some(id),
+            is_read: Some(1),
+            read_at: Some(now),
+            updated_at: Some(now),
+            ..Default::default()
+        };
+        update.update(pool).await?;
+        Ok(())
+    }
+
+    /// 将用户的全部未读消息标记为已读，返回更新条数
+    pub async fn mark_all_read(&self, user_id: i64) -> NotifyResult<u64> {
+        let now = chrono::Utc::now().timestamp_millis();
+        let update = SiteMessage {
+            is_read: Some(1),
+            read_at: Some(now),
+            updated_at: Some(now),
+            ..Default::default()
+        };
+
+        let affected = UpdateBuilder::new(update)
+            .fields(&["is_read", "read_at", "updated_at"])
+            .condition(|b| {
+                b.and_eq("user_id", user_id)
+                    .and_eq("is_read", 0)
+                    .and_eq("is_del", 0)
+            })
+            .execute(self.db_pool.mysql_pool())
+            .await?;
+        Ok(affected)
+    }
+
+    /// 逻辑删除用户的单条消息
+    pub async fn delete(&self, user_id: i64, id: i64) -> NotifyResult<()> {
+        self.find_owned(user_id, id).await?;
+        SiteMessage::delete_by_id(self.db_pool.mysql_pool(), id).await?;
+        Ok(())
+    }
+
+    /// 查询属于指定用户的消息，不存在或不属于该用户时返回 SiteMessageNotFound
+    async fn find_owned(&self, user_id: i64, id: i64) -> NotifyResult<SiteMessage> {
+        SiteMessage::find_by_id(self.db_pool.mysql_pool(), id)
+            .await?
+            .filter(|message| message.user_id == Some(user_id))
+            .ok_or(NotifyError::SiteMessageNotFound(id))
+    }
+}
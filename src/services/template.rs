@@ -0,0 +1,271 @@
+use super::idempotency::is_unique_violation;
+use crate::error::{NotifyError, NotifyResult};
+use crate::models::{looks_like_html, ChannelType, NotifyTemplate};
+use fbc_starter::CursorPageBaseResp;
+use minijinja::{Environment, UndefinedBehavior};
+use serde_json::{Map, Value};
+use sqlxplus::{Crud, DbPool, QueryBuilder};
+use std::sync::Arc;
+
+/// 单页最大条数
+const MAX_PAGE_SIZE: u32 = 100;
+/// 默认每页条数
+const DEFAULT_PAGE_SIZE: u32 = 20;
+/// 默认语言（请求语言无对应模板时回退）
+const DEFAULT_LOCALE: &str = "zh-CN";
+/// 单次渲染的指令预算，防止模板中的循环耗尽 CPU
+const RENDER_FUEL: u64 = 50_000;
+
+/// 模板渲染结果
+#[derive(Debug, Clone)]
+pub struct RenderedTemplate {
+    /// 渲染后的主题
+    pub subject: String,
+    /// 渲染后的正文
+    pub body: String,
+}
+
+/// 通知模板服务
+///
+/// 模板使用 MiniJinja 语法（`{{ name }}`、`{% if %}`、`{% for %}`），渲染环境不具备文件、
+/// 网络等访问能力；未定义的变量直接报错，避免发出带空占位符的消息。
+/// HTML 邮件正文中的变量自动转义（可信的 HTML 片段使用 `{{ value|safe }}`）；
+/// JSON 正文（飞书卡片、短信参数等）中的变量应使用 `{{ value|tojson }}` 输出。
+pub struct TemplateService {
+    db_pool: Arc<DbPool>,
+    env: Environment<'static>,
+}
+
+impl TemplateService {
+    /// 创建模板服务
+    ///
+    /// # 参数
+    /// - `db_pool`: 数据库连接池
+    pub fn new(db_pool: Arc<DbPool>) -> Self {
+        let mut env = Environment::new();
+        env.set_undefined_behavior(UndefinedBehavior::Strict);
+        env.set_fuel(Some(RENDER_FUEL));
+        Self { db_pool, env }
+    }
+
+    /// 新建模板，返回模板 ID
+    pub async fn create(&self, template: NotifyTemplate) -> NotifyResult<i64> {
+        let code = required(&template.code, "code")?;
+        let channel = required(&template.channel, "channel")?;
+        let locale = template
+            .locale
+            .clone()
+            .filter(|l| !l.trim().is_empty())
+            .unwrap_or_else(|| DEFAULT_LOCALE.to_string());
+        self.validate(&template)?;
+
+        let key = format!("{}/{}/{}", code, channel, locale);
+        if self.find(code, channel, &locale).await?.is_some() {
+            return Err(NotifyError::TemplateExists(key));
+        }
+
+        let now = chrono::Utc::now().timestamp_millis();
+        let template = NotifyTemplate {
+            id: None,
+            locale: Some(locale),
+            subject_template: Some(template.subject_template.unwrap_or_default()),
+            remark: Some(template.remark.unwrap_or_default()),
+            version: Some(1),
+            created_at: Some(now),
+            updated_at: Some(now),
+            is_del: Some(0),
+            ..template
+        };
+        // 并发创建时由唯一索引保证只有一个成功
+        match template.insert(self.db_pool.mysql_pool()).await {
+            Ok(id) => Ok(id),
+            Err(e) if is_unique_violation(&e) => Err(NotifyError::TemplateExists(key)),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// 修改模板内容（主题、正文、备注），版本号加 1
+    ///
+    /// code、channel、locale 作为模板的查找键，不允许修改
+    pub async fn update(&self, id: i64, template: NotifyTemplate) -> NotifyResult<()> {
+        let existing = self.get(id).await?;
+        let merged = NotifyTemplate {
+            subject_template: template.subject_template.or(existing.subject_template),
+            body_template: template.body_template.or(existing.body_template),
+            remark: template.remark.or(existing.remark),
+            ..existing
+        };
+        self.validate(&merged)?;
+
+        let update = NotifyTemplate {
+            id: Some(id),
+            subject_template: merged.subject_template,
+            body_template: merged.body_template,
+            remark: merged.remark,
+            version: Some(merged.version.unwrap_or(1) + 1),
+            updated_at: Some(chrono::Utc::now().timestamp_millis()),
+            ..Default::default()
+        };
+        update.update(self.db_pool.mysql_pool()).await?;
+        Ok(())
+    }
+
+    /// 按 ID 查询模板
+    pub async fn get(&self, id: i64) -> NotifyResult<NotifyTemplate> {
+        NotifyTemplate::find_by_id(self.db_pool.mysql_pool(), id)
+            .await?
+            .ok_or_else(|| NotifyError::TemplateNotFound(id.to_string()))
+    }
+
+    /// 按游标分页查询模板（按 ID 正序）
+    ///
+    /// # 参数
+    /// - `cursor`: 上一页返回的游标（首页为 None）
+    /// - `page_size`: 每页条数
+    /// - `code`: 模板代码过滤
+    /// - `channel`: 渠道过滤
+    pub async fn list(
+        &self,
+        cursor: Option<u32>,
+        page_size: Option<u32>,
+        code: Option<String>,
+        channel: Option<ChannelType>,
+    ) -> NotifyResult<CursorPageBaseResp<NotifyTemplate>> {
+        let pool = self.db_pool.mysql_pool();
+        let page_size = page_size
+            .unwrap_or(DEFAULT_PAGE_SIZE)
+            .clamp(1, MAX_PAGE_SIZE);
+
+        let filter = |builder: QueryBuilder| {
+            let builder = match &code {
+                Some(code) => builder.and_eq("code", code.as_str()),
+                None => builder,
+            };
+            match channel {
+                Some(channel) => builder.and_eq("channel", channel.as_str()),
+                None => builder,
+            }
+        };
+
+        let total = NotifyTemplate::count(pool, filter(QueryBuilder::new(""))).await?;
+        let cursor = cursor.filter(|c| *c > 0).map(i64::from);
+        let page =
+            NotifyTemplate::paginate_cursor(pool, filter(QueryBuilder::new("")), cursor, page_size)
+                .await?;
+        let next_cursor = page
+            .next_cursor
+            .filter(|_| page.has_next)
+            .and_then(|id| u32::try_from(id).ok());
+
+        Ok(CursorPageBaseResp::init(
+            next_cursor,
+            page.has_next,
+            page.items,
+            total as i64,
+        ))
+    }
+
+    /// 逻辑删除模板
+    pub async fn delete(&self, id: i64) -> NotifyResult<()> {
+        self.get(id).await?;
+        NotifyTemplate::delete_by_id(self.db_pool.mysql_pool(), id).await?;
+        Ok(())
+    }
+
+    /// 查找并渲染模板
+    ///
+    /// 优先使用请求语言的模板，不存在时回退到默认语言 [`DEFAULT_LOCALE`]
+    pub async fn render(
+        &self,
+        code: &str,
+        channel: ChannelType,
+        locale: Option<&str>,
+        variables: &Map<String, Value>,
+    ) -> NotifyResult<RenderedTemplate> {
+        let locale = locale
+            .filter(|l| !l.trim().is_empty())
+            .unwrap_or(DEFAULT_LOCALE);
+        let mut template = self.find(code, channel.as_str(), locale).await?;
+        if template.is_none() && locale != DEFAULT_LOCALE {
+            template = self.find(code, channel.as_str(), DEFAULT_LOCALE).await?;
+        }
+        let template = template.ok_or_else(|| {
+            NotifyError::TemplateNotFound(format!("{}/{}/{}", code, channel.as_str(), locale))
+        })?;
+
+        let body = template.body_template.as_deref();
+        let html = channel == ChannelType::Email && body.is_some_and(looks_like_html);
+        Ok(RenderedTemplate {
+            subject: self.render_str(
+                code,
+                template.subject_template.as_deref(),
+                variables,
+                false,
+            )?,
+            body: self.render_str(code, body, variables, html)?,
+        })
+    }
+
+    /// 渲染单个模板字符串
+    ///
+    /// `html` 为 true 时按 HTML 自动转义变量（模板名以 `.html` 结尾时启用）
+    fn render_str(
+        &self,
+        code: &str,
+        source: Option<&str>,
+        variables: &Map<String, Value>,
+        html: bool,
+    ) -> NotifyResult<String> {
+        let rendered = match source {
+            Some(source) if !source.is_empty() && html => {
+                self.env
+                    .render_named_str(&format!("{}.html", code), source, variables)
+            }
+            Some(source) if !source.is_empty() => self.env.render_str(source, variables),
+            _ => return Ok(String::new()),
+        };
+        rendered.map_err(|e| NotifyError::TemplateInvalid(format!("{}: {}", code, e)))
+    }
+
+    /// 校验模板语法
+    fn validate(&self, template: &NotifyTemplate) -> NotifyResult<()> {
+        let channel = required(&template.channel, "channel")?;
+        serde_json::from_value::<ChannelType>(Value::String(channel.to_string()))
+            .map_err(|_| NotifyError::TemplateInvalid(format!("不支持的渠道: {}", channel)))?;
+        required(&template.body_template, "body_template")?;
+
+        for source in [&template.subject_template, &template.body_template]
+            .into_iter()
+            .flatten()
+        {
+            self.env
+                .template_from_str(source)
+                .map_err(|e| NotifyError::TemplateInvalid(format!("模板语法错误: {}", e)))?;
+        }
+        Ok(())
+    }
+
+    /// 按 code + channel + locale 查找模板
+    async fn find(
+        &self,
+        code: &str,
+        channel: &str,
+        locale: &str,
+    ) -> NotifyResult<Option<NotifyTemplate>> {
+        let builder = QueryBuilder::new("")
+            .and_eq("code", code)
+            .and_eq("channel", channel)
+            .and_eq("locale", locale);
+        let template = NotifyTemplate::find_one(self.db_pool.mysql_pool(), builder).await?;
+        Ok(template)
+    }
+}
+
+/// 读取必填字段，缺失或为空时返回 TemplateInvalid
+fn required<'a>(value: &'a Option<String>, field: &str) -> NotifyResult<&'a str> {
+    value
+        .as_deref()
+        .map(str::trim)
+        .filter(|v| !v.is_empty())
+        .ok_or_else(|| NotifyError::TemplateInvalid(format!("缺少必填字段: {}", field)))
+}
@@ -0,0 +1,460 @@
+use crate::config::ShutdownConfig;
+use crate::models::{Notification, NotificationStatus};
+use crate::services::NotificationRecordService;
+use fbc_starter::{Message, MessageProducerType};
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::Duration;
+use tokio::signal;
+use tokio::sync::{watch, Notify};
+use tokio::time::Instant;
+use tracing::{error, info, warn};
+
+/// 截止时间到达后取消未完成的任务，等待其退出的最长时间
+const ABORT_GRACE: Duration = Duration::from_secs(2);
+
+tokio::task_local! {
+    /// 当前登记的发送任务写入的首条发送记录 ID
+    static CURRENT_RECORD: Arc<OnceLock<i64>>;
+}
+
+/// 回填当前登记的发送任务的发送记录 ID（只保留首条，即原始通知的记录），
+/// 保存未完成的任务时改为定时发送该记录，而不是写入新记录
+pub fn report_record(id: i64) {
+    let _ = CURRENT_RECORD.try_with(|record| record.set(id));
+}
+
+/// 进行中的发送任务
+pub struct InFlight {
+    /// 通知内容
+    pub notification: Notification,
+    /// 已有的发送记录 ID（定时通知到期发送时）
+    pub record_id: Option<i64>,
+    /// 原始 Kafka 消息（Kafka 消费链路）
+    pub message: Option<Message>,
+}
+
+/// 登记中的任务
+struct Entry {
+    work: InFlight,
+    /// 发送过程中写入的发送记录 ID
+    record: Arc<OnceLock<i64>>,
+    /// 截止时间到达时通知任务退出
+    cancel: Arc<Notify>,
+}
+
+impl Entry {
+    fn into_work(self) -> InFlight {
+        InFlight {
+            record_id: self.work.record_id.or(self.record.get().copied()),
+            ..self.work
+        }
+    }
+}
+
+/// 优雅关闭协调器
+///
+/// 收到 SIGTERM（或 Ctrl+C）后进入关闭状态：HTTP 发送请求直接拒绝，Kafka 新消息和排队中的任务
+/// 不再发送而是保存待恢复；`Server::run` 返回后等待进行中的发送完成，超过截止时间仍未完成的
+/// 先取消再保存。任务在提交到工作池前登记，排队中的任务同样会被等待或保存。
+/// 保存优先将已有的发送记录改为定时发送（立即到期，重启后由调度器发送），尚无记录时写入定时记录，
+/// 未配置数据库时重新发布到原 topic
+pub struct ShutdownCoordinator {
+    stopping: watch::Sender<Option<Instant>>,
+    in_flight: Mutex<HashMap<u64, Entry>>,
+    next_id: AtomicU64,
+    /// 未结束的登记数（含已被取走、任务尚未退出的登记）
+    active: AtomicUsize,
+    idle: Notify,
+    records: Option<Arc<NotificationRecordService>>,
+    producer: Option<MessageProducerType>,
+    drain_timeout: Duration,
+}
+
+/// 发送任务的登记，随任务一起移动，drop 时注销
+pub struct InFlightGuard {
+    coordinator: Arc<ShutdownCoordinator>,
+    id: u64,
+    record: Arc<OnceLock<i64>>,
+    cancel: Arc<Notify>,
+}
+
+impl InFlightGuard {
+    /// 是否已进入关闭状态
+    pub fn is_stopping(&self) -> bool {
+        self.coordinator.is_stopping()
+    }
+
+    /// 执行发送任务，关闭截止时间到达时取消
+    pub async fn run(&self, work: impl Future<Output = ()>) {
+        tokio::select! {
+            _ = CURRENT_RECORD.scope(self.record.clone(), work) => {}
+            _ = self.cancel.notified() => {
+                warn!("In-flight notification cancelled at shutdown deadline");
+            }
+        }
+    }
+
+    /// 不再发送，保存任务待恢复；已被关闭流程取走时由关闭流程保存，返回 false
+    pub async fn persist(self) -> bool {
+        let entry = self.coordinator.take(self.id);
+        match entry {
+            Some(entry) => {
+                self.coordinator.persist(entry.into_work()).await;
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+impl Drop for InFlightGuard {
+    fn drop(&mut self) {
+        self.coordinator.take(self.id);
+        if self.coordinator.active.fetch_sub(1, Ordering::AcqRel) == 1 {
+            self.coordinator.idle.notify_waiters();
+        }
+    }
+}
+
+impl ShutdownCoordinator {
+    /// 创建优雅关闭协调器
+    ///
+    /// # 参数
+    /// - `records`: 发送记录服务（可选，保存未完成的通知）
+    /// - `producer`: Kafka 生产者（可选，未配置数据库时重新发布未完成的消息）
+    /// - `config`: 优雅关闭配置
+    pub fn new(
+        records: Option<Arc<NotificationRecordService>>,
+        producer: Option<MessageProducerType>,
+        config: &ShutdownConfig,
+    ) -> Self {
+        Self {
+            stopping: watch::Sender::new(None),
+            in_flight: Mutex::new(HashMap::new()),
+            next_id: AtomicU64::new(0),
+            active: AtomicUsize::new(0),
+            idle: Notify::new(),
+            records,
+            producer,
+            drain_timeout: Duration::from_millis(config.drain_timeout_ms),
+        }
+    }
+
+    /// 在后台监听关闭信号（与框架的 HTTP 优雅关闭同时触发）
+    pub fn listen(self: &Arc<Self>) {
+        let coordinator = self.clone();
+        tokio::spawn(async move {
+            let ctrl_c = async {
+                let _ = signal::ctrl_c().await;
+            };
+            #[cfg(unix)]
+            let terminate = async {
+                match signal::unix::signal(signal::unix::SignalKind::terminate()) {
+                    Ok(mut sigterm) => {
+                        sigterm.recv().await;
+                    }
+                    Err(e) => {
+                        error!("无法监听 SIGTERM 信号: {}", e);
+                        std::future::pending::<()>().await;
+                    }
+                }
+            };
+            #[cfg(not(unix))]
+            let terminate = std::future::pending::<()>();
+
+            tokio::select! {
+                _ = ctrl_c => {},
+                _ = terminate => {},
+            }
+            coordinator.begin();
+        });
+    }
+
+    /// 进入关闭状态（重复调用无效）
+    pub fn begin(&self) {
+        self.stopping.send_if_modified(|stopping| {
+            if stopping.is_some() {
+                return false;
+            }
+            info!(
+                "Shutdown started, draining in-flight notifications (deadline {:?})",
+                self.drain_timeout
+            );
+            *stopping = Some(Instant::now());
+            true
+        });
+    }
+
+    /// 是否已进入关闭状态
+    pub fn is_stopping(&self) -> bool {
+        self.stopping.borrow().is_some()
+    }
+
+    /// 等待进入关闭状态
+    pub async fn stopped(&self) {
+        let mut receiver = self.stopping.subscribe();
+        let _ = receiver.wait_for(Option::is_some).await;
+    }
+
+    /// 登记发送任务（提交到工作池前调用），返回的 guard 随任务移动，任务结束时 drop
+    pub fn track(self: &Arc<Self>, work: InFlight) -> InFlightGuard {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let record = Arc::new(OnceLock::new());
+        let cancel = Arc::new(Notify::new());
+        self.active.fetch_add(1, Ordering::AcqRel);
+        self.in_flight
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .insert(
+                id,
+                Entry {
+                    work,
+                    record: record.clone(),
+                    cancel: cancel.clone(),
+                },
+            );
+        InFlightGuard {
+            coordinator: self.clone(),
+            id,
+            record,
+            cancel,
+        }
+    }
+
+    /// 取走登记，取走者负责保存
+    fn take(&self, id: u64) -> Option<Entry> {
+        self.in_flight
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .remove(&id)
+    }
+
+    /// 等待全部登记结束，到达截止时间时返回 false
+    async fn wait_idle(&self, deadline: Instant) -> bool {
+        loop {
+            let idle = self.idle.notified();
+            tokio::pin!(idle);
+            idle.as_mut().enable();
+            if self.active.load(Ordering::Acquire) == 0 {
+                return true;
+            }
+            if tokio::time::timeout_at(deadline, idle).await.is_err() {
+                return false;
+            }
+        }
+    }
+
+    /// 等待进行中和排队中的发送完成（自收到关闭信号起计算截止时间），
+    /// 超时仍未完成的任务先取消再保存待恢复
+    pub async fn drain(&self) {
+        for work in self.cancel_unfinished().await {
+            self.persist(work).await;
+        }
+    }
+
+    /// 等待登记的任务完成，截止时间到达后取消仍未完成的任务，返回待保存的任务
+    async fn cancel_unfinished(&self) -> Vec<InFlight> {
+        self.begin();
+        let started = (*self.stopping.borrow()).unwrap_or_else(Instant::now);
+        if self.wait_idle(started + self.drain_timeout).await {
+            info!("All in-flight notifications finished");
+            return Vec::new();
+        }
+
+        let unfinished: Vec<Entry> = self
+            .in_flight
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .drain()
+            .map(|(_, entry)| entry)
+            .collect();
+        warn!(
+            "Shutdown deadline reached, cancelling and persisting {} unfinished notification(s)",
+            unfinished.len()
+        );
+        for entry in &unfinished {
+            entry.cancel.notify_one();
+        }
+        // 等待被取消的任务退出，避免保存后原任务仍在发送
+        if !self.wait_idle(Instant::now() + ABORT_GRACE).await {
+            warn!(
+                "Some cancelled notifications did not exit within {:?}",
+                ABORT_GRACE
+            );
+        }
+        unfinished.into_iter().map(Entry::into_work).collect()
+    }
+
+    /// 保存未发送的通知，重启后继续发送
+    async fn persist(&self, work: InFlight) {
+        let now = chrono::Utc::now().timestamp_millis();
+        if let Some(records) = &self.records {
+            let saved = match work.record_id {
+                Some(id) => records.reschedule(id, now).await.map(|_| id),
+                None => {
+                    let notification = Notification {
+                        send_at: Some(work.notification.send_at.unwrap_or(now).max(now)),
+                        ..work.notification.clone()
+                    };
+                    records
+                        .create(&notification, NotificationStatus::Scheduled)
+                        .await
+                }
+            };
+            match saved {
+                Ok(id) => {
+                    info!(
+                        "Unfinished notification persisted for resumption: id={}",
+                        id
+                    );
+                    return;
+                }
+                Err(e) => warn!("Failed to persist unfinished notification: {}", e),
+            }
+        }
+
+        if let (Some(producer), Some(message)) = (&self.producer, &work.message) {
+            match producer.publish(&message.topic, message.clone()).await {
+                Ok(()) => {
+                    info!(
+                        "Unfinished notification republished: topic={}",
+                        message.topic
+                    );
+                    return;
+                }
+                Err(e) => warn!("Failed to republish unfinished notification: {}", e),
+            }
+        }
+
+        error!(
+            "未完成的通知无处保存，已丢弃: channel={:?}, record_id={:?}, notification={}",
+            work.notification.channel,
+            work.record_id,
+            serde_json::to_string(&work.notification).unwrap_or_default()
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::ChannelType;
+
+    fn coordinator(drain_timeout_ms: u64) -> Arc<ShutdownCoordinator> {
+        Arc::new(ShutdownCoordinator::new(
+            None,
+            None,
+            &ShutdownConfig { drain_timeout_ms },
+        ))
+    }
+
+    fn work(record_id: Option<i64>) -> InFlight {
+        InFlight {
+            notification: Notification::new(ChannelType::Email),
+            record_id,
+            message: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn drain_waits_for_running_tasks() {
+        let coordinator = coordinator(5_000);
+        let guard = coordinator.track(work(None));
+        let task = tokio::spawn(async move {
+            guard
+                .run(tokio::time::sleep(Duration::from_millis(20)))
+                .await;
+        });
+
+        assert!(coordinator.cancel_unfinished().await.is_empty());
+        assert!(task.is_finished());
+        assert!(coordinator.is_stopping());
+        assert_eq!(coordinator.active.load(Ordering::Acquire), 0);
+    }
+
+    #[tokio::test]
+    async fn deadline_cancels_and_collects_unfinished_tasks() {
+        let coordinator = coordinator(50);
+
+        // 尚无发送记录，运行中写入记录 42
+        let guard = coordinator.track(work(None));
+        let reported = tokio::spawn(async move {
+            guard
+                .run(async {
+                    report_record(42);
+                    report_record(43);
+                    std::future::pending::<()>().await
+                })
+                .await;
+        });
+        // 定时通知到期发送，已有记录 7
+        let guard = coordinator.track(work(Some(7)));
+        let scheduled = tokio::spawn(async move {
+            guard.run(std::future::pending()).await;
+        });
+        // 未写入记录
+        let guard = coordinator.track(work(None));
+        let unrecorded = tokio::spawn(async move {
+            guard.run(std::future::pending()).await;
+        });
+
+        let mut record_ids: Vec<_> = coordinator
+            .cancel_unfinished()
+            .await
+            .into_iter()
+            .map(|work| work.record_id)
+            .collect();
+        record_ids.sort();
+        assert_eq!(record_ids, vec![None, Some(7), Some(42)]);
+
+        // 保存前被取消的任务均已退出
+        for task in [reported, scheduled, unrecorded] {
+            assert!(task.is_finished());
+        }
+        assert!(coordinator.in_flight.lock().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn queued_task_does_not_persist_after_drain_took_it() {
+        let coordinator = coordinator(50);
+        let guard = coordinator.track(work(None));
+        // 排队中的任务在关闭流程取走登记后才开始执行
+        let queued = {
+            let coordinator = coordinator.clone();
+            tokio::spawn(async move {
+                while !coordinator.in_flight.lock().unwrap().is_empty() {
+                    tokio::time::sleep(Duration::from_millis(5)).await;
+                }
+                assert!(guard.is_stopping());
+                guard.persist().await
+            })
+        };
+
+        assert_eq!(coordinator.cancel_unfinished().await.len(), 1);
+        assert!(!queued.await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn queued_task_persists_itself_when_stopping() {
+        let coordinator = coordinator(5_000);
+        coordinator.begin();
+        let guard = coordinator.track(work(None));
+        assert!(guard.is_stopping());
+        assert!(guard.persist().await);
+        assert!(coordinator.in_flight.lock().unwrap().is_empty());
+        assert!(coordinator.cancel_unfinished().await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn report_record_outside_tracked_task_is_ignored() {
+        report_record(1);
+        let record = Arc::new(OnceLock::new());
+        CURRENT_RECORD
+            .scope(record.clone(), async { report_record(2) })
+            .await;
+        assert_eq!(record.get(), Some(&2));
+    }
+}
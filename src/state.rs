@@ -0,0 +1,149 @@
+use crate::adapters::{SenderRegistry, SiteMessageSender};
+use crate::config::{BatchConfig, NotifyConfig};
+use crate::kafka::NotificationHandlerContext;
+use crate::services::{
+    DeadLetterService, FallbackOrchestrator, IdempotencyService, IdempotencyStore,
+    MySqlIdempotencyStore, NotificationRecordService, PoisonMessageService, RateLimiter,
+    RedisIdempotencyStore, ResultEventService, SiteMessageService, TemplateService,
+};
+use crate::shutdown::ShutdownCoordinator;
+use fbc_starter::AppState as FbcAppState;
+use sqlxplus::DbPool;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::Notify;
+
+/// 应用状态
+/// 由 HTTP 路由共享，Kafka 处理器共享其中的通知上下文
+#[derive(Clone)]
+pub struct AppState {
+    /// 通知处理器上下文（发送器注册表）
+    pub context: Arc<NotificationHandlerContext>,
+    /// 降级编排（在处理器上下文之上执行降级链）
+    pub orchestrator: Arc<FallbackOrchestrator>,
+    /// 站内消息服务（未配置数据库时为 None）
+    pub site_message_service: Option<Arc<SiteMessageService>>,
+    /// 通知模板服务（未配置数据库时为 None）
+    pub template_service: Option<Arc<TemplateService>>,
+    /// 发送记录服务（未配置数据库时为 None）
+    pub record_service: Option<Arc<NotificationRecordService>>,
+    /// 死信服务
+    pub dead_letter_service: Arc<DeadLetterService>,
+    /// 毒消息服务
+    pub poison_service: Arc<PoisonMessageService>,
+    /// 优雅关闭协调器
+    pub shutdown: Arc<ShutdownCoordinator>,
+    /// 唤醒定时发送调度器（异步发送的通知写入后立即发送）
+    pub scheduler_wakeup: Arc<Notify>,
+    /// 批量发送配置
+    pub batch: BatchConfig,
+}
+
+impl AppState {
+    /// 创建应用状态
+    ///
+    /// 发送器注册表和限流器在启动服务器前创建（配置错误时启动失败），
+    /// 此处补充注册依赖数据库的站内消息发送器
+    ///
+    /// # 参数
+    /// - `fbc`: fbc-starter 应用状态（提供数据库连接池等）
+    /// - `config`: 通知服务配置
+    /// - `registry`: 已配置渠道的发送器注册表
+    /// - `rate_limiter`: 发送限流器
+    pub fn new(
+        fbc: &FbcAppState,
+        config: &NotifyConfig,
+        mut registry: SenderRegistry,
+        rate_limiter: RateLimiter,
+    ) -> Self {
+        // 创建失败时按未配置数据库处理（依赖数据库的功能不可用）
+        let db_pool = fbc.mysql.clone().and_then(|pool| {
+            DbPool::from_mysql_pool(pool)
+                .map(Arc::new)
+                .map_err(|e| tracing::error!("创建 DbPool 失败，数据库相关功能不可用: {}", e))
+                .ok()
+        });
+        let site_message_service = db_pool
+            .clone()
+            .map(|pool| Arc::new(SiteMessageService::new(pool)));
+        let template_service = db_pool
+            .clone()
+            .map(|pool| Arc::new(TemplateService::new(pool)));
+        let record_service = db_pool
+            .clone()
+            .map(|pool| Arc::new(NotificationRecordService::new(pool)));
+        // 幂等去重优先使用 Redis，未配置时使用数据库
+        let idempotency_store: Option<Arc<dyn IdempotencyStore>> = match (&fbc.redis, &db_pool) {
+            (Some(redis), _) => Some(Arc::new(RedisIdempotencyStore::new(redis.clone()))),
+            (None, Some(pool)) => Some(Arc::new(MySqlIdempotencyStore::new(pool.clone()))),
+            (None, None) => None,
+        };
+        let dead_letter_service = Arc::new(DeadLetterService::new(
+            db_pool,
+            fbc.message_producer.clone(),
+            config.notify.retry.dead_letter_topic.clone(),
+        ));
+
+        let poison_service = Arc::new(PoisonMessageService::new(
+            fbc.message_producer.clone(),
+            config.notify.kafka.poison_topic.clone(),
+        ));
+
+        if let Some(service) = &site_message_service {
+            registry.register(Arc::new(SiteMessageSender::new(service.clone())));
+        }
+
+        let mut context = NotificationHandlerContext::new(registry).with_events(Arc::new(
+            ResultEventService::new(
+                fbc.message_producer.clone(),
+                config.notify.kafka.result_topic.clone(),
+            ),
+        ));
+        if let Some(service) = &template_service {
+            context = context.with_templates(service.clone());
+        }
+        if let Some(service) = &record_service {
+            context = context.with_records(service.clone());
+        }
+        if rate_limiter.is_enabled() {
+            context = context.with_rate_limiter(
+                Arc::new(rate_limiter),
+                Duration::from_millis(config.notify.rate_limit.max_wait_ms),
+            );
+        }
+        if let Some(store) = idempotency_store {
+            context = context.with_idempotency(Arc::new(IdempotencyService::new(
+                store,
+                &config.notify.idempotency,
+            )));
+        }
+
+        let context = Arc::new(context);
+        let mut orchestrator = FallbackOrchestrator::new(context.clone());
+        if let Some(service) = &record_service {
+            orchestrator = orchestrator.with_records(service.clone());
+        }
+        if let Some(service) = &site_message_service {
+            orchestrator = orchestrator.with_site_messages(service.clone());
+        }
+
+        let shutdown = Arc::new(ShutdownCoordinator::new(
+            record_service.clone(),
+            fbc.message_producer.clone(),
+            &config.notify.shutdown,
+        ));
+
+        Self {
+            context,
+            orchestrator: Arc::new(orchestrator),
+            site_message_service,
+            template_service,
+            record_service,
+            dead_letter_service,
+            poison_service,
+            shutdown,
+            scheduler_wakeup: Arc::new(Notify::new()),
+            batch: config.notify.batch.clone(),
+        }
+    }
+}